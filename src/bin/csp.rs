@@ -0,0 +1,522 @@
+//! The `csp` command, for working with policies from the command line.
+
+#![deny(clippy::all)]
+#![deny(unsafe_code)]
+#![deny(clippy::cargo)]
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+
+use std::{
+  env,
+  fmt::Write,
+  fs,
+  io::{self, Read},
+  path::Path,
+  process::ExitCode,
+};
+
+use csp::{
+  CSP, DirectiveDiff, DirectiveKind, Disposition, Engine, HashAlgorithm, InlineHashes,
+  PageSources, SiteScan, Verdict, ViolationReport,
+};
+
+const USAGE: &str = "\
+Usage: csp <command> [arguments] [--json]
+
+Policies are read from an argument, a file with that name, or stdin when
+they are left out or `-`. Files are read from stdin when they are `-`.
+With --json, the output is JSON instead of text.
+
+Commands:
+  parse [POLICY]
+      Prints each directive of the policy on its own line.
+  lint [POLICY]
+      Lists weaknesses of the policy, and what browser engines ignore.
+      Fails if there are any.
+  diff OLD NEW
+      Lists the differences between two policies, and the changes that
+      make the new one looser. Fails if there are any.
+  check-url --directive NAME [--document URL] URL [POLICY]
+      Checks if the policy allows a request to the URL, which is subject to
+      the directive. 'self' only matches the document's origin, if given.
+      Fails if the request would be blocked.
+  hash [--algorithm sha256|sha384|sha512] [--html] FILE
+      Prints the hash source of the file's content, or with --html the
+      directives that allow the inline scripts and styles of an HTML file.
+  minimize [POLICY]
+      Prints a shorter policy that allows exactly the same things.
+  meta [POLICY]
+      Prints a <meta> element with the policy, without the directives that
+      browsers ignore there.
+  from-reports FILE
+      Generates a policy that allows what violation reports say was
+      blocked, other than inline code and eval.
+  generate DIRECTORY [--per-page]
+      Generates a policy for the HTML, CSS and JavaScript files of a static
+      site, or one for each page with --per-page.";
+
+/// The options that take a value.
+const OPTIONS: [&str; 3] = ["--algorithm", "--directive", "--document"];
+/// The options that don't.
+const FLAGS: [&str; 3] = ["--html", "--json", "--per-page"];
+
+fn main() -> ExitCode {
+  let arguments: Vec<String> = env::args().skip(1).collect();
+  let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+  let result = match arguments.split_first() {
+    Some((&("help" | "-h" | "--help"), _)) => {
+      println!("{USAGE}");
+      return ExitCode::SUCCESS;
+    }
+    Some((command, arguments)) => {
+      Arguments::parse(arguments).and_then(|arguments| run(command, &arguments))
+    }
+    None => Err(Error::Usage),
+  };
+
+  match result {
+    Ok(Output { text, success }) => {
+      print!("{text}");
+      if success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+    }
+    Err(Error::Usage) => {
+      eprintln!("{USAGE}");
+      ExitCode::from(2)
+    }
+    Err(Error::Failed(message)) => {
+      eprintln!("csp: {message}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+enum Error {
+  /// The arguments were invalid.
+  Usage,
+  /// The command failed, with a message.
+  Failed(String),
+}
+
+/// What a command prints, and if it found nothing to complain about.
+struct Output {
+  text: String,
+  success: bool,
+}
+
+impl Output {
+  const fn new(text: String) -> Self {
+    Self { text, success: true }
+  }
+}
+
+struct Arguments<'a> {
+  positional: Vec<&'a str>,
+  options: Vec<(&'a str, &'a str)>,
+  flags: Vec<&'a str>,
+}
+
+impl<'a> Arguments<'a> {
+  fn parse(arguments: &[&'a str]) -> Result<Self, Error> {
+    let mut parsed =
+      Self { positional: Vec::new(), options: Vec::new(), flags: Vec::new() };
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+      if OPTIONS.contains(argument) {
+        parsed.options.push((argument, arguments.next().ok_or(Error::Usage)?));
+      } else if FLAGS.contains(argument) {
+        parsed.flags.push(argument);
+      } else if argument.starts_with("--") {
+        return Err(Error::Usage);
+      } else {
+        parsed.positional.push(argument);
+      }
+    }
+
+    Ok(parsed)
+  }
+
+  fn option(&self, name: &str) -> Option<&'a str> {
+    self.options.iter().find(|(option, _)| *option == name).map(|(_, value)| *value)
+  }
+
+  fn flag(&self, name: &str) -> bool {
+    self.flags.contains(&name)
+  }
+
+  /// The positional arguments, if there are between `min` and `max` of them.
+  fn positional(&self, min: usize, max: usize) -> Result<&[&'a str], Error> {
+    if (min..=max).contains(&self.positional.len()) {
+      Ok(&self.positional)
+    } else {
+      Err(Error::Usage)
+    }
+  }
+}
+
+fn run(command: &str, arguments: &Arguments<'_>) -> Result<Output, Error> {
+  let json = arguments.flag("--json");
+
+  match command {
+    "parse" => {
+      let input = read_policy(arguments.positional(0, 1)?.first().copied())?;
+      parse(&input, json)
+    }
+    "lint" => {
+      let input = read_policy(arguments.positional(0, 1)?.first().copied())?;
+      lint(&input, json)
+    }
+    "diff" => {
+      let [old, new] = arguments.positional(2, 2)? else {
+        return Err(Error::Usage);
+      };
+      if *old == "-" && *new == "-" {
+        return Err(Error::Usage);
+      }
+      diff(&read_policy(Some(old))?, &read_policy(Some(new))?, json)
+    }
+    "check-url" => {
+      let directive = arguments.option("--directive").ok_or(Error::Usage)?;
+      let positional = arguments.positional(1, 2)?;
+      let input = read_policy(positional.get(1).copied())?;
+      check_url(&input, directive, positional[0], arguments.option("--document"), json)
+    }
+    "hash" => {
+      let [file] = arguments.positional(1, 1)? else {
+        return Err(Error::Usage);
+      };
+      let algorithm = arguments.option("--algorithm").unwrap_or("sha256");
+      hash(&read_file(file)?, algorithm, arguments.flag("--html"), json)
+    }
+    "minimize" => {
+      let input = read_policy(arguments.positional(0, 1)?.first().copied())?;
+      minimize(&input, json)
+    }
+    "meta" => {
+      let input = read_policy(arguments.positional(0, 1)?.first().copied())?;
+      meta(&input, json)
+    }
+    "from-reports" => {
+      let [file] = arguments.positional(1, 1)? else {
+        return Err(Error::Usage);
+      };
+      from_reports(&read_file(file)?, json)
+    }
+    "generate" => {
+      let [directory] = arguments.positional(1, 1)? else {
+        return Err(Error::Usage);
+      };
+      generate(directory, arguments.flag("--per-page"), json)
+    }
+    _ => Err(Error::Usage),
+  }
+}
+
+/// Reads a policy from an argument, a file with its name, or stdin.
+fn read_policy(argument: Option<&str>) -> Result<String, Error> {
+  match argument {
+    Some(path) if path != "-" && Path::new(path).is_file() => read_file(path),
+    Some(policy) if policy != "-" => Ok(policy.to_owned()),
+    _ => read_file("-"),
+  }
+}
+
+/// Reads a file, or stdin if the path is `-`.
+fn read_file(path: &str) -> Result<String, Error> {
+  let failed = |error: io::Error| Error::Failed(format!("{path}: {error}"));
+
+  if path == "-" {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).map_err(failed)?;
+    Ok(input)
+  } else {
+    fs::read_to_string(path).map_err(failed)
+  }
+}
+
+/// Parses a policy given to a command, failing on unknown directives so that a
+/// misspelled one is not silently left out.
+fn parse_policy(input: &str) -> Result<CSP<'_>, Error> {
+  CSP::parse_strict(input.trim())
+    .map_err(|error| Error::Failed(format!("invalid policy: {error}")))
+}
+
+fn parse(input: &str, json: bool) -> Result<Output, Error> {
+  let csp = parse_policy(input)?;
+
+  let mut output = String::new();
+  if json {
+    let directives = csp.into_iter().map(|directive| {
+      let directive = directive.to_string();
+      let mut values = directive.split_whitespace();
+      let name = values.next().unwrap_or_default();
+      format!("{}: {}", json_string(name), json_array(values.map(json_string)))
+    });
+    let _ = writeln!(output, "{{{}}}", directives.collect::<Vec<_>>().join(", "));
+  } else {
+    for directive in &csp {
+      let _ = writeln!(output, "{directive}");
+    }
+  }
+  Ok(Output::new(output))
+}
+
+fn lint(input: &str, json: bool) -> Result<Output, Error> {
+  let csp = parse_policy(input)?;
+
+  let mut warnings: Vec<String> = csp.lint().iter().map(ToString::to_string).collect();
+  // The same parts are often ignored by several engines.
+  let mut ignored: Vec<(String, Vec<Engine>)> = Vec::new();
+  for engine in Engine::ALL {
+    for part in csp.ignored_by(engine) {
+      let part = part.to_string();
+      match ignored.iter_mut().find(|(existing, _)| *existing == part) {
+        Some((_, engines)) => engines.push(engine),
+        None => ignored.push((part, vec![engine])),
+      }
+    }
+  }
+  warnings.extend(ignored.into_iter().map(|(part, engines)| {
+    let engines: Vec<_> = engines.iter().map(ToString::to_string).collect();
+    format!("{part} in {}", engines.join(", "))
+  }));
+
+  let text = if json {
+    format!("{}\n", json_array(warnings.iter().map(|warning| json_string(warning))))
+  } else {
+    lines(&warnings)
+  };
+  Ok(Output { text, success: warnings.is_empty() })
+}
+
+fn diff(old: &str, new: &str, json: bool) -> Result<Output, Error> {
+  let (old, new) = (parse_policy(old)?, parse_policy(new)?);
+  let diff = csp::diff(&old, &new);
+  let comparison = csp::compare(&old, &new);
+  let loosenings: Vec<_> =
+    comparison.loosenings.iter().map(ToString::to_string).collect();
+
+  let mut output = String::new();
+  if json {
+    let changes = diff.0.iter().map(|change| match change {
+      DirectiveDiff::Added(directive) => {
+        format!("{{\"added\": {}}}", json_string(&directive.to_string()))
+      }
+      DirectiveDiff::Removed(directive) => {
+        format!("{{\"removed\": {}}}", json_string(&directive.to_string()))
+      }
+      DirectiveDiff::Changed { directive, added, removed } => {
+        let items = |items: &[csp::Item<'_>]| {
+          json_array(items.iter().map(|item| json_string(&item.to_string())))
+        };
+        format!(
+          "{{\"changed\": {}, \"added\": {}, \"removed\": {}}}",
+          json_string(directive.name()),
+          items(added),
+          items(removed)
+        )
+      }
+    });
+    let changes: Vec<_> = changes.collect();
+    let list = |changes: &[csp::Change<'_>]| {
+      json_array(changes.iter().map(|change| json_string(&change.to_string())))
+    };
+
+    let _ = writeln!(
+      output,
+      "{{\"diff\": [{}], \"loosenings\": {}, \"tightenings\": {}}}",
+      changes.join(", "),
+      list(&comparison.loosenings),
+      list(&comparison.tightenings)
+    );
+  } else {
+    let _ = write!(output, "{diff}");
+    if !loosenings.is_empty() {
+      let _ = writeln!(output, "\nLoosenings:");
+      for loosening in &loosenings {
+        let _ = writeln!(output, "  {loosening}");
+      }
+    }
+  }
+
+  Ok(Output { text: output, success: loosenings.is_empty() })
+}
+
+fn check_url(
+  input: &str,
+  directive: &str,
+  url: &str,
+  document: Option<&str>,
+  json: bool,
+) -> Result<Output, Error> {
+  let csp = parse_policy(input)?;
+  let kind = DirectiveKind::from_name(directive)
+    .ok_or_else(|| Error::Failed(format!("unknown directive `{directive}`")))?;
+
+  // Without a document, 'self' has to match nothing, so it gets a host
+  // that is reserved for being invalid.
+  let scheme = url.split_once(':').map_or("https", |(scheme, _)| scheme);
+  let fallback = format!("{scheme}://document.invalid/");
+  let verdict = csp
+    .check_url(kind, url, document.unwrap_or(&fallback))
+    .ok_or_else(|| Error::Failed("URLs have to be absolute".to_owned()))?;
+
+  let text = if json {
+    let (verdict_name, directive) = match verdict {
+      Verdict::Allowed => ("allowed", None),
+      Verdict::Blocked(kind) => ("blocked", Some(kind)),
+      Verdict::Unverifiable(kind) => ("unverifiable", Some(kind)),
+    };
+    let directive =
+      directive.map_or_else(|| "null".to_owned(), |kind| json_string(kind.name()));
+    format!("{{\"verdict\": \"{verdict_name}\", \"directive\": {directive}}}\n")
+  } else {
+    format!("{verdict}\n")
+  };
+  Ok(Output { text, success: verdict == Verdict::Allowed })
+}
+
+fn hash(content: &str, algorithm: &str, html: bool, json: bool) -> Result<Output, Error> {
+  let output = if html {
+    if algorithm != "sha256" {
+      return Err(Error::Failed("HTML files are hashed with sha256".to_owned()));
+    }
+    let hashes = InlineHashes::scan_with_attributes(content);
+    let directives: Vec<_> =
+      hashes.directives().iter().map(ToString::to_string).collect();
+
+    if json {
+      format!(
+        "{}\n",
+        json_array(directives.iter().map(|directive| json_string(directive)))
+      )
+    } else {
+      lines(&directives)
+    }
+  } else {
+    let algorithm = HashAlgorithm::from_name(algorithm)
+      .ok_or_else(|| Error::Failed(format!("unknown hash algorithm `{algorithm}`")))?;
+    let source = format!("'{algorithm}-{}'", algorithm.digest(content.as_bytes()));
+
+    if json { format!("{}\n", json_string(&source)) } else { format!("{source}\n") }
+  };
+
+  Ok(Output::new(output))
+}
+
+fn minimize(input: &str, json: bool) -> Result<Output, Error> {
+  let minimized = parse_policy(input)?.minimize();
+
+  Ok(Output::new(if json {
+    format!(
+      "{{\"policy\": {}, \"original_len\": {}, \"minimized_len\": {}}}\n",
+      json_string(&minimized.policy.to_string()),
+      minimized.original_len,
+      minimized.minimized_len
+    )
+  } else {
+    format!("{}\n", minimized.policy)
+  }))
+}
+
+fn meta(input: &str, json: bool) -> Result<Output, Error> {
+  let meta = parse_policy(input)?
+    .to_meta_tag(Disposition::Enforce)
+    .map_err(|error| Error::Failed(error.to_string()))?;
+
+  Ok(Output::new(if json {
+    format!(
+      "{{\"html\": {}, \"stripped\": {}}}\n",
+      json_string(&meta.html),
+      json_array(meta.stripped.iter().map(|kind| json_string(kind.name())))
+    )
+  } else {
+    for kind in &meta.stripped {
+      eprintln!("csp: left out {kind}, as browsers ignore it in <meta> elements");
+    }
+    format!("{meta}\n")
+  }))
+}
+
+fn from_reports(input: &str, json: bool) -> Result<Output, Error> {
+  let reports =
+    ViolationReport::parse(input).map_err(|error| Error::Failed(error.to_string()))?;
+
+  let mut sources = PageSources::default();
+  let skipped = reports.iter().filter(|report| !sources.add_report(report)).count();
+  let policy = sources.policy();
+
+  Ok(Output::new(if json {
+    format!(
+      "{{\"policy\": {}, \"skipped\": {skipped}}}\n",
+      json_string(&policy.to_string())
+    )
+  } else {
+    if skipped > 0 {
+      eprintln!("csp: skipped {skipped} reports of inline code, eval or frame-ancestors");
+    }
+    format!("{policy}\n")
+  }))
+}
+
+fn generate(directory: &str, per_page: bool, json: bool) -> Result<Output, Error> {
+  let scan = SiteScan::scan(directory)
+    .map_err(|error| Error::Failed(format!("{directory}: {error}")))?;
+
+  let mut output = String::new();
+  match (per_page, json) {
+    (true, true) => {
+      let pages = scan.pages.iter().map(|(path, page)| {
+        let path = path.to_string_lossy();
+        format!("{}: {}", json_string(&path), json_string(&page.policy().to_string()))
+      });
+      let _ = writeln!(output, "{{{}}}", pages.collect::<Vec<_>>().join(", "));
+    }
+    (true, false) => {
+      for (path, page) in &scan.pages {
+        let _ = writeln!(output, "{}: {}", path.display(), page.policy());
+      }
+    }
+    (false, true) => {
+      let _ = writeln!(output, "{}", json_string(&scan.site().policy().to_string()));
+    }
+    (false, false) => {
+      let _ = writeln!(output, "{}", scan.site().policy());
+    }
+  }
+  Ok(Output::new(output))
+}
+
+/// Joins text with a newline after each line.
+fn lines(lines: &[String]) -> String {
+  let mut text = String::new();
+  for line in lines {
+    let _ = writeln!(text, "{line}");
+  }
+  text
+}
+
+fn json_string(string: &str) -> String {
+  let mut json = String::with_capacity(string.len() + 2);
+  json.push('"');
+  for char in string.chars() {
+    match char {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      char if char < ' ' => {
+        let _ = write!(json, "\\u{:04x}", u32::from(char));
+      }
+      char => json.push(char),
+    }
+  }
+  json.push('"');
+  json
+}
+
+fn json_array(values: impl Iterator<Item = String>) -> String {
+  format!("[{}]", values.collect::<Vec<_>>().join(", "))
+}
@@ -0,0 +1,227 @@
+//! Sources that common third-party integrations need, as policy fragments
+//! that can be merged into a base policy.
+//!
+//! The bundles are loaded from a data file that is embedded into the crate,
+//! `src/bundles.txt`, which documents its own format. Additions are welcome,
+//! as long as they link to the vendor's documentation of their sources.
+//! Applications can also [`parse`] their own files in the same format.
+//!
+//! # Example usage
+//! ```rust
+//! use csp::{CSP, bundles};
+//!
+//! let mut csp = CSP::parse("default-src 'self'; object-src 'none'").unwrap();
+//! let conflicts = bundles::get("youtube").unwrap().merge_into(&mut csp);
+//!
+//! assert!(conflicts.is_empty());
+//!
+//! assert_eq!(
+//!   csp.to_string(),
+//!   "default-src 'self'; object-src 'none'; \
+//!    frame-src 'self' https://www.youtube.com https://www.youtube-nocookie.com; \
+//!    img-src 'self' https://i.ytimg.com"
+//! );
+//! ```
+
+use std::{error, fmt, sync::OnceLock};
+
+use crate::{
+  CSP, ParseError,
+  registry::{MergeConflict, merge},
+};
+
+const DATA: &str = include_str!("bundles.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The sources that a third-party integration needs.
+pub struct Bundle<'a> {
+  /// The name of the integration, such as `stripe`.
+  pub name: &'a str,
+  /// The version of the bundle, which changes whenever its sources do.
+  pub version: &'a str,
+  /// A link to the vendor's documentation of the sources.
+  pub docs: Option<&'a str>,
+  /// The directives with the sources that the integration needs.
+  pub policy: CSP<'a>,
+}
+
+impl<'a> Bundle<'a> {
+  /// Merges the bundle into a policy, adding its sources to what each of its
+  /// directives allowed in the policy, including through fallbacks such as
+  /// `default-src`.
+  ///
+  /// Returns the sources of the policy that browsers ignore once the bundle
+  /// is merged, such as `'unsafe-inline'` when the bundle has a nonce, so
+  /// they don't allow what they used to.
+  pub fn merge_into(&self, csp: &mut CSP<'a>) -> Vec<MergeConflict<'a>> {
+    merge(csp, &self.policy)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a bundle file could not be parsed. Lines are counted from 1.
+pub enum BundleError {
+  /// A line comes before the first `[name]` header.
+  OutsideBundle(usize),
+  /// A bundle has no `version`.
+  MissingVersion(String),
+  /// Two bundles have the same name.
+  DuplicateBundle(String),
+  /// A directive line is not valid.
+  InvalidPolicy {
+    /// The line of the directive.
+    line: usize,
+    /// Why it is not valid.
+    error: ParseError,
+  },
+}
+
+impl fmt::Display for BundleError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::OutsideBundle(line) => write!(fmt, "line {line} is not in a bundle"),
+      Self::MissingVersion(name) => write!(fmt, "bundle `{name}` has no version"),
+      Self::DuplicateBundle(name) => write!(fmt, "bundle `{name}` is defined twice"),
+      Self::InvalidPolicy { line, error } => write!(fmt, "line {line}: {error}"),
+    }
+  }
+}
+
+impl error::Error for BundleError {}
+
+/// Parses bundles from a file in the same format as the embedded one.
+///
+/// # Errors
+/// If a line is outside of a bundle, a bundle has no version or is defined
+/// twice, or a directive is unknown or not valid.
+pub fn parse(data: &str) -> Result<Vec<Bundle<'_>>, BundleError> {
+  let mut bundles: Vec<Bundle<'_>> = Vec::new();
+
+  for (index, line) in data.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+      finish(bundles.last())?;
+      if bundles.iter().any(|bundle| bundle.name == name) {
+        return Err(BundleError::DuplicateBundle(name.to_owned()));
+      }
+
+      bundles.push(Bundle { name, version: "", docs: None, policy: CSP::new() });
+      continue;
+    }
+
+    let Some(bundle) = bundles.last_mut() else {
+      return Err(BundleError::OutsideBundle(index + 1));
+    };
+
+    match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+      Some(("version", version)) => bundle.version = version,
+      Some(("docs", docs)) => bundle.docs = Some(docs),
+      _ => {
+        let directives = CSP::parse_strict(line)
+          .map_err(|error| BundleError::InvalidPolicy { line: index + 1, error })?;
+        for directive in directives {
+          bundle.policy.push_borrowed(directive);
+        }
+      }
+    }
+  }
+
+  finish(bundles.last())?;
+  Ok(bundles)
+}
+
+fn finish(bundle: Option<&Bundle<'_>>) -> Result<(), BundleError> {
+  match bundle {
+    Some(bundle) if bundle.version.is_empty() => {
+      Err(BundleError::MissingVersion(bundle.name.to_owned()))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Every bundle in the embedded data file.
+///
+/// # Panics
+/// If the embedded data file is not valid, which the tests check for.
+pub fn all() -> &'static [Bundle<'static>] {
+  static BUNDLES: OnceLock<Vec<Bundle<'static>>> = OnceLock::new();
+
+  BUNDLES.get_or_init(|| match parse(DATA) {
+    Ok(bundles) => bundles,
+    Err(error) => panic!("invalid embedded bundles: {error}"),
+  })
+}
+
+#[must_use]
+/// Gets the embedded bundle with the given name.
+pub fn get(name: &str) -> Option<&'static Bundle<'static>> {
+  all().iter().find(|bundle| bundle.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Source;
+
+  #[test]
+  fn embedded() {
+    let bundles = parse(DATA).unwrap();
+    assert_eq!(bundles.len(), all().len());
+
+    for bundle in bundles {
+      assert!(
+        bundle.docs.is_some_and(|docs| docs.starts_with("https://")),
+        "{}",
+        bundle.name
+      );
+
+      for directive in &bundle.policy {
+        // Only secure hosts, so that merging a bundle never allows much more
+        // than the integration itself.
+        let is_vetted = |source: &Source<'_>| match source {
+          Source::Host(host) => {
+            host.starts_with("https://") || host.starts_with("wss://")
+          }
+          Source::Scheme(scheme) => {
+            directive.kind() == crate::DirectiveKind::ImgSrc
+              && matches!(*scheme, "blob" | "data")
+          }
+          _ => false,
+        };
+        assert!(
+          directive.sources().unwrap().0.iter().all(is_vetted),
+          "{}: {directive}",
+          bundle.name
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn errors() {
+    assert_eq!(parse("img-src *"), Err(BundleError::OutsideBundle(1)));
+    assert_eq!(
+      parse("[a]\nversion = 1\n[a]\nversion = 2"),
+      Err(BundleError::DuplicateBundle("a".to_owned()))
+    );
+    assert_eq!(
+      parse("[a]\nversion = 1\n[b]\nimg-src *"),
+      Err(BundleError::MissingVersion("b".to_owned()))
+    );
+    assert_eq!(
+      parse("# comment\n[a]\nversion = 1\nimg-src 'self").unwrap_err().to_string(),
+      "line 4: invalid value `'self` for `img-src`"
+    );
+    assert_eq!(
+      parse("[a]\nversion = 1\nscirpt-src https://example.org"),
+      Err(BundleError::InvalidPolicy {
+        line: 3,
+        error: ParseError::UnknownDirective("scirpt-src".to_owned())
+      })
+    );
+  }
+}
@@ -0,0 +1,452 @@
+//! Semantic comparison of two policies, to catch changes that weaken them.
+
+use std::fmt;
+
+use crate::{CSP, Directive, SandboxAllow, Source, Sources, SriFor, kind::DirectiveKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A single value of a directive.
+pub enum Item<'a> {
+  /// A source of a directive that takes [`Sources`].
+  Source(Source<'a>),
+  /// An allowance of the `sandbox` directive.
+  SandboxAllow(SandboxAllow),
+  /// A MIME type of the `plugin-types` directive.
+  Plugin((&'a str, &'a str)),
+  /// Any other value, such as a `trusted-types` policy name.
+  Token(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What happened to a directive between two policies.
+pub enum ChangeKind<'a> {
+  /// The directive was added.
+  DirectiveAdded {
+    /// The sources it has that what applied in its place didn't.
+    added: Vec<Item<'a>>,
+    /// The sources it doesn't have that what applied in its place did.
+    removed: Vec<Item<'a>>,
+  },
+  /// The directive was removed.
+  DirectiveRemoved {
+    /// The directive that now applies in its place, if any.
+    fallback: Option<DirectiveKind>,
+    /// The sources that the fallback has and the directive didn't.
+    added: Vec<Item<'a>>,
+    /// The sources that the directive had and the fallback doesn't.
+    removed: Vec<Item<'a>>,
+  },
+  /// A value was added to the directive.
+  Added(Item<'a>),
+  /// A value was removed from the directive.
+  Removed(Item<'a>),
+}
+
+impl<'a> ChangeKind<'a> {
+  #[must_use]
+  /// The values that the change adds, including those that a fallback adds
+  /// in place of a removed directive.
+  pub fn added(&self) -> &[Item<'a>] {
+    match self {
+      Self::DirectiveAdded { added, .. } | Self::DirectiveRemoved { added, .. } => added,
+      Self::Added(item) => std::slice::from_ref(item),
+      Self::Removed(_) => &[],
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A change to a single directive.
+pub struct Change<'a> {
+  /// The directive that changed.
+  pub directive: DirectiveKind,
+  /// What changed about it.
+  pub kind: ChangeKind<'a>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The result of [`compare`].
+pub struct Comparison<'a> {
+  /// Changes that allow more than the old policy did.
+  pub loosenings: Vec<Change<'a>>,
+  /// Changes that allow less than the old policy did.
+  pub tightenings: Vec<Change<'a>>,
+}
+
+/// Compares two policies, listing the changes that make the new policy looser
+/// or stricter than the old one.
+///
+/// Directives are compared in the form they are written in, except when one
+/// is removed or added: then it is compared against what effectively applied
+/// in its place, such as `default-src`, and listed as a single change along
+/// with the sources that differ. Changes that make no difference, such as
+/// reordering sources, adding a host that is already covered by a scheme
+/// or changing where reports are sent, are not listed.
+///
+/// # Example usage
+/// ```rust
+/// use csp::CSP;
+///
+/// let old = CSP::parse("default-src 'self'; object-src 'none'").unwrap();
+/// let new = CSP::parse("default-src 'self' 'unsafe-inline'").unwrap();
+///
+/// let comparison = csp::compare(&old, &new);
+///
+/// let loosenings: Vec<_> = comparison.loosenings.iter().map(ToString::to_string).collect();
+/// assert_eq!(
+///   loosenings,
+///   [
+///     "default-src: added 'unsafe-inline'",
+///     "object-src: removed, falls back to default-src, adding 'self' 'unsafe-inline'",
+///   ]
+/// );
+/// assert!(comparison.tightenings.is_empty());
+/// ```
+#[must_use]
+pub fn compare<'a>(old: &CSP<'a>, new: &CSP<'a>) -> Comparison<'a> {
+  let mut comparison = Comparison::default();
+
+  for kind in DirectiveKind::ALL {
+    match (old.get(kind), new.get(kind)) {
+      (None, None) => {}
+      _ if kind.has_sources() => comparison.sources(old, new, kind),
+      (Some(old), Some(new)) => comparison.values(old, new),
+      (Some(_), None) => {
+        if !matches!(kind, DirectiveKind::ReportTo | DirectiveKind::ReportUri) {
+          let change = ChangeKind::DirectiveRemoved {
+            fallback: None,
+            added: vec![],
+            removed: vec![],
+          };
+          comparison.loosen(kind, change);
+        }
+      }
+      (None, Some(_)) => {
+        if !matches!(kind, DirectiveKind::ReportTo | DirectiveKind::ReportUri) {
+          let change = ChangeKind::DirectiveAdded { added: vec![], removed: vec![] };
+          comparison.tighten(kind, change);
+        }
+      }
+    }
+  }
+
+  comparison
+}
+
+impl<'a> Comparison<'a> {
+  /// If the new policy allows nothing that the old one didn't.
+  #[must_use]
+  pub const fn is_not_weaker(&self) -> bool {
+    self.loosenings.is_empty()
+  }
+
+  fn loosen(&mut self, kind: DirectiveKind, change: ChangeKind<'a>) {
+    self.loosenings.push(Change { directive: kind, kind: change });
+  }
+
+  fn tighten(&mut self, kind: DirectiveKind, change: ChangeKind<'a>) {
+    self.tightenings.push(Change { directive: kind, kind: change });
+  }
+
+  fn sources(&mut self, old: &CSP<'a>, new: &CSP<'a>, kind: DirectiveKind) {
+    let is_script = kind.is_script();
+    let (old_sources, new_sources) =
+      (old.effective_sources(kind), new.effective_sources(kind));
+
+    let (added, removed) = match (old_sources, new_sources) {
+      (Some(old_sources), Some(new_sources)) => {
+        let (mut added, mut removed) = difference(&old_sources.0, &new_sources.0);
+        added.retain(|source| widens(new_sources, old_sources, source, is_script));
+        removed.retain(|source| widens(old_sources, new_sources, source, is_script));
+        (added, removed)
+      }
+      _ => (vec![], vec![]),
+    };
+
+    // An added or removed directive is a single change, with the sources that
+    // differ from what applied in its place.
+    if old.get(kind).is_none() || new.get(kind).is_none() {
+      let added = added.into_iter().map(Item::Source).collect();
+      let removed = removed.into_iter().map(Item::Source).collect();
+      let change = if new.get(kind).is_none() {
+        ChangeKind::DirectiveRemoved { fallback: fallback_of(new, kind), added, removed }
+      } else {
+        ChangeKind::DirectiveAdded { added, removed }
+      };
+
+      match (old_sources, new_sources) {
+        (_, None) => self.loosen(kind, change),
+        (None, Some(_)) => self.tighten(kind, change),
+        (Some(old_sources), Some(new_sources)) => {
+          match (
+            new_sources.is_subsumed_by(old_sources, is_script),
+            old_sources.is_subsumed_by(new_sources, is_script),
+          ) {
+            (false, _) => self.loosen(kind, change),
+            (true, false) => self.tighten(kind, change),
+            (true, true) => {}
+          }
+        }
+      }
+      return;
+    }
+
+    let (Some(old_sources), Some(new_sources)) = (old_sources, new_sources) else {
+      return;
+    };
+    let (old_inline, new_inline) =
+      (old_sources.allows_inline(is_script), new_sources.allows_inline(is_script));
+
+    for source in added {
+      let change = ChangeKind::Added(Item::Source(source.clone()));
+      match source {
+        // Nonces and hashes only allow a part of what `'unsafe-inline'` did,
+        // and turn it off.
+        Source::Nonce(_) | Source::Hash(_) if old_inline => {
+          if !new_inline {
+            self.tighten(kind, change);
+          }
+        }
+        _ => self.loosen(kind, change),
+      }
+    }
+    for source in removed {
+      // Removing a source can turn back on what it made browsers ignore.
+      let loosens = match source {
+        Source::Nonce(_) | Source::Hash(_) => new_inline && !old_inline,
+        Source::StrictDynamic => {
+          (new_inline && !old_inline) || new_sources.expressions().next().is_some()
+        }
+        _ => false,
+      };
+      let change = ChangeKind::Removed(Item::Source(source));
+      if loosens { self.loosen(kind, change) } else { self.tighten(kind, change) }
+    }
+  }
+
+  fn values(&mut self, old: &Directive<'a>, new: &Directive<'a>) {
+    let kind = old.kind();
+    let (old, new) = (old.items(), new.items());
+    let (added, removed) = difference(&old, &new);
+
+    // Requiring integrity for more resource types is stricter, while the
+    // other values allow more the more of them there are.
+    let (added_loosens, removed_loosens) = match kind {
+      DirectiveKind::ReportTo | DirectiveKind::ReportUri => return,
+      DirectiveKind::RequireSriFor => (false, true),
+      _ => (true, false),
+    };
+
+    for item in added {
+      let change = ChangeKind::Added(item);
+      if added_loosens { self.loosen(kind, change) } else { self.tighten(kind, change) }
+    }
+    for item in removed {
+      let change = ChangeKind::Removed(item);
+      if removed_loosens { self.loosen(kind, change) } else { self.tighten(kind, change) }
+    }
+  }
+}
+
+impl<'a> Directive<'a> {
+  /// The values of the directive as a list of [`Item`]s.
+  pub(crate) fn items(&self) -> Vec<Item<'a>> {
+    match self {
+      Self::PluginTypes(plugins) => plugins.0.iter().copied().map(Item::Plugin).collect(),
+      Self::ReportTo(group) => vec![Item::Token(group)],
+      Self::ReportUri(uris) => uris.0.iter().copied().map(Item::Token).collect(),
+      Self::RequireSriFor(for_) => match for_ {
+        SriFor::Script => vec![Item::Token("script")],
+        SriFor::Style => vec![Item::Token("style")],
+        SriFor::ScriptStyle => vec![Item::Token("script"), Item::Token("style")],
+      },
+      Self::Sandbox(list) => list.0.iter().cloned().map(Item::SandboxAllow).collect(),
+      Self::TrustedTypes(names) => names.iter().copied().map(Item::Token).collect(),
+      Self::BlockAllMixedContent | Self::UpgradeInsecureRequests => vec![],
+      _ => self
+        .sources()
+        .map(|sources| sources.0.iter().cloned().map(Item::Source).collect())
+        .unwrap_or_default(),
+    }
+  }
+}
+
+/// The directive that effectively applies instead of a missing one.
+fn fallback_of(csp: &CSP<'_>, kind: DirectiveKind) -> Option<DirectiveKind> {
+  kind.fallback_list().iter().copied().find(|kind| csp.get(*kind).is_some())
+}
+
+/// If `source` from `with` makes it allow more than `without`.
+fn widens(
+  with: &Sources<'_>,
+  without: &Sources<'_>,
+  source: &Source<'_>,
+  is_script: bool,
+) -> bool {
+  match source {
+    Source::ReportSample => false,
+    Source::Host(_) | Source::Scheme(_) | Source::Self_ => {
+      let ignored =
+        with.is_strict_dynamic(is_script) && without.is_strict_dynamic(is_script);
+      let covered =
+        without.expressions().any(|expression| source.is_subsumed_by(expression));
+
+      !ignored && !covered
+    }
+    _ => true,
+  }
+}
+
+/// The values that were added and removed between two lists, ignoring their
+/// order and duplicates.
+pub fn difference<T: PartialEq + Clone>(old: &[T], new: &[T]) -> (Vec<T>, Vec<T>) {
+  let unique = |items: &[T], others: &[T]| {
+    let mut unique: Vec<T> = Vec::new();
+    for item in items {
+      if !others.contains(item) && !unique.contains(item) {
+        unique.push(item.clone());
+      }
+    }
+    unique
+  };
+
+  (unique(new, old), unique(old, new))
+}
+
+impl fmt::Display for Item<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Source(source) => write!(fmt, "{source}"),
+      Self::SandboxAllow(allow) => write!(fmt, "{allow}"),
+      Self::Plugin((kind, subtype)) => write!(fmt, "{kind}/{subtype}"),
+      Self::Token(token) => write!(fmt, "{token}"),
+    }
+  }
+}
+
+impl fmt::Display for Change<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    let (added, removed) = match &self.kind {
+      ChangeKind::Added(item) => return write!(fmt, "{}: added {item}", self.directive),
+      ChangeKind::Removed(item) => {
+        return write!(fmt, "{}: removed {item}", self.directive);
+      }
+      ChangeKind::DirectiveAdded { added, removed } => {
+        write!(fmt, "{}: added", self.directive)?;
+        (added, removed)
+      }
+      ChangeKind::DirectiveRemoved { fallback, added, removed } => {
+        write!(fmt, "{}: removed", self.directive)?;
+        if let Some(fallback) = fallback {
+          write!(fmt, ", falls back to {fallback}")?;
+        }
+        (added, removed)
+      }
+    };
+
+    for (verb, items) in [("adding", added), ("removing", removed)] {
+      if !items.is_empty() {
+        write!(fmt, ", {verb}")?;
+        for item in items {
+          write!(fmt, " {item}")?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn compare_str(old: &'static str, new: &'static str) -> Comparison<'static> {
+    compare(&CSP::parse(old).unwrap(), &CSP::parse(new).unwrap())
+  }
+
+  fn describe(changes: &[Change<'_>]) -> Vec<String> {
+    changes.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn sources() {
+    let comparison = compare_str(
+      "script-src 'self' https://a.example.org; img-src https:",
+      "script-src https://b.example.org 'unsafe-eval' 'self'; img-src https: https://a.example.org",
+    );
+
+    assert_eq!(
+      describe(&comparison.loosenings),
+      ["script-src: added https://b.example.org", "script-src: added 'unsafe-eval'"]
+    );
+    assert_eq!(
+      describe(&comparison.tightenings),
+      ["script-src: removed https://a.example.org"]
+    );
+  }
+
+  #[test]
+  fn fallbacks() {
+    let comparison = compare_str(
+      "default-src 'self'; object-src 'none'; img-src 'self'",
+      "default-src 'self'; frame-src 'none'",
+    );
+
+    assert_eq!(
+      describe(&comparison.loosenings),
+      ["object-src: removed, falls back to default-src, adding 'self'"]
+    );
+    assert_eq!(describe(&comparison.tightenings), ["frame-src: added, removing 'self'"]);
+    assert!(!comparison.is_not_weaker());
+  }
+
+  #[test]
+  fn other_directives() {
+    let comparison = compare_str(
+      "sandbox allow-forms; upgrade-insecure-requests; report-to a",
+      "sandbox allow-scripts; require-sri-for script; report-to b",
+    );
+
+    assert_eq!(
+      describe(&comparison.loosenings),
+      ["sandbox: added allow-scripts", "upgrade-insecure-requests: removed"]
+    );
+    assert_eq!(
+      describe(&comparison.tightenings),
+      ["require-sri-for: added", "sandbox: removed allow-forms"]
+    );
+    assert!(
+      compare_str("img-src 'self' https:", "img-src https: 'self'").is_not_weaker()
+    );
+  }
+
+  #[test]
+  fn disabled_sources() {
+    let comparison =
+      compare_str("script-src 'nonce-a' 'unsafe-inline'", "script-src 'unsafe-inline'");
+    assert_eq!(describe(&comparison.loosenings), ["script-src: removed 'nonce-a'"]);
+    assert!(comparison.tightenings.is_empty());
+    assert!(!comparison.is_not_weaker());
+
+    let comparison = compare_str(
+      "script-src 'nonce-a' 'strict-dynamic' https:",
+      "script-src 'nonce-a' https:",
+    );
+    assert_eq!(
+      describe(&comparison.loosenings),
+      ["script-src: removed 'strict-dynamic'"]
+    );
+    assert!(!comparison.is_not_weaker());
+
+    let comparison =
+      compare_str("style-src 'unsafe-inline'", "style-src 'unsafe-inline' 'nonce-a'");
+    assert!(comparison.is_not_weaker());
+    assert_eq!(describe(&comparison.tightenings), ["style-src: added 'nonce-a'"]);
+
+    let comparison = compare_str(
+      "script-src 'nonce-a' 'nonce-b' 'unsafe-inline'",
+      "script-src 'nonce-a' 'unsafe-inline'",
+    );
+    assert!(comparison.is_not_weaker());
+  }
+}
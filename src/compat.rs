@@ -0,0 +1,392 @@
+//! How browsers that implement older levels of CSP interpret a policy, and
+//! the fallbacks that keep strict policies working in them.
+
+use std::fmt;
+
+use crate::{CSP, Directive, DirectiveKind, Source};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A level of the CSP specification, as implemented by a browser.
+pub enum Level {
+  /// CSP Level 1, with source lists of hosts, schemes, `'self'`,
+  /// `'unsafe-inline'` and `'unsafe-eval'`.
+  One,
+  /// CSP Level 2, which added nonces, hashes and directives such as
+  /// `base-uri` and `frame-ancestors`.
+  Two,
+  /// CSP Level 3, which added `'strict-dynamic'` and the `-elem` and `-attr`
+  /// directives, among others.
+  Three,
+}
+
+impl Level {
+  /// Every level, from the oldest.
+  pub const ALL: [Self; 3] = [Self::One, Self::Two, Self::Three];
+}
+
+impl fmt::Display for Level {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::One => write!(fmt, "CSP Level 1"),
+      Self::Two => write!(fmt, "CSP Level 2"),
+      Self::Three => write!(fmt, "CSP Level 3"),
+    }
+  }
+}
+
+impl DirectiveKind {
+  #[must_use]
+  /// The level of CSP that introduced the directive. Directives from other
+  /// specifications, such as `upgrade-insecure-requests`, count as level 3.
+  pub const fn level(self) -> Level {
+    match self {
+      Self::ConnectSrc
+      | Self::DefaultSrc
+      | Self::FontSrc
+      | Self::FrameSrc
+      | Self::ImgSrc
+      | Self::MediaSrc
+      | Self::ObjectSrc
+      | Self::ReportUri
+      | Self::Sandbox
+      | Self::ScriptSrc
+      | Self::StyleSrc => Level::One,
+      Self::BaseUri
+      | Self::ChildSrc
+      | Self::FormAction
+      | Self::FrameAncestors
+      | Self::PluginTypes => Level::Two,
+      Self::BlockAllMixedContent
+      | Self::ManifestSrc
+      | Self::NavigateTo
+      | Self::PrefetchSrc
+      | Self::ReportTo
+      | Self::RequireSriFor
+      | Self::ScriptSrcAttr
+      | Self::ScriptSrcElem
+      | Self::StyleSrcAttr
+      | Self::StyleSrcElem
+      | Self::TrustedTypes
+      | Self::UpgradeInsecureRequests
+      | Self::WorkerSrc => Level::Three,
+    }
+  }
+}
+
+impl Source<'_> {
+  #[must_use]
+  /// The level of CSP that introduced the source expression.
+  pub const fn level(&self) -> Level {
+    match self {
+      Self::Host(_)
+      | Self::Scheme(_)
+      | Self::Self_
+      | Self::UnsafeEval
+      | Self::UnsafeInline => Level::One,
+      Self::Nonce(_) | Self::Hash(_) => Level::Two,
+      Self::StrictDynamic
+      | Self::UnsafeHashes
+      | Self::ReportSample
+      | Self::WasmUnsafeEval => Level::Three,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Why a browser ignores part of a policy.
+pub enum IgnoredReason {
+  /// The directive or source expression is from a later level, or not
+  /// supported by the browser engine.
+  Unsupported,
+  /// `'unsafe-inline'` is ignored when there are nonces or hashes, from
+  /// level 2 on.
+  NonceOrHash,
+  /// `'strict-dynamic'` makes scripts ignore `'unsafe-inline'`, hosts,
+  /// schemes and `'self'`, from level 3 on.
+  StrictDynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A part of a policy that a browser ignores.
+pub struct Ignored<'a> {
+  /// The directive that is or has the ignored part.
+  pub directive: DirectiveKind,
+  /// The ignored source expression, or `None` if the whole directive is.
+  pub source: Option<Source<'a>>,
+  /// Why it is ignored.
+  pub reason: IgnoredReason,
+}
+
+impl fmt::Display for Ignored<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match &self.source {
+      Some(source) => write!(fmt, "{}: {source} ", self.directive)?,
+      None => write!(fmt, "{} ", self.directive)?,
+    }
+    match self.reason {
+      IgnoredReason::Unsupported => write!(fmt, "is not supported"),
+      IgnoredReason::NonceOrHash => write!(fmt, "is ignored because of nonces or hashes"),
+      IgnoredReason::StrictDynamic => {
+        write!(fmt, "is ignored because of 'strict-dynamic'")
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a browser that implements a level of CSP interprets a policy, as
+/// returned by [`CSP::interpret`].
+pub struct Interpretation<'a> {
+  /// The level of the browser.
+  pub level: Level,
+  /// The policy without the parts that the browser ignores, so it allows
+  /// what the browser allows.
+  pub policy: CSP<'a>,
+  /// The parts of the policy that the browser ignores, in order.
+  pub ignored: Vec<Ignored<'a>>,
+}
+
+impl<'a> CSP<'a> {
+  #[must_use]
+  /// Adds the fallbacks that let a strict policy keep pages working in
+  /// browsers that don't support its newer keywords, where they are ignored
+  /// by browsers that do.
+  ///
+  /// - `'unsafe-inline'` is added to `script-src` and `style-src` when they
+  ///   have nonces or hashes, which level 1 browsers don't understand and
+  ///   would otherwise block every inline script or style with.
+  /// - `https:` is added to a `script-src` with `'strict-dynamic'` and no
+  ///   hosts, schemes or `'self'`, as level 2 browsers don't let trusted
+  ///   scripts load others without them. When there are hosts already, they
+  ///   are kept as the fallback instead of allowing every secure host.
+  ///
+  /// Level 1 browsers then allow every inline script, which they have no way
+  /// of telling apart, so [`interpret`](Self::interpret) the result to see
+  /// what each level enforces.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse("script-src 'nonce-abc' 'strict-dynamic'; style-src 'self' 'nonce-abc'")
+  ///   .unwrap()
+  ///   .with_compat_fallbacks();
+  ///
+  /// assert_eq!(
+  ///   csp.to_string(),
+  ///   "script-src 'nonce-abc' 'strict-dynamic' https: 'unsafe-inline'; \
+  ///    style-src 'self' 'nonce-abc' 'unsafe-inline'"
+  /// );
+  /// ```
+  pub fn with_compat_fallbacks(mut self) -> Self {
+    for directive in &mut self {
+      let (is_script, sources) = match directive {
+        Directive::ScriptSrc(sources) => (true, sources),
+        Directive::StyleSrc(sources) => (false, sources),
+        _ => continue,
+      };
+
+      if is_script
+        && sources.0.contains(&Source::StrictDynamic)
+        && sources.expressions().next().is_none()
+      {
+        sources.push_borrowed(Source::Scheme("https"));
+      }
+
+      let has_nonce_or_hash = sources
+        .0
+        .iter()
+        .any(|source| matches!(source, Source::Nonce(_) | Source::Hash(_)));
+      if has_nonce_or_hash && !sources.0.contains(&Source::UnsafeInline) {
+        sources.push_borrowed(Source::UnsafeInline);
+      }
+    }
+    self
+  }
+
+  #[must_use]
+  /// Interprets the policy as a browser that implements the given level of
+  /// CSP does, by leaving out the directives and source expressions that it
+  /// doesn't support, and the sources that newer keywords make it ignore.
+  ///
+  /// Source lists that are left empty allow nothing, like `'none'`.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, Level};
+  ///
+  /// let csp = CSP::strict_nonce_based("abc");
+  /// let enforced: Vec<_> =
+  ///   Level::ALL.into_iter().map(|level| csp.interpret(level).policy.to_string()).collect();
+  ///
+  /// assert_eq!(
+  ///   enforced,
+  ///   [
+  ///     "script-src https: 'unsafe-inline'; object-src 'none'",
+  ///     "script-src 'nonce-abc' https:; object-src 'none'; base-uri 'none'",
+  ///     "script-src 'nonce-abc' 'strict-dynamic'; object-src 'none'; base-uri 'none'",
+  ///   ]
+  /// );
+  /// ```
+  pub fn interpret(&self, level: Level) -> Interpretation<'a> {
+    let (policy, ignored) =
+      self.interpret_with(|kind| kind.level() <= level, |source| source.level() <= level);
+    Interpretation { level, policy, ignored }
+  }
+
+  /// Leaves out the directives and sources that aren't supported, and the
+  /// sources that the supported ones make browsers ignore.
+  pub(crate) fn interpret_with(
+    &self,
+    supports_directive: impl Fn(DirectiveKind) -> bool,
+    supports_source: impl Fn(&Source<'_>) -> bool,
+  ) -> (Self, Vec<Ignored<'a>>) {
+    let mut policy = Self::new();
+    let mut ignored = Vec::new();
+
+    for directive in self {
+      let kind = directive.kind();
+      if !supports_directive(kind) {
+        ignored.push(Ignored {
+          directive: kind,
+          source: None,
+          reason: IgnoredReason::Unsupported,
+        });
+        continue;
+      }
+
+      let mut directive = directive.clone();
+      if let Some(sources) = directive.sources_mut() {
+        let supported = sources.0.iter().filter(|source| supports_source(source));
+        let has_nonce_or_hash = supported
+          .clone()
+          .any(|source| matches!(source, Source::Nonce(_) | Source::Hash(_)));
+        let is_strict_dynamic = kind.is_script()
+          && supported.clone().any(|source| *source == Source::StrictDynamic);
+
+        sources.0.to_mut().retain(|source| {
+          let reason = if !supports_source(source) {
+            IgnoredReason::Unsupported
+          } else if *source == Source::UnsafeInline && has_nonce_or_hash {
+            IgnoredReason::NonceOrHash
+          } else if is_strict_dynamic
+            && matches!(
+              source,
+              Source::UnsafeInline | Source::Host(_) | Source::Scheme(_) | Source::Self_
+            )
+          {
+            IgnoredReason::StrictDynamic
+          } else {
+            return true;
+          };
+
+          ignored.push(Ignored { directive: kind, source: Some(source.clone()), reason });
+          false
+        });
+      }
+      policy.push_borrowed(directive);
+    }
+
+    (policy, ignored)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn describe(interpretation: &Interpretation<'_>) -> Vec<String> {
+    interpretation.ignored.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn fallbacks() {
+    let csp = CSP::parse(
+      "script-src 'sha256-abc=' 'strict-dynamic'; style-src 'nonce-abc'; \
+       img-src 'nonce-abc'",
+    )
+    .unwrap();
+    let with_fallbacks = csp.clone().with_compat_fallbacks();
+
+    assert_eq!(
+      with_fallbacks.to_string(),
+      "script-src 'sha256-abc=' 'strict-dynamic' https: 'unsafe-inline'; \
+       style-src 'nonce-abc' 'unsafe-inline'; img-src 'nonce-abc'"
+    );
+    assert_eq!(with_fallbacks.clone().with_compat_fallbacks(), with_fallbacks);
+
+    // Level 3 browsers enforce the same policy with or without them.
+    assert_eq!(
+      with_fallbacks.interpret(Level::Three).policy,
+      csp.interpret(Level::Three).policy
+    );
+    assert!(with_fallbacks.subsumes(&csp) && csp.subsumes(&with_fallbacks));
+
+    // Existing hosts are kept as the fallback of `'strict-dynamic'`.
+    let csp =
+      CSP::parse("script-src 'nonce-abc' 'strict-dynamic' https://cdn.example.org")
+        .unwrap()
+        .with_compat_fallbacks();
+    assert_eq!(
+      csp.to_string(),
+      "script-src 'nonce-abc' 'strict-dynamic' https://cdn.example.org 'unsafe-inline'"
+    );
+  }
+
+  #[test]
+  fn levels() {
+    let csp = CSP::parse(
+      "default-src 'self'; script-src 'nonce-abc' 'strict-dynamic' 'self' 'unsafe-inline'; \
+       script-src-attr 'unsafe-hashes' 'sha256-abc='; frame-ancestors 'none'",
+    )
+    .unwrap();
+
+    let one = csp.interpret(Level::One);
+    assert_eq!(
+      one.policy.to_string(),
+      "default-src 'self'; script-src 'self' 'unsafe-inline'"
+    );
+    assert_eq!(
+      describe(&one),
+      [
+        "script-src: 'nonce-abc' is not supported",
+        "script-src: 'strict-dynamic' is not supported",
+        "script-src-attr is not supported",
+        "frame-ancestors is not supported",
+      ]
+    );
+
+    let two = csp.interpret(Level::Two);
+    assert_eq!(
+      two.policy.to_string(),
+      "default-src 'self'; script-src 'nonce-abc' 'self'; frame-ancestors 'none'"
+    );
+    assert_eq!(
+      describe(&two),
+      [
+        "script-src: 'strict-dynamic' is not supported",
+        "script-src: 'unsafe-inline' is ignored because of nonces or hashes",
+        "script-src-attr is not supported",
+      ]
+    );
+
+    let three = csp.interpret(Level::Three);
+    assert_eq!(
+      three.policy.to_string(),
+      "default-src 'self'; script-src 'nonce-abc' 'strict-dynamic'; \
+       script-src-attr 'unsafe-hashes' 'sha256-abc='; frame-ancestors 'none'"
+    );
+    assert_eq!(
+      describe(&three),
+      [
+        "script-src: 'self' is ignored because of 'strict-dynamic'",
+        "script-src: 'unsafe-inline' is ignored because of nonces or hashes",
+      ]
+    );
+
+    // Directives that only know level 1 sources are enforced as they are.
+    let csp = CSP::static_site();
+    assert_eq!(csp.interpret(Level::Three).policy, csp);
+    assert!(csp.interpret(Level::Two).ignored.is_empty());
+  }
+}
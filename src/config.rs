@@ -0,0 +1,356 @@
+//! (De)serializing policies with serde, for storing them in configuration.
+
+use std::{
+  fmt,
+  hash::{Hash, Hasher},
+  marker::PhantomData,
+};
+
+use serde::{
+  Deserialize, Deserializer, Serialize, Serializer,
+  de::{self, MapAccess, SeqAccess, Visitor, value},
+  ser::SerializeMap,
+};
+
+use crate::{CSP, DirectiveKind, ParseError, parse::parse_directive};
+
+/// Serializes the policy as a map from directive names to lists of values.
+///
+/// Only the first of duplicate directives is serialized, as browsers only use
+/// that one. Directives without any sources get `'none'` as their value.
+impl Serialize for CSP<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut kinds: Vec<DirectiveKind> = Vec::new();
+    for directive in self {
+      if !kinds.contains(&directive.kind()) {
+        kinds.push(directive.kind());
+      }
+    }
+
+    let mut map = serializer.serialize_map(Some(kinds.len()))?;
+    for kind in kinds {
+      let Some(directive) = self.get(kind) else {
+        continue;
+      };
+
+      let mut values: Vec<String> =
+        directive.items().iter().map(ToString::to_string).collect();
+      if values.is_empty() && directive.sources().is_some() {
+        values.push("'none'".to_owned());
+      }
+      map.serialize_entry(kind.name(), &values)?;
+    }
+    map.end()
+  }
+}
+
+/// Deserializes a policy from either a map from directive names to lists of
+/// values, or the policy's header string. Unlike [`CSP::parse`], unknown
+/// directives are an error in both forms, so that typos are not dropped.
+///
+/// As the policy borrows from the input, this only works with deserializers
+/// that can lend out strings, such as `serde_json::from_str`. For other
+/// formats, deserialize a [`PolicyConfig`] instead.
+impl<'de> Deserialize<'de> for CSP<'de> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct PolicyVisitor;
+
+    impl<'de> Visitor<'de> for PolicyVisitor {
+      type Value = CSP<'de>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "a borrowed policy string or a map of directives")
+      }
+
+      fn visit_borrowed_str<E: de::Error>(self, policy: &'de str) -> Result<CSP<'de>, E> {
+        CSP::parse_strict(policy).map_err(E::custom)
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<CSP<'de>, A::Error> {
+        let mut csp = CSP::new();
+        for (name, values) in entries::<A, &'de str>(map)? {
+          csp.push_borrowed(to_directive(name, values).map_err(de::Error::custom)?);
+        }
+        Ok(csp)
+      }
+    }
+
+    deserializer.deserialize_any(PolicyVisitor)
+  }
+}
+
+#[derive(Debug, Clone)]
+/// A validated policy that owns its string, for deserializing policies from
+/// configuration formats that can't lend out strings, such as TOML or YAML.
+///
+/// Deserializes from either a map from directive names to lists of values, or
+/// the policy's header string, and serializes as the former. Errors point at
+/// the offending directive or value. Compares like [`CSP`] does.
+///
+/// # Example usage
+/// ```rust
+/// use csp::PolicyConfig;
+///
+/// let config: PolicyConfig = toml::from_str(
+///   r#"
+///   default-src = ["'self'"]
+///   script-src = ["'self'", "https://cdn.example.org"]
+///   upgrade-insecure-requests = []
+///   "#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///   config.policy().to_string(),
+///   "default-src 'self'; script-src 'self' https://cdn.example.org; \
+///    upgrade-insecure-requests"
+/// );
+/// ```
+pub struct PolicyConfig(String);
+
+impl PolicyConfig {
+  /// Validates and stores a policy string.
+  ///
+  /// # Errors
+  /// If the policy can't be parsed with [`CSP::parse_strict`].
+  pub fn new(policy: String) -> Result<Self, ParseError> {
+    CSP::parse_strict(&policy)?;
+    Ok(Self(policy))
+  }
+
+  /// The policy, borrowing from the configuration.
+  #[must_use]
+  pub fn policy(&self) -> CSP<'_> {
+    // The string was validated when the configuration was created.
+    CSP::parse_strict(&self.0).unwrap_or_default()
+  }
+}
+
+impl From<&CSP<'_>> for PolicyConfig {
+  fn from(csp: &CSP<'_>) -> Self {
+    Self(csp.to_string())
+  }
+}
+
+impl PartialEq for PolicyConfig {
+  fn eq(&self, other: &Self) -> bool {
+    self.policy() == other.policy()
+  }
+}
+
+impl Eq for PolicyConfig {}
+
+impl Hash for PolicyConfig {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.policy().hash(state);
+  }
+}
+
+impl fmt::Display for PolicyConfig {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "{}", self.0)
+  }
+}
+
+impl Serialize for PolicyConfig {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.policy().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for PolicyConfig {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct ConfigVisitor;
+
+    impl<'de> Visitor<'de> for ConfigVisitor {
+      type Value = PolicyConfig;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "a policy string or a map of directives")
+      }
+
+      fn visit_str<E: de::Error>(self, policy: &str) -> Result<PolicyConfig, E> {
+        PolicyConfig::new(policy.to_owned()).map_err(E::custom)
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<PolicyConfig, A::Error> {
+        let mut policy = String::new();
+        for (name, values) in entries::<A, String>(map)? {
+          let values: Vec<&str> = values.iter().map(String::as_str).collect();
+          let directive = to_directive(&name, values).map_err(de::Error::custom)?;
+
+          if !policy.is_empty() {
+            policy.push_str("; ");
+          }
+          policy.push_str(&directive.to_string());
+        }
+        Ok(PolicyConfig(policy))
+      }
+    }
+
+    deserializer.deserialize_any(ConfigVisitor)
+  }
+}
+
+/// Reads the entries of a map of directives, rejecting duplicate directives.
+fn entries<'de, A, T>(mut map: A) -> Result<Vec<(T, Vec<T>)>, A::Error>
+where
+  A: MapAccess<'de>,
+  T: Deserialize<'de> + AsRef<str>,
+{
+  let mut entries: Vec<(T, Vec<T>)> = Vec::new();
+  while let Some((name, Values(values))) = map.next_entry::<T, Values<T>>()? {
+    let is_duplicate = entries
+      .iter()
+      .any(|(existing, _)| existing.as_ref().eq_ignore_ascii_case(name.as_ref()));
+    if is_duplicate {
+      return Err(de::Error::custom(format_args!(
+        "duplicate directive `{}`",
+        name.as_ref()
+      )));
+    }
+    entries.push((name, values));
+  }
+  Ok(entries)
+}
+
+fn to_directive<'a>(
+  name: &str,
+  values: Vec<&'a str>,
+) -> Result<crate::Directive<'a>, ParseError> {
+  let kind = DirectiveKind::from_name(name)
+    .ok_or_else(|| ParseError::UnknownDirective(name.to_owned()))?;
+
+  // Values that would be split into several when parsing them back from the
+  // header string are not a single value.
+  if let Some(value) = values.iter().find(|value| {
+    value.is_empty() || value.contains(|c: char| c == ';' || c.is_ascii_whitespace())
+  }) {
+    return Err(ParseError::InvalidValue {
+      directive: kind.name(),
+      value: (*value).to_owned(),
+    });
+  }
+
+  parse_directive(kind, values.into_iter())
+}
+
+/// The values of a directive, either as a list or as a space separated string.
+struct Values<T>(Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Values<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct ValuesVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for ValuesVisitor<T> {
+      type Value = Values<T>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "a list of directive values or a space separated string of them")
+      }
+
+      fn visit_borrowed_str<E: de::Error>(
+        self,
+        values: &'de str,
+      ) -> Result<Values<T>, E> {
+        values
+          .split_ascii_whitespace()
+          .map(|value| T::deserialize(value::BorrowedStrDeserializer::new(value)))
+          .collect::<Result<_, _>>()
+          .map(Values)
+      }
+
+      fn visit_str<E: de::Error>(self, values: &str) -> Result<Values<T>, E> {
+        values
+          .split_ascii_whitespace()
+          .map(|value| T::deserialize(value::StrDeserializer::new(value)))
+          .collect::<Result<_, _>>()
+          .map(Values)
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Values<T>, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+          values.push(value);
+        }
+        Ok(Values(values))
+      }
+    }
+
+    deserializer.deserialize_any(ValuesVisitor(PhantomData))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use super::*;
+
+  #[test]
+  fn borrowed() {
+    let csp: CSP<'_> = serde_json::from_str(
+      r#"{"img-src": ["'self'", "data:"], "sandbox": "allow-scripts", "object-src": []}"#,
+    )
+    .unwrap();
+    assert_eq!(
+      csp.to_string(),
+      "img-src 'self' data:; sandbox allow-scripts; object-src 'none'"
+    );
+
+    assert_eq!(
+      serde_json::to_string(&csp).unwrap(),
+      r#"{"img-src":["'self'","data:"],"sandbox":["allow-scripts"],"object-src":["'none'"]}"#
+    );
+
+    let from_header: CSP<'_> = serde_json::from_str(r#""img-src 'self' data:""#).unwrap();
+    assert_eq!(from_header.to_string(), "img-src 'self' data:");
+  }
+
+  #[test]
+  fn owned() {
+    let table: BTreeMap<String, PolicyConfig> =
+      toml::from_str("policy = \"script-src 'self'; report-to main\"").unwrap();
+    let config = &table["policy"];
+    assert_eq!(config.policy().to_string(), "script-src 'self'; report-to main");
+
+    let serialized = toml::to_string(&config).unwrap();
+    assert_eq!(serialized, "script-src = [\"'self'\"]\nreport-to = [\"main\"]\n");
+    assert_eq!(&toml::from_str::<PolicyConfig>(&serialized).unwrap(), config);
+  }
+
+  #[test]
+  fn errors() {
+    let error =
+      |input: &str| toml::from_str::<PolicyConfig>(input).unwrap_err().to_string();
+
+    assert!(error("scirpt-src = []").contains("unknown directive `scirpt-src`"));
+    assert!(
+      toml::from_str::<BTreeMap<String, PolicyConfig>>(
+        "policy = \"scirpt-src 'self'; img-src *\""
+      )
+      .unwrap_err()
+      .to_string()
+      .contains("unknown directive `scirpt-src`")
+    );
+    assert!(
+      serde_json::from_str::<CSP<'_>>(r#""scirpt-src 'self'; img-src *""#)
+        .unwrap_err()
+        .to_string()
+        .contains("unknown directive `scirpt-src`")
+    );
+    assert!(
+      error("img-src = [\"'self\"]").contains("invalid value `'self` for `img-src`")
+    );
+    assert!(
+      error("img-src = [\"'self'; script-src *\"]")
+        .contains("invalid value `'self'; script-src *` for `img-src`")
+    );
+    assert!(
+      serde_json::from_str::<CSP<'_>>(r#"{"img-src": [], "IMG-SRC": []}"#)
+        .unwrap_err()
+        .to_string()
+        .contains("duplicate directive `IMG-SRC`")
+    );
+  }
+}
@@ -0,0 +1,151 @@
+//! Textual differences between two policies.
+
+use std::fmt;
+
+use crate::{CSP, Directive, DirectiveKind, Item, compare::difference};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a single directive differs between two policies.
+pub enum DirectiveDiff<'a> {
+  /// The directive only exists in the new policy.
+  Added(Directive<'a>),
+  /// The directive only exists in the old policy.
+  Removed(Directive<'a>),
+  /// The directive exists in both, but with different values.
+  Changed {
+    /// The kind of the directive.
+    directive: DirectiveKind,
+    /// The values that only the new directive has.
+    added: Vec<Item<'a>>,
+    /// The values that only the old directive has.
+    removed: Vec<Item<'a>>,
+  },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The result of [`diff`], which can be displayed as text.
+///
+/// Directives are listed in alphabetical order, and values in the order they
+/// appear in the policies. Added directives and values are prefixed with `+`,
+/// removed ones with `-` and changed directives with `~`.
+pub struct Diff<'a>(pub Vec<DirectiveDiff<'a>>);
+
+/// Lists the directives and values that differ between two policies.
+///
+/// Unlike [`compare`](crate::compare), this looks at the policies as they are
+/// written, without taking any fallbacks into account. Reordering values or
+/// directives is not a difference.
+///
+/// # Example usage
+/// ```rust
+/// use csp::CSP;
+///
+/// let old = CSP::parse("script-src 'self' https://a.example.org; object-src 'none'").unwrap();
+/// let new = CSP::parse("img-src 'self'; script-src https://b.example.org 'self'").unwrap();
+///
+/// assert_eq!(
+///   csp::diff(&old, &new).to_string(),
+///   "+ img-src 'self'\n\
+///    - object-src 'none'\n\
+///    ~ script-src\n  \
+///      + https://b.example.org\n  \
+///      - https://a.example.org\n"
+/// );
+/// ```
+#[must_use]
+pub fn diff<'a>(old: &CSP<'a>, new: &CSP<'a>) -> Diff<'a> {
+  let mut diff = Diff::default();
+
+  for kind in DirectiveKind::ALL {
+    match (old.get(kind), new.get(kind)) {
+      (None, None) => {}
+      (None, Some(new)) => diff.0.push(DirectiveDiff::Added(new.clone())),
+      (Some(old), None) => diff.0.push(DirectiveDiff::Removed(old.clone())),
+      (Some(old), Some(new)) => {
+        let (added, removed) = difference(&old.items(), &new.items());
+
+        if !added.is_empty() || !removed.is_empty() {
+          diff.0.push(DirectiveDiff::Changed { directive: kind, added, removed });
+        }
+      }
+    }
+  }
+
+  diff
+}
+
+impl Diff<'_> {
+  /// If the policies had no differences.
+  #[must_use]
+  pub const fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+}
+
+impl fmt::Display for DirectiveDiff<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Added(directive) => writeln!(fmt, "+ {directive}"),
+      Self::Removed(directive) => writeln!(fmt, "- {directive}"),
+      Self::Changed { directive, added, removed } => {
+        writeln!(fmt, "~ {directive}")?;
+
+        for item in added {
+          writeln!(fmt, "  + {item}")?;
+        }
+        for item in removed {
+          writeln!(fmt, "  - {item}")?;
+        }
+
+        Ok(())
+      }
+    }
+  }
+}
+
+impl fmt::Display for Diff<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    for directive in &self.0 {
+      write!(fmt, "{directive}")?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn diff_str(old: &str, new: &str) -> String {
+    diff(&CSP::parse(old).unwrap(), &CSP::parse(new).unwrap()).to_string()
+  }
+
+  #[test]
+  fn reordering() {
+    assert_eq!(
+      diff_str(
+        "img-src https: 'self'; upgrade-insecure-requests",
+        "upgrade-insecure-requests; img-src 'self' https: 'self'"
+      ),
+      ""
+    );
+  }
+
+  #[test]
+  fn values() {
+    assert_eq!(
+      diff_str(
+        "sandbox allow-forms; trusted-types one; upgrade-insecure-requests",
+        "sandbox allow-scripts allow-forms; trusted-types two; block-all-mixed-content"
+      ),
+      "+ block-all-mixed-content\n\
+       ~ sandbox\n  \
+         + allow-scripts\n\
+       ~ trusted-types\n  \
+         + two\n  \
+         - one\n\
+       - upgrade-insecure-requests\n"
+    );
+  }
+}
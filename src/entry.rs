@@ -0,0 +1,64 @@
+//! In-place manipulation of a single directive of a policy.
+
+use crate::{CSP, Directive, DirectiveKind};
+
+/// A view into the directive of a certain kind in a [`CSP`], which may or may
+/// not exist.
+///
+/// Created with [`CSP::entry`].
+pub struct Entry<'c, 'a> {
+  pub(crate) csp: &'c mut CSP<'a>,
+  pub(crate) kind: DirectiveKind,
+}
+
+impl<'c, 'a> Entry<'c, 'a> {
+  #[must_use]
+  /// The kind of directive this entry is for
+  pub const fn kind(&self) -> DirectiveKind {
+    self.kind
+  }
+
+  #[allow(clippy::must_use_candidate)]
+  /// Gets the directive, pushing the given one to the end of the CSP if there
+  /// is none.
+  ///
+  /// # Panics
+  /// If the given directive is of a different kind than the entry.
+  pub fn or_insert(self, default: Directive<'a>) -> &'c mut Directive<'a> {
+    self.or_insert_with(|| default)
+  }
+
+  /// Gets the directive, pushing the one returned by the closure to the end
+  /// of the CSP if there is none.
+  ///
+  /// # Panics
+  /// If the returned directive is of a different kind than the entry.
+  pub fn or_insert_with<F>(self, default: F) -> &'c mut Directive<'a>
+  where
+    F: FnOnce() -> Directive<'a>,
+  {
+    let kind = self.kind;
+    let index = if let Some(index) = self.csp.0.iter().position(|d| d.kind() == kind) {
+      index
+    } else {
+      let directive = default();
+      assert_eq!(directive.kind(), kind, "the directive must be of the entry's kind");
+      self.csp.0.to_mut().push(directive);
+      self.csp.0.len() - 1
+    };
+
+    &mut self.csp.0.to_mut()[index]
+  }
+
+  #[must_use]
+  /// Modifies the directive if there is one.
+  pub fn and_modify<F>(self, modify: F) -> Self
+  where
+    F: FnOnce(&mut Directive<'a>),
+  {
+    if let Some(directive) = self.csp.get_mut(self.kind) {
+      modify(directive);
+    }
+    self
+  }
+}
@@ -0,0 +1,90 @@
+//! Writing out what every fetch directive inherits.
+
+use crate::{CSP, kind::DirectiveKind};
+
+/// The fetch directives that [`CSP::expand`] materializes.
+pub const FETCH_DIRECTIVES: [DirectiveKind; 15] = [
+  DirectiveKind::ChildSrc,
+  DirectiveKind::ConnectSrc,
+  DirectiveKind::FontSrc,
+  DirectiveKind::FrameSrc,
+  DirectiveKind::ImgSrc,
+  DirectiveKind::ManifestSrc,
+  DirectiveKind::MediaSrc,
+  DirectiveKind::ObjectSrc,
+  DirectiveKind::ScriptSrc,
+  DirectiveKind::ScriptSrcAttr,
+  DirectiveKind::ScriptSrcElem,
+  DirectiveKind::StyleSrc,
+  DirectiveKind::StyleSrcAttr,
+  DirectiveKind::StyleSrcElem,
+  DirectiveKind::WorkerSrc,
+];
+
+impl CSP<'_> {
+  /// The inverse of [`CSP::minimize`]: adds every missing fetch directive with
+  /// the sources that it effectively inherits, so that it's easy to see what
+  /// each type of resource is allowed to load.
+  ///
+  /// Fetch directives that inherit nothing, because neither they nor anything
+  /// they fall back to is present, are left out as they are unrestricted. The
+  /// deprecated `prefetch-src` is never added. The existing directives are
+  /// kept as they are, and the added ones are appended in alphabetical order.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse("default-src 'self'; script-src 'nonce-a'; child-src 'none'").unwrap();
+  ///
+  /// assert_eq!(
+  ///   csp.expand().to_string(),
+  ///   "default-src 'self'; script-src 'nonce-a'; child-src 'none'; connect-src 'self'; \
+  ///    font-src 'self'; frame-src 'none'; img-src 'self'; manifest-src 'self'; \
+  ///    media-src 'self'; object-src 'self'; script-src-attr 'nonce-a'; \
+  ///    script-src-elem 'nonce-a'; style-src 'self'; style-src-attr 'self'; \
+  ///    style-src-elem 'self'; worker-src 'none'"
+  /// );
+  /// ```
+  #[must_use]
+  pub fn expand(&self) -> Self {
+    let mut expanded = self.clone();
+
+    for kind in FETCH_DIRECTIVES {
+      if self.get(kind).is_some() {
+        continue;
+      }
+
+      if let Some(sources) = self.effective_sources(kind) {
+        expanded.0.to_mut().extend(kind.with_sources(sources.clone()));
+      }
+    }
+
+    expanded
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let csp =
+      CSP::parse("default-src 'self' https:; img-src *; worker-src 'none'").unwrap();
+    let expanded = csp.expand();
+
+    assert!(expanded.subsumes(&csp) && csp.subsumes(&expanded));
+    assert_eq!(expanded.minimize().policy, csp);
+  }
+
+  #[test]
+  fn unrestricted() {
+    let csp = CSP::parse("style-src 'self'; base-uri 'none'").unwrap();
+
+    assert_eq!(
+      csp.expand().to_string(),
+      "style-src 'self'; base-uri 'none'; style-src-attr 'self'; style-src-elem 'self'"
+    );
+  }
+}
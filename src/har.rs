@@ -0,0 +1,399 @@
+//! Reading HAR files exported from browser devtools, and checking the
+//! requests they recorded against a policy.
+
+use std::{collections::BTreeMap, error, fmt};
+
+use crate::{
+  CSP, DirectiveKind, PageSources, Source, Verdict,
+  json::{self, Value},
+  matching::Url,
+  site::source_of,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a string could not be read as a HAR file.
+pub enum HarError {
+  /// The string is not valid JSON, with the byte offset of the error.
+  Json(usize),
+  /// The JSON document has no `log.entries` array.
+  MissingEntries,
+}
+
+impl fmt::Display for HarError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Json(offset) => write!(fmt, "invalid JSON at byte {offset}"),
+      Self::MissingEntries => write!(fmt, "not a HAR file, as `log.entries` is missing"),
+    }
+  }
+}
+
+impl error::Error for HarError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A request that was recorded in a HAR file.
+pub struct HarEntry {
+  /// The URL of the request.
+  pub url: String,
+  /// The type of resource that devtools recorded, such as `script` or
+  /// `image`. When there is none, it is guessed from the MIME type of the
+  /// response.
+  pub resource_type: Option<String>,
+  /// The ID of the page that made the request.
+  pub page: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A request from a HAR file that a policy applies to.
+pub struct HarRequest<'h> {
+  /// The URL of the request.
+  pub url: &'h str,
+  /// The URL of the document that made the request, which is what `'self'`
+  /// is matched against.
+  pub document: &'h str,
+  /// The directive that the request is subject to, before fallbacks.
+  pub directive: DirectiveKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A request from a HAR file that a policy would not allow, as returned by
+/// [`Har::check`].
+pub struct HarViolation<'h> {
+  /// The request.
+  pub request: HarRequest<'h>,
+  /// What the policy does with it, which is never [`Verdict::Allowed`].
+  pub verdict: Verdict,
+}
+
+impl fmt::Display for HarViolation<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "{}: {}", self.request.url, self.verdict)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The requests of a HAR file, such as one exported from the network panel
+/// of browser devtools, for testing a policy against a real browsing session
+/// before deploying it.
+///
+/// # Example usage
+/// ```rust
+/// use csp::{CSP, DirectiveKind, Har, Verdict};
+///
+/// let har = Har::parse(
+///   r#"{"log": {"entries": [
+///     {"pageref": "page_1", "_resourceType": "document",
+///      "request": {"url": "https://example.org/"}},
+///     {"pageref": "page_1", "_resourceType": "script",
+///      "request": {"url": "https://example.org/app.js"}},
+///     {"pageref": "page_1", "_resourceType": "image",
+///      "request": {"url": "https://images.example.org/a.png"}}
+///   ]}}"#,
+/// )
+/// .unwrap();
+///
+/// let csp = CSP::parse("default-src 'self'").unwrap();
+/// let violations = har.check(&csp);
+///
+/// assert_eq!(violations.len(), 1);
+/// assert_eq!(violations[0].request.url, "https://images.example.org/a.png");
+/// assert_eq!(violations[0].verdict, Verdict::Blocked(DirectiveKind::DefaultSrc));
+/// ```
+pub struct Har {
+  /// The entries of the file, in the order they were recorded.
+  pub entries: Vec<HarEntry>,
+}
+
+impl Har {
+  /// Reads the entries of a HAR file.
+  ///
+  /// # Errors
+  /// If the string is not JSON, or has no `log.entries` array.
+  pub fn parse(har: &str) -> Result<Self, HarError> {
+    let har = json::parse(har).map_err(HarError::Json)?;
+    let entries = har
+      .get("log")
+      .and_then(|log| log.get("entries"))
+      .and_then(Value::as_array)
+      .ok_or(HarError::MissingEntries)?;
+
+    let entries = entries
+      .iter()
+      .filter_map(|entry| {
+        let string =
+          |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_owned);
+
+        let url = string(entry.get("request").and_then(|request| request.get("url")))?;
+        let resource_type = string(entry.get("_resourceType")).or_else(|| {
+          let content =
+            entry.get("response").and_then(|response| response.get("content"));
+          let mime_type =
+            content.and_then(|content| content.get("mimeType"))?.as_str()?;
+          resource_type(mime_type).map(str::to_owned)
+        });
+
+        Some(HarEntry { url, resource_type, page: string(entry.get("pageref")) })
+      })
+      .collect();
+
+    Ok(Self { entries })
+  }
+
+  #[must_use]
+  /// The requests that a policy applies to, with the directive that each is
+  /// subject to and the document that made it.
+  ///
+  /// The document of a request is the first `document` entry of its page,
+  /// and later `document` entries of the page are taken to be frames. Entries
+  /// of other types, such as redirects and preflights, or of pages without a
+  /// document are skipped.
+  pub fn requests(&self) -> Vec<HarRequest<'_>> {
+    let is_document = |entry: &HarEntry| {
+      entry
+        .resource_type
+        .as_ref()
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("document"))
+    };
+    let document_of = |page: Option<&str>| {
+      self
+        .entries
+        .iter()
+        .find(|entry| is_document(entry) && entry.page.as_deref() == page)
+        .or_else(|| self.entries.iter().find(|entry| is_document(entry)))
+    };
+
+    self
+      .entries
+      .iter()
+      .filter_map(|entry| {
+        let document = document_of(entry.page.as_deref())?;
+        let directive =
+          match entry.resource_type.as_deref()?.to_ascii_lowercase().as_str() {
+            "document" if std::ptr::eq(entry, document) => return None,
+            "document" => DirectiveKind::FrameSrc,
+            "script" => DirectiveKind::ScriptSrcElem,
+            "stylesheet" => DirectiveKind::StyleSrcElem,
+            "image" => DirectiveKind::ImgSrc,
+            "font" => DirectiveKind::FontSrc,
+            "media" | "texttrack" => DirectiveKind::MediaSrc,
+            "manifest" => DirectiveKind::ManifestSrc,
+            "xhr" | "fetch" | "eventsource" | "websocket" | "ping" | "beacon" => {
+              DirectiveKind::ConnectSrc
+            }
+            _ => return None,
+          };
+
+        Some(HarRequest { url: &entry.url, document: &document.url, directive })
+      })
+      .collect()
+  }
+
+  #[must_use]
+  /// The requests that the policy would not have allowed, and the directive
+  /// that would have blocked each, following the matching rules of the
+  /// specification. Requests with URLs that can't be matched, such as
+  /// relative ones, are skipped.
+  pub fn check<'h>(&'h self, csp: &CSP<'_>) -> Vec<HarViolation<'h>> {
+    self
+      .requests()
+      .into_iter()
+      .filter_map(|request| {
+        let verdict = csp.check_url(request.directive, request.url, request.document)?;
+        (verdict != Verdict::Allowed).then_some(HarViolation { request, verdict })
+      })
+      .collect()
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Generates the sources of a minimal policy that allows every request of a
+/// HAR file, so that onboarding an existing site can start from the traffic
+/// that was observed.
+///
+/// Requests are grouped by the directive that they are subject to, with
+/// `script-src` and `style-src` used instead of `script-src-elem` and
+/// `style-src-elem`, which older browsers don't support. Each origin is
+/// allowed on its own, and the document's own origin through `'self'`.
+/// Origins are only collapsed into wildcards that were explicitly allowed,
+/// and only when more than one origin in a directive matches the wildcard.
+///
+/// # Example usage
+/// ```rust
+/// use csp::{Har, HarPolicy};
+///
+/// let har = Har::parse(
+///   r#"{"log": {"entries": [
+///     {"_resourceType": "document", "request": {"url": "https://example.org/"}},
+///     {"_resourceType": "script", "request": {"url": "https://example.org/app.js"}},
+///     {"_resourceType": "image", "request": {"url": "https://a.cdn.example.net/1.png"}},
+///     {"_resourceType": "image", "request": {"url": "https://b.cdn.example.net/2.png"}},
+///     {"_resourceType": "fetch", "request": {"url": "https://api.example.com/v1"}}
+///   ]}}"#,
+/// )
+/// .unwrap();
+///
+/// let sources = HarPolicy::new().allow_wildcard("https://*.cdn.example.net").sources(&har);
+/// assert_eq!(
+///   sources.policy().to_string(),
+///   "default-src 'none'; connect-src https://api.example.com; \
+///    img-src https://*.cdn.example.net; script-src 'self'; base-uri 'none'; form-action 'none'"
+/// );
+/// ```
+pub struct HarPolicy<'a> {
+  wildcards: Vec<&'a str>,
+}
+
+impl<'a> HarPolicy<'a> {
+  #[must_use]
+  /// Creates new options that don't allow any wildcards
+  pub const fn new() -> Self {
+    Self { wildcards: Vec::new() }
+  }
+
+  #[must_use]
+  /// Allows origins to be collapsed into a host source with a wildcard,
+  /// such as `https://*.example.org` or `*.example.org:*`
+  pub fn allow_wildcard(mut self, pattern: &'a str) -> Self {
+    self.wildcards.push(pattern);
+    self
+  }
+
+  #[must_use]
+  /// The sources that the requests of the HAR file need. Their
+  /// [`PageSources::policy`] blocks everything else, including `<base>`
+  /// elements and form submissions, which HAR files don't tell apart from
+  /// other requests.
+  pub fn sources(&self, har: &Har) -> PageSources {
+    // The sources of each directive, with the wildcard that matches each.
+    let mut grouped: BTreeMap<DirectiveKind, BTreeMap<String, Option<&str>>> =
+      BTreeMap::new();
+
+    for request in har.requests() {
+      let (Some(url), Some(document)) =
+        (Url::parse(request.url), Url::parse(request.document))
+      else {
+        continue;
+      };
+
+      let source = if Source::Self_.matches_url(&url, &document) {
+        Source::Self_.to_string()
+      } else if let Some(source) = source_of(request.url) {
+        source
+      } else {
+        continue;
+      };
+      let wildcard = self.wildcards.iter().copied().find(|pattern| {
+        pattern.contains('*') && Source::Host(pattern).matches_url(&url, &document)
+      });
+
+      let kind = match request.directive {
+        DirectiveKind::ScriptSrcElem => DirectiveKind::ScriptSrc,
+        DirectiveKind::StyleSrcElem => DirectiveKind::StyleSrc,
+        kind => kind,
+      };
+      grouped
+        .entry(kind)
+        .or_default()
+        .insert(source, wildcard.filter(|_| url.host.is_some()));
+    }
+
+    let mut sources = PageSources::default();
+    for (kind, group) in grouped {
+      let collapsed = group.iter().map(|(source, wildcard)| match wildcard {
+        Some(wildcard)
+          if source != "'self'"
+            && group.values().filter(|other| *other == &Some(*wildcard)).count() > 1 =>
+        {
+          (*wildcard).to_owned()
+        }
+        _ => source.clone(),
+      });
+      sources.sources.entry(kind).or_default().extend(collapsed);
+    }
+    sources
+  }
+}
+
+/// The devtools resource type of a response with the MIME type, for files
+/// from browsers that don't record it.
+fn resource_type(mime_type: &str) -> Option<&'static str> {
+  let mime_type = mime_type.split(';').next()?.trim().to_ascii_lowercase();
+  let (kind, subtype) = mime_type.split_once('/')?;
+
+  Some(match (kind, subtype) {
+    ("text", "html") | ("application", "xhtml+xml") => "document",
+    ("text", "css") => "stylesheet",
+    (_, "javascript" | "ecmascript" | "x-javascript") => "script",
+    ("image", _) => "image",
+    ("font", _) | ("application", "font-woff" | "x-font-woff" | "x-font-ttf") => "font",
+    ("audio" | "video", _) => "media",
+    ("application", "manifest+json") => "manifest",
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HAR: &str = r#"{"log": {
+    "pages": [{"id": "a", "title": "https://example.org/"}],
+    "entries": [
+      {"pageref": "a", "request": {"url": "https://example.org/"},
+       "response": {"content": {"mimeType": "text/html; charset=utf-8"}}},
+      {"pageref": "a", "_resourceType": "stylesheet",
+       "request": {"url": "https://example.org/main.css"}},
+      {"pageref": "a", "_resourceType": "script",
+       "request": {"url": "https://cdn.example.net/lib.js"}},
+      {"pageref": "a", "_resourceType": "fetch",
+       "request": {"url": "https://api.example.org/v1"}},
+      {"pageref": "a", "_resourceType": "websocket",
+       "request": {"url": "wss://example.org/live"}},
+      {"pageref": "a", "request": {"url": "https://example.org/font.woff2"},
+       "response": {"content": {"mimeType": "font/woff2"}}},
+      {"pageref": "a", "_resourceType": "document",
+       "request": {"url": "https://www.youtube.com/embed/x"}},
+      {"pageref": "a", "_resourceType": "preflight",
+       "request": {"url": "https://api.example.org/v1"}},
+      {"pageref": "a", "_resourceType": "image", "request": {"url": "data:image/png,x"}},
+      {"pageref": "a", "_resourceType": "image", "request": {}}
+    ]
+  }}"#;
+
+  #[test]
+  fn check() {
+    let har = Har::parse(HAR).unwrap();
+    assert_eq!(har.entries.len(), 9);
+    assert_eq!(har.entries[0].resource_type.as_deref(), Some("document"));
+    assert_eq!(har.requests().len(), 7);
+
+    let csp = CSP::parse(
+      "default-src 'self'; script-src 'self' 'nonce-abc'; connect-src 'self' \
+       https://*.example.org; frame-src https://www.youtube.com/embed/",
+    )
+    .unwrap();
+    let violations: Vec<_> = har.check(&csp).iter().map(ToString::to_string).collect();
+    assert_eq!(
+      violations,
+      [
+        "https://cdn.example.net/lib.js: not matched by script-src, unless allowed by a \
+         nonce, hash or 'strict-dynamic'",
+        "data:image/png,x: blocked by default-src",
+      ]
+    );
+
+    // The generated policy allows everything in the file.
+    let sources = HarPolicy::new().allow_wildcard("*.example.org").sources(&har);
+    assert_eq!(
+      sources.policy().to_string(),
+      "default-src 'none'; connect-src 'self' https://api.example.org; \
+       font-src 'self'; frame-src https://www.youtube.com; img-src data:; \
+       script-src https://cdn.example.net; style-src 'self'; base-uri 'none'; \
+       form-action 'none'"
+    );
+    assert!(har.check(&sources.policy()).is_empty());
+
+    assert_eq!(Har::parse("{\"log\": {}}"), Err(HarError::MissingEntries));
+    assert_eq!(Har::parse("{\"log\"").unwrap_err().to_string(), "invalid JSON at byte 6");
+  }
+}
@@ -0,0 +1,184 @@
+//! Splitting `Source::Host` expressions into their parts, following the
+//! `host-source` grammar of the CSP specification.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A `Source::Host` split into its parts.
+pub struct HostSource<'a> {
+  /// The scheme, without the `://` separator.
+  pub scheme: Option<&'a str>,
+  /// The host, which may be `*` or start with `*.`.
+  pub host: &'a str,
+  /// The port, which may be `*`.
+  pub port: Option<&'a str>,
+  /// The path, including the leading `/`.
+  pub path: Option<&'a str>,
+}
+
+impl<'a> HostSource<'a> {
+  /// Splits a host source expression into its parts, returning `None` if it
+  /// is not a valid host source.
+  pub(crate) fn parse(expression: &'a str) -> Option<Self> {
+    let (scheme, rest) = match expression.split_once("://") {
+      Some((scheme, rest)) if is_scheme(scheme) => (Some(scheme), rest),
+      Some(_) => return None,
+      None => (None, expression),
+    };
+
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = (!path.is_empty()).then_some(path);
+
+    let (host, port) = match authority.rsplit_once(':') {
+      Some((host, port)) => (host, Some(port)),
+      None => (authority, None),
+    };
+
+    if !is_host(host) {
+      return None;
+    }
+    if let Some(port) = port {
+      if port != "*" && (port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+      }
+    }
+
+    Some(Self { scheme, host, port, path })
+  }
+
+  /// If every URL that this expression matches is also matched by `other`.
+  ///
+  /// When the answer depends on the protected resource's own origin, this
+  /// errs on the side of returning `false`.
+  pub(crate) fn is_subsumed_by(&self, other: &HostSource<'_>) -> bool {
+    let schemes_ok = match (other.scheme, self.scheme) {
+      (Some(other), Some(this)) => scheme_part_matches(other, this),
+      // A missing scheme means the protected resource's scheme, which is
+      // either `http` or `https`. Only `http` (and `ws`) cover both of them.
+      (Some(other), None) => {
+        other.eq_ignore_ascii_case("http") || other.eq_ignore_ascii_case("ws")
+      }
+      (None, Some(this)) => this.eq_ignore_ascii_case("https"),
+      (None, None) => true,
+    };
+
+    schemes_ok
+      && host_subsumes(other.host, self.host)
+      && self.port_is_subsumed_by(other)
+      && path_subsumes(other.path, self.path)
+  }
+
+  fn port_is_subsumed_by(&self, other: &HostSource<'_>) -> bool {
+    match (other.port, self.port) {
+      (Some("*"), _) | (None, None) => true,
+      (Some(_), Some("*")) => false,
+      (Some(other_port), Some(port)) => other_port == port,
+      (Some(other_port), None) => self.scheme.and_then(default_port) == Some(other_port),
+      (None, Some(port)) => {
+        let scheme = other.scheme.or(self.scheme);
+        scheme.and_then(default_port) == Some(port)
+      }
+    }
+  }
+}
+
+/// If the string is a valid `scheme` as defined by RFC 3986.
+pub fn is_scheme(scheme: &str) -> bool {
+  let mut bytes = scheme.bytes();
+  bytes.next().is_some_and(|b| b.is_ascii_alphabetic())
+    && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+}
+
+fn is_host(host: &str) -> bool {
+  let host = host.strip_prefix("*.").unwrap_or(host);
+  host == "*"
+    || (!host.is_empty()
+      && host.split('.').all(|label| {
+        !label.is_empty() && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+      }))
+}
+
+/// The "scheme-part match" algorithm, which allows secure upgrades.
+pub fn scheme_part_matches(expression: &str, url: &str) -> bool {
+  let is = |a: &str, b: &str| a.eq_ignore_ascii_case(b);
+
+  is(expression, url)
+    || (is(expression, "http") && is(url, "https"))
+    || (is(expression, "ws") && (is(url, "wss") || is(url, "http") || is(url, "https")))
+    || (is(expression, "wss") && is(url, "https"))
+}
+
+/// If a host pattern matches everything that another host pattern matches.
+fn host_subsumes(pattern: &str, host: &str) -> bool {
+  if pattern == "*" {
+    return true;
+  }
+
+  let Some(suffix) = pattern.strip_prefix('*') else {
+    return host.eq_ignore_ascii_case(pattern);
+  };
+
+  let host = host.strip_prefix('*').unwrap_or(host);
+  host.len() >= suffix.len()
+    && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+    && host != "*"
+}
+
+fn path_subsumes(pattern: Option<&str>, path: Option<&str>) -> bool {
+  match (pattern, path) {
+    (None | Some("/"), _) => true,
+    (Some(_), None) => false,
+    (Some(pattern), Some(path)) if pattern.ends_with('/') => path.starts_with(pattern),
+    (Some(pattern), Some(path)) => pattern == path,
+  }
+}
+
+/// The default port of a scheme, if it has a well known one.
+pub fn default_port(scheme: &str) -> Option<&'static str> {
+  match scheme.to_ascii_lowercase().as_str() {
+    "http" | "ws" => Some("80"),
+    "https" | "wss" => Some("443"),
+    "ftp" => Some("21"),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parsing() {
+    assert_eq!(
+      HostSource::parse("https://*.example.org:8443/assets/"),
+      Some(HostSource {
+        scheme: Some("https"),
+        host: "*.example.org",
+        port: Some("8443"),
+        path: Some("/assets/"),
+      })
+    );
+    assert_eq!(
+      HostSource::parse("example.org"),
+      Some(HostSource { scheme: None, host: "example.org", port: None, path: None })
+    );
+    assert_eq!(HostSource::parse("https://"), None);
+    assert_eq!(HostSource::parse("example.org:http"), None);
+    assert_eq!(HostSource::parse("'self'"), None);
+  }
+
+  #[test]
+  fn subsumption() {
+    let subsumed = |a: &str, b: &str| {
+      HostSource::parse(a).unwrap().is_subsumed_by(&HostSource::parse(b).unwrap())
+    };
+
+    assert!(subsumed("https://a.example.org", "https://*.example.org"));
+    assert!(subsumed("https://a.example.org", "http://a.example.org"));
+    assert!(subsumed("https://a.example.org/x/y", "a.example.org/x/"));
+    assert!(subsumed("https://a.example.org:443", "https://a.example.org"));
+    assert!(!subsumed("https://*.example.org", "https://a.example.org"));
+    assert!(!subsumed("http://a.example.org", "https://a.example.org"));
+    assert!(!subsumed("https://a.example.org/x", "https://a.example.org/y"));
+    assert!(!subsumed("https://a.example.org:*", "https://a.example.org"));
+    assert!(!subsumed("https://example.org", "https://*.example.org"));
+  }
+}
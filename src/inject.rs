@@ -0,0 +1,390 @@
+//! Adding nonces to the scripts and styles of HTML as it is streamed out.
+
+use crate::{
+  CSP, DirectiveKind, Source,
+  scan::{RAW_TEXT, Tag},
+};
+
+/// The longest tag that is buffered to add a nonce to.
+const MAX_TAG_LENGTH: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+  Data,
+  /// In a tag, and in the attribute value with this quote if any.
+  Tag(Option<u8>),
+  /// In a tag that was too long to buffer and is sent on as it is, in the
+  /// attribute value with this quote if any, and followed by the text content
+  /// of the element if it has some.
+  LongTag(Option<u8>, Option<&'static str>),
+  Comment,
+  /// In the text content of an element, such as a `<script>`, until its end
+  /// tag.
+  RawText(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Rewrites HTML chunk by chunk, adding a nonce to every `<script>`,
+/// `<style>` and `<link rel=stylesheet>` element, for pages from templates
+/// that don't know about nonces.
+///
+/// **It must never be used on HTML that contains untrusted markup**, such as
+/// user content that was not escaped, as it adds the nonce to injected
+/// scripts just like to the template's own, which defeats the policy.
+///
+/// Only the tag that is being read is buffered, and only up to 8 KiB: longer
+/// tags are sent on as they are, without a nonce. Elements that already have
+/// a `nonce` are left as they are, as are tags in comments and in elements
+/// whose content is text, such as strings that look like tags in scripts.
+///
+/// The nonce has to be unguessable and different for every response, such
+/// as 16 random bytes in base64. Once it is added to the policy, inline
+/// event handlers and `'unsafe-inline'` stop working, as browsers ignore
+/// `'unsafe-inline'` when there are nonces.
+///
+/// # Example usage
+/// ```rust
+/// use csp::{CSP, NonceInjector};
+///
+/// let mut injector = NonceInjector::new("rAnd0m");
+/// let mut body = Vec::new();
+/// for chunk in ["<scr", "ipt>alert(1)</script><link rel=stylesheet href=/a.css>"] {
+///   body.extend(injector.rewrite(chunk.as_bytes()));
+/// }
+/// body.extend(injector.finish());
+///
+/// assert_eq!(
+///   String::from_utf8(body).unwrap(),
+///   "<script nonce=\"rAnd0m\">alert(1)</script>\
+///    <link nonce=\"rAnd0m\" rel=stylesheet href=/a.css>"
+/// );
+///
+/// let mut csp = CSP::parse("default-src 'self'").unwrap();
+/// NonceInjector::new("rAnd0m").add_to(&mut csp);
+/// assert_eq!(
+///   csp.to_string(),
+///   "default-src 'self'; script-src 'self' 'nonce-rAnd0m'; style-src 'self' 'nonce-rAnd0m'"
+/// );
+/// ```
+pub struct NonceInjector<'a> {
+  nonce: &'a str,
+  state: State,
+  buffer: Vec<u8>,
+}
+
+impl<'a> NonceInjector<'a> {
+  #[must_use]
+  /// Creates a new injector for a response, with its nonce
+  pub const fn new(nonce: &'a str) -> Self {
+    Self { nonce, state: State::Data, buffer: Vec::new() }
+  }
+
+  #[must_use]
+  /// The source that allows the elements with the nonce
+  pub const fn source(&self) -> Source<'a> {
+    Source::Nonce(self.nonce)
+  }
+
+  /// Adds the nonce to the directives of a policy that apply to scripts and
+  /// styles. `script-src` and `style-src` are first written out with what
+  /// they inherited from `default-src`, and left out if nothing restricts
+  /// them, as the nonce would only make the policy stricter there.
+  pub fn add_to(&self, csp: &mut CSP<'a>) {
+    for kind in [DirectiveKind::ScriptSrc, DirectiveKind::StyleSrc] {
+      if csp.get(kind).is_none() {
+        if let Some(directive) = csp
+          .effective_sources(kind)
+          .cloned()
+          .and_then(|sources| kind.with_sources(sources))
+        {
+          csp.push_borrowed(directive);
+        }
+      }
+    }
+
+    for kind in [
+      DirectiveKind::ScriptSrc,
+      DirectiveKind::ScriptSrcElem,
+      DirectiveKind::StyleSrc,
+      DirectiveKind::StyleSrcElem,
+    ] {
+      if let Some(sources) =
+        csp.get_mut(kind).and_then(|directive| directive.sources_mut())
+      {
+        if !sources.0.contains(&self.source()) {
+          sources.push_borrowed(self.source());
+        }
+      }
+    }
+  }
+
+  #[must_use]
+  /// Rewrites the next chunk of the document, returning what can be sent on.
+  /// Chunks can be split anywhere, even in the middle of tags or characters.
+  pub fn rewrite(&mut self, chunk: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunk.len());
+    for byte in chunk {
+      self.step(*byte, &mut out);
+    }
+    out
+  }
+
+  #[must_use]
+  /// Returns the rest of the document, such as a tag that was never closed.
+  pub fn finish(self) -> Vec<u8> {
+    match self.state {
+      // What is buffered there was already sent on.
+      State::Comment | State::LongTag(..) => Vec::new(),
+      _ => self.buffer,
+    }
+  }
+
+  fn step(&mut self, byte: u8, out: &mut Vec<u8>) {
+    match self.state {
+      State::Data => {
+        if byte == b'<' {
+          self.buffer.push(byte);
+          self.state = State::Tag(None);
+        } else {
+          out.push(byte);
+        }
+      }
+      State::Tag(quote) => {
+        self.buffer.push(byte);
+
+        // A `<` that doesn't start a tag, such as in `a < b`, is text.
+        if self.buffer.len() == 2
+          && !(byte.is_ascii_alphabetic() || matches!(byte, b'/' | b'!' | b'?'))
+        {
+          self.buffer.pop();
+          out.append(&mut self.buffer);
+          self.state = State::Data;
+          self.step(byte, out);
+        } else if self.buffer == b"<!--" {
+          out.append(&mut self.buffer);
+          self.state = State::Comment;
+        } else if quote == Some(byte) {
+          self.state = State::Tag(None);
+        } else if quote.is_none() && matches!(byte, b'"' | b'\'') && self.after_equals() {
+          self.state = State::Tag(Some(byte));
+        } else if quote.is_none() && byte == b'>' {
+          self.finish_tag(out);
+        }
+
+        if let State::Tag(quote) = self.state {
+          if self.buffer.len() >= MAX_TAG_LENGTH {
+            self.flush_tag(quote, out);
+          }
+        }
+      }
+      State::LongTag(quote, raw_text) => {
+        out.push(byte);
+        if quote.is_some() && quote != Some(byte) {
+          return;
+        }
+
+        if quote.is_some() {
+          self.state = State::LongTag(None, raw_text);
+        } else if matches!(byte, b'"' | b'\'') && self.buffer == b"=" {
+          self.state = State::LongTag(Some(byte), raw_text);
+        } else if byte == b'>' {
+          self.buffer.clear();
+          self.state = raw_text.map_or(State::Data, State::RawText);
+          return;
+        }
+        // Only the last byte that isn't a space is kept, to find the quotes
+        // that start attribute values.
+        if !byte.is_ascii_whitespace() {
+          self.buffer.clear();
+          self.buffer.push(byte);
+        }
+      }
+      State::Comment => {
+        out.push(byte);
+        self.buffer.push(byte);
+        if self.buffer.ends_with(b"-->") {
+          self.buffer.clear();
+          self.state = State::Data;
+        } else if self.buffer.len() > 2 {
+          self.buffer.remove(0);
+        }
+      }
+      State::RawText(name) => {
+        if self.buffer.is_empty() && byte != b'<' {
+          out.push(byte);
+          return;
+        }
+        self.buffer.push(byte);
+
+        // Looks for `</name` followed by a space, `/` or `>`.
+        let length = self.buffer.len();
+        let matches = if length <= name.len() + 2 {
+          b"</"
+            .iter()
+            .chain(name.as_bytes())
+            .zip(&self.buffer)
+            .all(|(expected, byte)| expected.eq_ignore_ascii_case(byte))
+        } else {
+          byte.is_ascii_whitespace() || matches!(byte, b'/' | b'>')
+        };
+
+        if !matches {
+          self.buffer.pop();
+          out.append(&mut self.buffer);
+          if byte == b'<' {
+            self.buffer.push(byte);
+          } else {
+            out.push(byte);
+          }
+        } else if length > name.len() + 2 {
+          out.append(&mut self.buffer);
+          self.state = State::Data;
+        }
+      }
+    }
+  }
+
+  /// If the quote that was just read starts an attribute value.
+  fn after_equals(&self) -> bool {
+    self.buffer[..self.buffer.len() - 1]
+      .iter()
+      .rev()
+      .find(|byte| !byte.is_ascii_whitespace())
+      .is_some_and(|byte| *byte == b'=')
+  }
+
+  /// Sends on the start of a tag that is too long to buffer, keeping track of
+  /// the rest of it without adding a nonce.
+  fn flush_tag(&mut self, quote: Option<u8>, out: &mut Vec<u8>) {
+    let name = self.buffer[1..]
+      .iter()
+      .position(|byte| byte.is_ascii_whitespace() || matches!(byte, b'/' | b'>'))
+      .map_or(&self.buffer[1..], |end| &self.buffer[1..=end]);
+    let raw_text = RAW_TEXT.into_iter().find(|raw_text| {
+      name.first().is_some_and(u8::is_ascii_alphabetic)
+        && raw_text.as_bytes().eq_ignore_ascii_case(name)
+    });
+    let last = self.buffer.iter().rev().find(|byte| !byte.is_ascii_whitespace()).copied();
+
+    out.append(&mut self.buffer);
+    self.buffer.extend(last);
+    self.state = State::LongTag(quote, raw_text);
+  }
+
+  fn finish_tag(&mut self, out: &mut Vec<u8>) {
+    let (inject, raw_text) = std::str::from_utf8(&self.buffer)
+      .ok()
+      .filter(|html| html.as_bytes().get(1).is_some_and(u8::is_ascii_alphabetic))
+      .map_or((None, None), |html| {
+        let (tag, _) = Tag::parse(&html[1..]);
+        let is_stylesheet = || {
+          tag.attribute("rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+          })
+        };
+        let needs_nonce =
+          ["script", "style"].iter().any(|name| name.eq_ignore_ascii_case(tag.name))
+            || (tag.name.eq_ignore_ascii_case("link") && is_stylesheet());
+
+        (
+          (needs_nonce && tag.attribute("nonce").is_none()).then_some(1 + tag.name.len()),
+          RAW_TEXT.into_iter().find(|name| name.eq_ignore_ascii_case(tag.name)),
+        )
+      });
+
+    if let Some(end) = inject {
+      out.extend_from_slice(&self.buffer[..end]);
+      out.extend_from_slice(b" nonce=\"");
+      out.extend_from_slice(self.nonce.as_bytes());
+      out.push(b'"');
+      out.extend_from_slice(&self.buffer[end..]);
+      self.buffer.clear();
+    } else {
+      out.append(&mut self.buffer);
+    }
+    self.state = raw_text.map_or(State::Data, State::RawText);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rewrite(html: &str, chunk_size: usize) -> String {
+    let mut injector = NonceInjector::new("abc");
+    let mut out = Vec::new();
+    for chunk in html.as_bytes().chunks(chunk_size) {
+      out.extend(injector.rewrite(chunk));
+    }
+    out.extend(injector.finish());
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn elements() {
+    let html = "<!DOCTYPE html><html><head>\
+      <SCRIPT src=/a.js></SCRIPT>\
+      <script nonce=other>a()</script>\
+      <script>if (a < b) { x = '<style>' + \"</scripts>\"; }</script >\
+      <link rel=\"preload stylesheet\" href=/a.css><link rel=icon href=/a.png>\
+      <style>a { content: '>' }</style>\
+      <!-- <script>commented()</script> --><title><script></title>\
+      </head><body><p title='>' data-x=\"<script>\">é</p><script/></body>";
+
+    let expected = "<!DOCTYPE html><html><head>\
+      <SCRIPT nonce=\"abc\" src=/a.js></SCRIPT>\
+      <script nonce=other>a()</script>\
+      <script nonce=\"abc\">if (a < b) { x = '<style>' + \"</scripts>\"; }</script >\
+      <link nonce=\"abc\" rel=\"preload stylesheet\" href=/a.css><link rel=icon href=/a.png>\
+      <style nonce=\"abc\">a { content: '>' }</style>\
+      <!-- <script>commented()</script> --><title><script></title>\
+      </head><body><p title='>' data-x=\"<script>\">é</p><script nonce=\"abc\"/></body>";
+
+    for chunk_size in [1, 2, 3, 7, html.len()] {
+      assert_eq!(rewrite(html, chunk_size), expected, "{chunk_size}");
+    }
+    assert_eq!(rewrite("<p>a <script", 1), "<p>a <script");
+  }
+
+  #[test]
+  fn long_tags() {
+    let long = "a".repeat(MAX_TAG_LENGTH);
+    let html = format!(
+      "<img alt = \"{long}>\" src=/a.png><script>a()</script>\
+       <script data-x='{long}'>'<style>'</script><style>a {{}}</style>"
+    );
+    let expected = format!(
+      "<img alt = \"{long}>\" src=/a.png><script nonce=\"abc\">a()</script>\
+       <script data-x='{long}'>'<style>'</script><style nonce=\"abc\">a {{}}</style>"
+    );
+
+    for chunk_size in [1, 7, html.len()] {
+      let mut injector = NonceInjector::new("abc");
+      let mut out = Vec::new();
+      for chunk in html.as_bytes().chunks(chunk_size) {
+        out.extend(injector.rewrite(chunk));
+        assert!(injector.buffer.len() <= MAX_TAG_LENGTH);
+      }
+      out.extend(injector.finish());
+      assert_eq!(String::from_utf8(out).unwrap(), expected, "{chunk_size}");
+    }
+
+    let unclosed = format!("<script src='{long}");
+    assert_eq!(rewrite(&unclosed, 5), unclosed);
+    assert_eq!(rewrite("<!-- a", 1), "<!-- a");
+  }
+
+  #[test]
+  fn policy() {
+    let injector = NonceInjector::new("abc");
+
+    let mut csp =
+      CSP::parse("script-src-elem 'self'; style-src 'self' 'nonce-abc'; img-src 'self'")
+        .unwrap();
+    injector.add_to(&mut csp);
+    assert_eq!(
+      csp.to_string(),
+      "script-src-elem 'self' 'nonce-abc'; style-src 'self' 'nonce-abc'; img-src 'self'"
+    );
+  }
+}
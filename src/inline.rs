@@ -0,0 +1,243 @@
+//! Checking inline content against a policy, following the "Should
+//! element's inline type behavior be blocked by Content Security Policy?"
+//! algorithm of the CSP specification.
+
+use std::fmt;
+
+use crate::{CSP, DirectiveKind, HashAlgorithm, Source, Sources};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kinds of inline content that policies restrict.
+pub enum InlineKind {
+  /// The content of a `<script>` element.
+  Script,
+  /// The value of an event handler attribute, such as `onclick`.
+  ScriptAttribute,
+  /// The content of a `<style>` element.
+  Style,
+  /// The value of a `style` attribute.
+  StyleAttribute,
+  /// A navigation to a `javascript:` URL, whose content is the whole URL.
+  Navigation,
+}
+
+impl InlineKind {
+  #[must_use]
+  /// The directive that applies to the content, before fallbacks.
+  pub const fn directive(self) -> DirectiveKind {
+    match self {
+      Self::Script | Self::Navigation => DirectiveKind::ScriptSrcElem,
+      Self::ScriptAttribute => DirectiveKind::ScriptSrcAttr,
+      Self::Style => DirectiveKind::StyleSrcElem,
+      Self::StyleAttribute => DirectiveKind::StyleSrcAttr,
+    }
+  }
+
+  /// If the content is that of an element, which nonces and hashes apply to
+  /// without `'unsafe-hashes'`.
+  const fn is_element(self) -> bool {
+    matches!(self, Self::Script | Self::Style)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a policy does with inline content, as returned by
+/// [`CSP::check_inline`].
+pub enum InlineVerdict<'a> {
+  /// The content is allowed by the directive through the source, which is
+  /// `'unsafe-inline'`, the nonce of the element or a hash of the content.
+  /// Both are `None` if nothing restricts the content.
+  Allowed(Option<(DirectiveKind, Source<'a>)>),
+  /// The content is blocked by the directive, which is the one that
+  /// effectively applies to it, possibly through fallbacks.
+  ///
+  /// The source, if any, is the reason why something that looks like it
+  /// allows the content doesn't: `'strict-dynamic'`, or a nonce or hash,
+  /// which make browsers ignore `'unsafe-inline'`, or `'unsafe-hashes'`,
+  /// without which hashes of attributes and URLs don't apply.
+  Blocked(DirectiveKind, Option<Source<'a>>),
+}
+
+impl InlineVerdict<'_> {
+  #[must_use]
+  /// If the content is allowed.
+  pub const fn is_allowed(&self) -> bool {
+    matches!(self, Self::Allowed(_))
+  }
+}
+
+impl fmt::Display for InlineVerdict<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Allowed(None) => write!(fmt, "allowed"),
+      Self::Allowed(Some((kind, source))) => write!(fmt, "allowed by {source} in {kind}"),
+      Self::Blocked(kind, None) => write!(fmt, "blocked by {kind}"),
+      Self::Blocked(kind, Some(Source::UnsafeHashes)) => {
+        write!(fmt, "blocked by {kind}, which has a hash of it but not 'unsafe-hashes'")
+      }
+      Self::Blocked(kind, Some(source)) => {
+        write!(fmt, "blocked by {kind}, where {source} disables 'unsafe-inline'")
+      }
+    }
+  }
+}
+
+impl<'a> CSP<'a> {
+  #[must_use]
+  /// Checks if the policy allows inline content of the given kind, such as
+  /// the content of a `<script>` element with the given nonce attribute.
+  /// The fallback list of the directive is taken into account, so the
+  /// directive that decides may be another one, such as `default-src`.
+  ///
+  /// Nonces only apply to elements, and hashes of attributes and
+  /// `javascript:` URLs only apply along with `'unsafe-hashes'`.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, DirectiveKind, InlineKind, InlineVerdict, Source};
+  ///
+  /// let csp = CSP::parse(
+  ///   "script-src 'nonce-rAnd0m' 'unsafe-inline' 'strict-dynamic'; style-src 'unsafe-inline'",
+  /// )
+  /// .unwrap();
+  ///
+  /// assert_eq!(
+  ///   csp.check_inline(InlineKind::Script, Some("rAnd0m"), "alert(1)"),
+  ///   InlineVerdict::Allowed(Some((DirectiveKind::ScriptSrc, Source::Nonce("rAnd0m"))))
+  /// );
+  /// assert_eq!(
+  ///   csp.check_inline(InlineKind::ScriptAttribute, None, "alert(1)"),
+  ///   InlineVerdict::Blocked(DirectiveKind::ScriptSrc, Some(Source::StrictDynamic))
+  /// );
+  /// assert!(csp.check_inline(InlineKind::StyleAttribute, None, "color: red").is_allowed());
+  /// ```
+  pub fn check_inline(
+    &self,
+    kind: InlineKind,
+    nonce: Option<&str>,
+    content: &str,
+  ) -> InlineVerdict<'a> {
+    let Some((directive, sources)) = self.effective_directive(kind.directive()) else {
+      return InlineVerdict::Allowed(None);
+    };
+    let is_script = kind.directive().is_script();
+
+    if sources.allows_inline(is_script) {
+      return InlineVerdict::Allowed(Some((directive, Source::UnsafeInline)));
+    }
+
+    let nonce_source = nonce.filter(|_| kind.is_element()).and_then(|nonce| {
+      sources
+        .0
+        .iter()
+        .find(|source| matches!(source, Source::Nonce(value) if *value == nonce))
+    });
+    let unsafe_hashes = sources.0.contains(&Source::UnsafeHashes);
+    let hash_source = sources.matching_hash(content);
+
+    match (nonce_source, hash_source) {
+      (Some(source), _) => InlineVerdict::Allowed(Some((directive, source.clone()))),
+      (None, Some(source)) if kind.is_element() || unsafe_hashes => {
+        InlineVerdict::Allowed(Some((directive, source.clone())))
+      }
+      (None, Some(_)) => InlineVerdict::Blocked(directive, Some(Source::UnsafeHashes)),
+      (None, None) => {
+        InlineVerdict::Blocked(directive, sources.disables_inline(is_script))
+      }
+    }
+  }
+}
+
+impl<'a> Sources<'a> {
+  /// The first hash source of the content, with an algorithm that browsers
+  /// support.
+  fn matching_hash(&self, content: &str) -> Option<&Source<'a>> {
+    self.0.iter().find(|source| {
+      let Source::Hash((algorithm, expected)) = source else {
+        return false;
+      };
+      HashAlgorithm::from_name(algorithm).is_some_and(|algorithm| {
+        // Hashes may also be written in base64url.
+        let expected = expected.replace('-', "+").replace('_', "/");
+        algorithm.digest(content.as_bytes()) == expected
+      })
+    })
+  }
+
+  /// The source that makes browsers ignore `'unsafe-inline'`, if it is there.
+  fn disables_inline(&self, is_script: bool) -> Option<Source<'a>> {
+    if !self.0.contains(&Source::UnsafeInline) {
+      return None;
+    }
+    if self.is_strict_dynamic(is_script) {
+      return Some(Source::StrictDynamic);
+    }
+    self
+      .0
+      .iter()
+      .find(|source| matches!(source, Source::Nonce(_) | Source::Hash(_)))
+      .cloned()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hashes() {
+    let csp = CSP::parse(
+      "default-src 'none'; script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF-pI='; \
+       style-src-attr 'unsafe-hashes' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='",
+    )
+    .unwrap();
+    let hash = |value| Source::Hash(("sha256", value));
+
+    assert_eq!(
+      csp.check_inline(InlineKind::Script, None, "alert(1)"),
+      InlineVerdict::Allowed(Some((
+        DirectiveKind::ScriptSrc,
+        hash("bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF-pI=")
+      )))
+    );
+    assert_eq!(
+      csp.check_inline(InlineKind::ScriptAttribute, None, "alert(1)"),
+      InlineVerdict::Blocked(DirectiveKind::ScriptSrc, Some(Source::UnsafeHashes))
+    );
+    assert_eq!(
+      csp.check_inline(InlineKind::StyleAttribute, None, "alert(1)"),
+      InlineVerdict::Allowed(Some((
+        DirectiveKind::StyleSrcAttr,
+        hash("bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=")
+      )))
+    );
+    assert_eq!(
+      csp.check_inline(InlineKind::Style, Some("rAnd0m"), "p {}"),
+      InlineVerdict::Blocked(DirectiveKind::DefaultSrc, None)
+    );
+    assert_eq!(
+      csp.check_inline(InlineKind::Navigation, None, "javascript:alert(1)"),
+      InlineVerdict::Blocked(DirectiveKind::ScriptSrc, None)
+    );
+  }
+
+  #[test]
+  fn unsafe_inline() {
+    let csp =
+      CSP::parse("script-src 'unsafe-inline' 'nonce-abc'; style-src 'unsafe-inline'")
+        .unwrap();
+
+    assert_eq!(
+      csp.check_inline(InlineKind::ScriptAttribute, Some("abc"), "a()"),
+      InlineVerdict::Blocked(DirectiveKind::ScriptSrc, Some(Source::Nonce("abc")))
+    );
+    assert_eq!(
+      csp.check_inline(InlineKind::Style, None, "p {}"),
+      InlineVerdict::Allowed(Some((DirectiveKind::StyleSrc, Source::UnsafeInline)))
+    );
+    assert_eq!(
+      CSP::parse("img-src 'none'").unwrap().check_inline(InlineKind::Script, None, "a()"),
+      InlineVerdict::Allowed(None)
+    );
+  }
+}
@@ -0,0 +1,259 @@
+//! Reading JSON documents, such as HAR files from browser devtools, without
+//! any dependencies.
+
+#[derive(Debug, Clone, PartialEq)]
+/// A JSON value.
+pub enum Value {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Self>),
+  /// The members of an object, in the order they were in the document.
+  Object(Vec<(String, Self)>),
+}
+
+impl Value {
+  /// The member of an object with the given name, if any.
+  pub fn get(&self, name: &str) -> Option<&Self> {
+    match self {
+      Self::Object(members) => {
+        members.iter().find(|(existing, _)| existing == name).map(|(_, value)| value)
+      }
+      _ => None,
+    }
+  }
+
+  /// The value of a string.
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(string) => Some(string),
+      _ => None,
+    }
+  }
+
+  /// The values of an array.
+  pub fn as_array(&self) -> Option<&[Self]> {
+    match self {
+      Self::Array(values) => Some(values),
+      _ => None,
+    }
+  }
+}
+
+/// Parses a JSON document, returning the byte offset of the first error if
+/// it isn't valid.
+pub fn parse(json: &str) -> Result<Value, usize> {
+  let mut parser = Parser { json: json.as_bytes(), position: 0, depth: 0 };
+  let value = parser.value()?;
+  parser.whitespace();
+  if parser.position == json.len() { Ok(value) } else { Err(parser.position) }
+}
+
+/// How deeply arrays and objects can be nested, so that hostile documents
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+  json: &'a [u8],
+  position: usize,
+  depth: usize,
+}
+
+impl Parser<'_> {
+  fn peek(&self) -> Option<u8> {
+    self.json.get(self.position).copied()
+  }
+
+  fn whitespace(&mut self) {
+    while self.peek().is_some_and(|byte| matches!(byte, b' ' | b'\t' | b'\n' | b'\r')) {
+      self.position += 1;
+    }
+  }
+
+  fn expect(&mut self, literal: &str) -> Result<(), usize> {
+    if self.json[self.position..].starts_with(literal.as_bytes()) {
+      self.position += literal.len();
+      Ok(())
+    } else {
+      Err(self.position)
+    }
+  }
+
+  fn value(&mut self) -> Result<Value, usize> {
+    self.whitespace();
+    match self.peek() {
+      Some(b'n') => self.expect("null").map(|()| Value::Null),
+      Some(b't') => self.expect("true").map(|()| Value::Bool(true)),
+      Some(b'f') => self.expect("false").map(|()| Value::Bool(false)),
+      Some(b'"') => self.string().map(Value::String),
+      Some(b'[' | b'{') if self.depth == MAX_DEPTH => Err(self.position),
+      Some(b'[') => {
+        self.depth += 1;
+        let values = self.list(b']', Self::value)?;
+        self.depth -= 1;
+        Ok(Value::Array(values))
+      }
+      Some(b'{') => {
+        self.depth += 1;
+        let members = self.list(b'}', |parser| {
+          parser.whitespace();
+          let name = parser.string()?;
+          parser.whitespace();
+          parser.expect(":")?;
+          Ok((name, parser.value()?))
+        })?;
+        self.depth -= 1;
+        Ok(Value::Object(members))
+      }
+      Some(b'-' | b'0'..=b'9') => self.number(),
+      _ => Err(self.position),
+    }
+  }
+
+  /// The items of an array or object, after its opening bracket.
+  fn list<T>(
+    &mut self,
+    end: u8,
+    mut item: impl FnMut(&mut Self) -> Result<T, usize>,
+  ) -> Result<Vec<T>, usize> {
+    self.position += 1;
+    let mut items = Vec::new();
+
+    self.whitespace();
+    if self.peek() == Some(end) {
+      self.position += 1;
+      return Ok(items);
+    }
+
+    loop {
+      items.push(item(self)?);
+      self.whitespace();
+      match self.peek() {
+        Some(b',') => self.position += 1,
+        Some(byte) if byte == end => {
+          self.position += 1;
+          return Ok(items);
+        }
+        _ => return Err(self.position),
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<Value, usize> {
+    let start = self.position;
+    while self.peek().is_some_and(|byte| {
+      byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E')
+    }) {
+      self.position += 1;
+    }
+
+    std::str::from_utf8(&self.json[start..self.position])
+      .ok()
+      .and_then(|number| number.parse().ok())
+      .map(Value::Number)
+      .ok_or(start)
+  }
+
+  fn string(&mut self) -> Result<String, usize> {
+    if self.peek() != Some(b'"') {
+      return Err(self.position);
+    }
+    self.position += 1;
+
+    let mut string = Vec::new();
+    loop {
+      let byte = self.peek().ok_or(self.position)?;
+      self.position += 1;
+      match byte {
+        b'"' => break,
+        b'\\' => {
+          let escaped = self.peek().ok_or(self.position)?;
+          self.position += 1;
+          let char = match escaped {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => self.unicode_escape()?,
+            _ => return Err(self.position - 1),
+          };
+          string.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+        0..=0x1f => return Err(self.position - 1),
+        byte => string.push(byte),
+      }
+    }
+
+    // The input is a `str`, and escapes are encoded from `char`s.
+    String::from_utf8(string).map_err(|_| self.position)
+  }
+
+  /// The character of a `\u` escape, combining surrogate pairs. Lone
+  /// surrogates are replaced, as they can't be in a `String`.
+  fn unicode_escape(&mut self) -> Result<char, usize> {
+    let high = self.hex()?;
+    if !(0xd800..0xdc00).contains(&high) {
+      return Ok(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+
+    if self.json[self.position..].starts_with(b"\\u") {
+      let position = self.position;
+      self.position += 2;
+      let low = self.hex()?;
+      if (0xdc00..0xe000).contains(&low) {
+        let code = 0x1_0000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+        return Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+      }
+      self.position = position;
+    }
+    Ok(char::REPLACEMENT_CHARACTER)
+  }
+
+  fn hex(&mut self) -> Result<u32, usize> {
+    let digits = self.json.get(self.position..self.position + 4).ok_or(self.position)?;
+    let code = std::str::from_utf8(digits)
+      .ok()
+      .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+      .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+      .ok_or(self.position)?;
+    self.position += 4;
+    Ok(code)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parsing() {
+    let value =
+      parse(r#" {"a": [1, -2.5e1, true, false, null], "b": "x\"é😀\ud800\n", "c": {}} "#)
+        .unwrap();
+
+    assert_eq!(
+      value.get("a"),
+      Some(&Value::Array(vec![
+        Value::Number(1.0),
+        Value::Number(-25.0),
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Null,
+      ]))
+    );
+    assert_eq!(value.get("b").and_then(Value::as_str), Some("x\"é😀\u{fffd}\n"));
+    assert_eq!(value.get("c"), Some(&Value::Object(Vec::new())));
+    assert_eq!(value.get("d"), None);
+
+    assert_eq!(parse("[1, 2,]"), Err(6));
+    assert_eq!(parse("{\"a\" 1}"), Err(5));
+    assert_eq!(parse("\"a\nb\""), Err(2));
+    assert_eq!(parse("[] []"), Err(3));
+    assert!(parse(&"[".repeat(1000)).is_err());
+  }
+}
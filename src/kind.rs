@@ -0,0 +1,405 @@
+use std::fmt;
+
+use crate::{Directive, Source, Sources};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The kind of a [`Directive`], without any of its values.
+///
+/// Used to look up, replace and remove directives of a [`CSP`].
+///
+/// [`CSP`]: crate::CSP
+pub enum DirectiveKind {
+  /// `base-uri`, see [`Directive::BaseUri`].
+  BaseUri,
+  /// `block-all-mixed-content`, see [`Directive::BlockAllMixedContent`].
+  BlockAllMixedContent,
+  /// `child-src`, see [`Directive::ChildSrc`].
+  ChildSrc,
+  /// `connect-src`, see [`Directive::ConnectSrc`].
+  ConnectSrc,
+  /// `default-src`, see [`Directive::DefaultSrc`].
+  DefaultSrc,
+  /// `font-src`, see [`Directive::FontSrc`].
+  FontSrc,
+  /// `form-action`, see [`Directive::FormAction`].
+  FormAction,
+  /// `frame-ancestors`, see [`Directive::FrameAncestors`].
+  FrameAncestors,
+  /// `frame-src`, see [`Directive::FrameSrc`].
+  FrameSrc,
+  /// `img-src`, see [`Directive::ImgSrc`].
+  ImgSrc,
+  /// `manifest-src`, see [`Directive::ManifestSrc`].
+  ManifestSrc,
+  /// `media-src`, see [`Directive::MediaSrc`].
+  MediaSrc,
+  /// `navigate-to`, see [`Directive::NavigateTo`].
+  NavigateTo,
+  /// `object-src`, see [`Directive::ObjectSrc`].
+  ObjectSrc,
+  /// `plugin-types`, see [`Directive::PluginTypes`].
+  PluginTypes,
+  /// `prefetch-src`, see [`Directive::PrefetchSrc`].
+  PrefetchSrc,
+  /// `report-to`, see [`Directive::ReportTo`].
+  ReportTo,
+  /// `report-uri`, see [`Directive::ReportUri`].
+  ReportUri,
+  /// `require-sri-for`, see [`Directive::RequireSriFor`].
+  RequireSriFor,
+  /// `sandbox`, see [`Directive::Sandbox`].
+  Sandbox,
+  /// `script-src`, see [`Directive::ScriptSrc`].
+  ScriptSrc,
+  /// `script-src-attr`, see [`Directive::ScriptSrcAttr`].
+  ScriptSrcAttr,
+  /// `script-src-elem`, see [`Directive::ScriptSrcElem`].
+  ScriptSrcElem,
+  /// `style-src`, see [`Directive::StyleSrc`].
+  StyleSrc,
+  /// `style-src-attr`, see [`Directive::StyleSrcAttr`].
+  StyleSrcAttr,
+  /// `style-src-elem`, see [`Directive::StyleSrcElem`].
+  StyleSrcElem,
+  /// `trusted-types`, see [`Directive::TrustedTypes`].
+  TrustedTypes,
+  /// `upgrade-insecure-requests`, see [`Directive::UpgradeInsecureRequests`].
+  UpgradeInsecureRequests,
+  /// `worker-src`, see [`Directive::WorkerSrc`].
+  WorkerSrc,
+}
+
+impl DirectiveKind {
+  /// Every directive kind, in alphabetical order.
+  pub const ALL: [Self; 29] = [
+    Self::BaseUri,
+    Self::BlockAllMixedContent,
+    Self::ChildSrc,
+    Self::ConnectSrc,
+    Self::DefaultSrc,
+    Self::FontSrc,
+    Self::FormAction,
+    Self::FrameAncestors,
+    Self::FrameSrc,
+    Self::ImgSrc,
+    Self::ManifestSrc,
+    Self::MediaSrc,
+    Self::NavigateTo,
+    Self::ObjectSrc,
+    Self::PluginTypes,
+    Self::PrefetchSrc,
+    Self::ReportTo,
+    Self::ReportUri,
+    Self::RequireSriFor,
+    Self::Sandbox,
+    Self::ScriptSrc,
+    Self::ScriptSrcAttr,
+    Self::ScriptSrcElem,
+    Self::StyleSrc,
+    Self::StyleSrcAttr,
+    Self::StyleSrcElem,
+    Self::TrustedTypes,
+    Self::UpgradeInsecureRequests,
+    Self::WorkerSrc,
+  ];
+
+  #[must_use]
+  /// The name of the directive, as it appears in the header.
+  pub const fn name(self) -> &'static str {
+    match self {
+      Self::BaseUri => "base-uri",
+      Self::BlockAllMixedContent => "block-all-mixed-content",
+      Self::ChildSrc => "child-src",
+      Self::ConnectSrc => "connect-src",
+      Self::DefaultSrc => "default-src",
+      Self::FontSrc => "font-src",
+      Self::FormAction => "form-action",
+      Self::FrameAncestors => "frame-ancestors",
+      Self::FrameSrc => "frame-src",
+      Self::ImgSrc => "img-src",
+      Self::ManifestSrc => "manifest-src",
+      Self::MediaSrc => "media-src",
+      Self::NavigateTo => "navigate-to",
+      Self::ObjectSrc => "object-src",
+      Self::PluginTypes => "plugin-types",
+      Self::PrefetchSrc => "prefetch-src",
+      Self::ReportTo => "report-to",
+      Self::ReportUri => "report-uri",
+      Self::RequireSriFor => "require-sri-for",
+      Self::Sandbox => "sandbox",
+      Self::ScriptSrc => "script-src",
+      Self::ScriptSrcAttr => "script-src-attr",
+      Self::ScriptSrcElem => "script-src-elem",
+      Self::StyleSrc => "style-src",
+      Self::StyleSrcAttr => "style-src-attr",
+      Self::StyleSrcElem => "style-src-elem",
+      Self::TrustedTypes => "trusted-types",
+      Self::UpgradeInsecureRequests => "upgrade-insecure-requests",
+      Self::WorkerSrc => "worker-src",
+    }
+  }
+
+  #[must_use]
+  /// Looks up a directive kind by its (ASCII case-insensitive) name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+  }
+
+  #[must_use]
+  /// The directives that are consulted, in order, when this one is absent.
+  ///
+  /// The first element is always the directive itself. Directives that do not
+  /// fall back to anything only contain themselves.
+  pub const fn fallback_list(self) -> &'static [Self] {
+    match self {
+      Self::ScriptSrcElem => &[Self::ScriptSrcElem, Self::ScriptSrc, Self::DefaultSrc],
+      Self::ScriptSrcAttr => &[Self::ScriptSrcAttr, Self::ScriptSrc, Self::DefaultSrc],
+      Self::StyleSrcElem => &[Self::StyleSrcElem, Self::StyleSrc, Self::DefaultSrc],
+      Self::StyleSrcAttr => &[Self::StyleSrcAttr, Self::StyleSrc, Self::DefaultSrc],
+      Self::WorkerSrc => {
+        &[Self::WorkerSrc, Self::ChildSrc, Self::ScriptSrc, Self::DefaultSrc]
+      }
+      Self::FrameSrc => &[Self::FrameSrc, Self::ChildSrc, Self::DefaultSrc],
+      Self::ChildSrc => &[Self::ChildSrc, Self::DefaultSrc],
+      Self::ConnectSrc => &[Self::ConnectSrc, Self::DefaultSrc],
+      Self::FontSrc => &[Self::FontSrc, Self::DefaultSrc],
+      Self::ImgSrc => &[Self::ImgSrc, Self::DefaultSrc],
+      Self::ManifestSrc => &[Self::ManifestSrc, Self::DefaultSrc],
+      Self::MediaSrc => &[Self::MediaSrc, Self::DefaultSrc],
+      Self::ObjectSrc => &[Self::ObjectSrc, Self::DefaultSrc],
+      Self::PrefetchSrc => &[Self::PrefetchSrc, Self::DefaultSrc],
+      Self::ScriptSrc => &[Self::ScriptSrc, Self::DefaultSrc],
+      Self::StyleSrc => &[Self::StyleSrc, Self::DefaultSrc],
+      Self::BaseUri => &[Self::BaseUri],
+      Self::BlockAllMixedContent => &[Self::BlockAllMixedContent],
+      Self::DefaultSrc => &[Self::DefaultSrc],
+      Self::FormAction => &[Self::FormAction],
+      Self::FrameAncestors => &[Self::FrameAncestors],
+      Self::NavigateTo => &[Self::NavigateTo],
+      Self::PluginTypes => &[Self::PluginTypes],
+      Self::ReportTo => &[Self::ReportTo],
+      Self::ReportUri => &[Self::ReportUri],
+      Self::RequireSriFor => &[Self::RequireSriFor],
+      Self::Sandbox => &[Self::Sandbox],
+      Self::TrustedTypes => &[Self::TrustedTypes],
+      Self::UpgradeInsecureRequests => &[Self::UpgradeInsecureRequests],
+    }
+  }
+
+  /// If the directive governs script execution, which changes how some
+  /// keywords such as `'strict-dynamic'` are interpreted.
+  pub(crate) const fn is_script(self) -> bool {
+    matches!(
+      self,
+      Self::ScriptSrc | Self::ScriptSrcAttr | Self::ScriptSrcElem | Self::WorkerSrc
+    )
+  }
+
+  /// If directives of this kind take [`Sources`].
+  pub(crate) const fn has_sources(self) -> bool {
+    !matches!(
+      self,
+      Self::BlockAllMixedContent
+        | Self::PluginTypes
+        | Self::ReportTo
+        | Self::ReportUri
+        | Self::RequireSriFor
+        | Self::Sandbox
+        | Self::TrustedTypes
+        | Self::UpgradeInsecureRequests
+    )
+  }
+
+  /// Creates a directive of this kind with the given sources, if this kind of
+  /// directive takes sources.
+  pub(crate) fn with_sources(self, sources: Sources<'_>) -> Option<Directive<'_>> {
+    let mut directive = self.with_source_slice(&[])?;
+    *directive.sources_mut()? = sources;
+    Some(directive)
+  }
+
+  /// Like [`DirectiveKind::with_sources`], borrowing the sources, which works
+  /// in a `const`.
+  pub(crate) const fn with_source_slice<'a>(
+    self,
+    sources: &'a [Source<'a>],
+  ) -> Option<Directive<'a>> {
+    Some(match self {
+      Self::BaseUri => Directive::BaseUri(Sources::from_slice(sources)),
+      Self::ChildSrc => Directive::ChildSrc(Sources::from_slice(sources)),
+      Self::ConnectSrc => Directive::ConnectSrc(Sources::from_slice(sources)),
+      Self::DefaultSrc => Directive::DefaultSrc(Sources::from_slice(sources)),
+      Self::FontSrc => Directive::FontSrc(Sources::from_slice(sources)),
+      Self::FormAction => Directive::FormAction(Sources::from_slice(sources)),
+      Self::FrameAncestors => Directive::FrameAncestors(Sources::from_slice(sources)),
+      Self::FrameSrc => Directive::FrameSrc(Sources::from_slice(sources)),
+      Self::ImgSrc => Directive::ImgSrc(Sources::from_slice(sources)),
+      Self::ManifestSrc => Directive::ManifestSrc(Sources::from_slice(sources)),
+      Self::MediaSrc => Directive::MediaSrc(Sources::from_slice(sources)),
+      Self::NavigateTo => Directive::NavigateTo(Sources::from_slice(sources)),
+      Self::ObjectSrc => Directive::ObjectSrc(Sources::from_slice(sources)),
+      Self::PrefetchSrc => Directive::PrefetchSrc(Sources::from_slice(sources)),
+      Self::ScriptSrc => Directive::ScriptSrc(Sources::from_slice(sources)),
+      Self::ScriptSrcAttr => Directive::ScriptSrcAttr(Sources::from_slice(sources)),
+      Self::ScriptSrcElem => Directive::ScriptSrcElem(Sources::from_slice(sources)),
+      Self::StyleSrc => Directive::StyleSrc(Sources::from_slice(sources)),
+      Self::StyleSrcAttr => Directive::StyleSrcAttr(Sources::from_slice(sources)),
+      Self::StyleSrcElem => Directive::StyleSrcElem(Sources::from_slice(sources)),
+      Self::WorkerSrc => Directive::WorkerSrc(Sources::from_slice(sources)),
+      Self::BlockAllMixedContent
+      | Self::PluginTypes
+      | Self::ReportTo
+      | Self::ReportUri
+      | Self::RequireSriFor
+      | Self::Sandbox
+      | Self::TrustedTypes
+      | Self::UpgradeInsecureRequests => return None,
+    })
+  }
+}
+
+impl<'a> Directive<'a> {
+  #[must_use]
+  /// The kind of this directive.
+  pub const fn kind(&self) -> DirectiveKind {
+    match self {
+      Self::BaseUri(_) => DirectiveKind::BaseUri,
+      Self::BlockAllMixedContent => DirectiveKind::BlockAllMixedContent,
+      Self::ChildSrc(_) => DirectiveKind::ChildSrc,
+      Self::ConnectSrc(_) => DirectiveKind::ConnectSrc,
+      Self::DefaultSrc(_) => DirectiveKind::DefaultSrc,
+      Self::FontSrc(_) => DirectiveKind::FontSrc,
+      Self::FormAction(_) => DirectiveKind::FormAction,
+      Self::FrameAncestors(_) => DirectiveKind::FrameAncestors,
+      Self::FrameSrc(_) => DirectiveKind::FrameSrc,
+      Self::ImgSrc(_) => DirectiveKind::ImgSrc,
+      Self::ManifestSrc(_) => DirectiveKind::ManifestSrc,
+      Self::MediaSrc(_) => DirectiveKind::MediaSrc,
+      Self::NavigateTo(_) => DirectiveKind::NavigateTo,
+      Self::ObjectSrc(_) => DirectiveKind::ObjectSrc,
+      Self::PluginTypes(_) => DirectiveKind::PluginTypes,
+      Self::PrefetchSrc(_) => DirectiveKind::PrefetchSrc,
+      Self::ReportTo(_) => DirectiveKind::ReportTo,
+      Self::ReportUri(_) => DirectiveKind::ReportUri,
+      Self::RequireSriFor(_) => DirectiveKind::RequireSriFor,
+      Self::Sandbox(_) => DirectiveKind::Sandbox,
+      Self::ScriptSrc(_) => DirectiveKind::ScriptSrc,
+      Self::ScriptSrcAttr(_) => DirectiveKind::ScriptSrcAttr,
+      Self::ScriptSrcElem(_) => DirectiveKind::ScriptSrcElem,
+      Self::StyleSrc(_) => DirectiveKind::StyleSrc,
+      Self::StyleSrcAttr(_) => DirectiveKind::StyleSrcAttr,
+      Self::StyleSrcElem(_) => DirectiveKind::StyleSrcElem,
+      Self::TrustedTypes(_) => DirectiveKind::TrustedTypes,
+      Self::UpgradeInsecureRequests => DirectiveKind::UpgradeInsecureRequests,
+      Self::WorkerSrc(_) => DirectiveKind::WorkerSrc,
+    }
+  }
+
+  #[must_use]
+  /// The sources of this directive, if it is a directive that has them.
+  pub const fn sources(&self) -> Option<&Sources<'a>> {
+    match self {
+      Self::BaseUri(s)
+      | Self::ChildSrc(s)
+      | Self::ConnectSrc(s)
+      | Self::DefaultSrc(s)
+      | Self::FontSrc(s)
+      | Self::FormAction(s)
+      | Self::FrameAncestors(s)
+      | Self::FrameSrc(s)
+      | Self::ImgSrc(s)
+      | Self::ManifestSrc(s)
+      | Self::MediaSrc(s)
+      | Self::NavigateTo(s)
+      | Self::ObjectSrc(s)
+      | Self::PrefetchSrc(s)
+      | Self::ScriptSrc(s)
+      | Self::ScriptSrcAttr(s)
+      | Self::ScriptSrcElem(s)
+      | Self::StyleSrc(s)
+      | Self::StyleSrcAttr(s)
+      | Self::StyleSrcElem(s)
+      | Self::WorkerSrc(s) => Some(s),
+      Self::BlockAllMixedContent
+      | Self::PluginTypes(_)
+      | Self::ReportTo(_)
+      | Self::ReportUri(_)
+      | Self::RequireSriFor(_)
+      | Self::Sandbox(_)
+      | Self::TrustedTypes(_)
+      | Self::UpgradeInsecureRequests => None,
+    }
+  }
+  /// The sources of this directive mutably, if it is a directive that has
+  /// them.
+  pub const fn sources_mut(&mut self) -> Option<&mut Sources<'a>> {
+    match self {
+      Self::BaseUri(s)
+      | Self::ChildSrc(s)
+      | Self::ConnectSrc(s)
+      | Self::DefaultSrc(s)
+      | Self::FontSrc(s)
+      | Self::FormAction(s)
+      | Self::FrameAncestors(s)
+      | Self::FrameSrc(s)
+      | Self::ImgSrc(s)
+      | Self::ManifestSrc(s)
+      | Self::MediaSrc(s)
+      | Self::NavigateTo(s)
+      | Self::ObjectSrc(s)
+      | Self::PrefetchSrc(s)
+      | Self::ScriptSrc(s)
+      | Self::ScriptSrcAttr(s)
+      | Self::ScriptSrcElem(s)
+      | Self::StyleSrc(s)
+      | Self::StyleSrcAttr(s)
+      | Self::StyleSrcElem(s)
+      | Self::WorkerSrc(s) => Some(s),
+      Self::BlockAllMixedContent
+      | Self::PluginTypes(_)
+      | Self::ReportTo(_)
+      | Self::ReportUri(_)
+      | Self::RequireSriFor(_)
+      | Self::Sandbox(_)
+      | Self::TrustedTypes(_)
+      | Self::UpgradeInsecureRequests => None,
+    }
+  }
+}
+
+impl<'a> crate::CSP<'a> {
+  #[must_use]
+  /// The sources that effectively apply to the given directive kind, taking
+  /// the fallback list into account. `None` means that nothing restricts it.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{DirectiveKind, CSP};
+  ///
+  /// let csp = CSP::parse("default-src 'self'; script-src 'none'").unwrap();
+  /// let sources = csp.effective_sources(DirectiveKind::ScriptSrcElem).unwrap();
+  ///
+  /// assert_eq!(sources.to_string(), "'none'");
+  /// ```
+  pub fn effective_sources(&self, kind: DirectiveKind) -> Option<&Sources<'a>> {
+    kind.fallback_list().iter().find_map(|kind| self.get(*kind)?.sources())
+  }
+
+  /// Like [`CSP::effective_sources`], along with the kind of the directive
+  /// that they are from.
+  pub(crate) fn effective_directive(
+    &self,
+    kind: DirectiveKind,
+  ) -> Option<(DirectiveKind, &Sources<'a>)> {
+    kind
+      .fallback_list()
+      .iter()
+      .find_map(|kind| Some((*kind, self.get(*kind)?.sources()?)))
+  }
+}
+
+impl fmt::Display for DirectiveKind {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "{}", self.name())
+  }
+}
@@ -4,7 +4,7 @@
 //! This library can help you when you don't want to remember some weird
 //! formatting rules of CSP, and want to avoid typos. And it certainly can be
 //! handy if you need to re-use things, for example a list of sources (just
-//! .clone() them everywhere and you're good to go!).
+//! `.clone()` them everywhere and you're good to go!).
 //!
 //! WARNING: this library does not care if you create invalid CSP rules, and
 //! happily allows them and turns them into Strings. But it does force you to
@@ -50,7 +50,70 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 
-use std::fmt;
+use std::{borrow::Cow, fmt};
+
+pub mod bundles;
+mod compare;
+mod compat;
+#[cfg(feature = "serde")]
+mod config;
+mod diff;
+mod entry;
+mod expand;
+mod har;
+mod hash;
+mod host;
+#[cfg(feature = "rewriter")]
+mod inject;
+mod inline;
+mod json;
+mod kind;
+mod lint;
+mod matching;
+mod meta;
+mod minimize;
+mod normalize;
+mod parse;
+mod presets;
+mod profile;
+mod registry;
+mod reports;
+mod rewrite;
+mod scan;
+mod site;
+mod subsume;
+mod support;
+mod validate;
+
+pub use compare::{Change, ChangeKind, Comparison, Item, compare};
+pub use compat::{Ignored, IgnoredReason, Interpretation, Level};
+#[cfg(feature = "serde")]
+pub use config::PolicyConfig;
+pub use diff::{Diff, DirectiveDiff, diff};
+pub use entry::Entry;
+pub use har::{Har, HarEntry, HarError, HarPolicy, HarRequest, HarViolation};
+pub use hash::HashAlgorithm;
+#[cfg(feature = "rewriter")]
+pub use inject::NonceInjector;
+pub use inline::{InlineKind, InlineVerdict};
+pub use kind::DirectiveKind;
+pub use lint::LintWarning;
+pub use matching::Verdict;
+pub use meta::{Disposition, IGNORED_IN_META, MetaError, MetaTag};
+pub use minimize::Minimized;
+pub use parse::{ALLOW_CSP_FROM, ParseError, SEC_REQUIRED_CSP};
+pub use profile::{Adjustment, Overlay, PolicyProfile, ProfileError, ProfileViolation};
+pub use registry::{MergeConflict, PolicyRegistry, RegistryError};
+pub use reports::{ReportError, ViolationReport};
+pub use scan::InlineHashes;
+pub use site::{PageSources, SiteScan};
+pub use support::{Engine, Status, Support};
+#[doc(hidden)]
+pub use validate::{
+  Part as __Part, count as __count, directives as __directives, plugins as __plugins,
+  report_uris as __report_uris, sandbox_allows as __sandbox_allows, sources as __sources,
+  validate as __validate,
+};
 
 #[derive(Debug, Default, Clone)]
 /// The starting point for building a Content Security Policy.
@@ -59,10 +122,32 @@ use std::fmt;
 /// on it to get it as a header compatible string. Doesn't include
 /// content-security-policy: part in it though.
 ///
+/// Policies that never change can be built from slices with
+/// [`CSP::from_slice`] and friends, so they can be put in a `static` and only
+/// need to be turned into a string once. As directives can own their values,
+/// the slice of them has to be a `static` of its own.
+///
+/// # Example usage
+/// ```rust
+/// use std::sync::LazyLock;
+///
+/// use csp::{CSP, Directive, Source, Sources};
+///
+/// static DIRECTIVES: [Directive<'static>; 3] = [
+///   Directive::DefaultSrc(Sources::from_slice(&[Source::Self_])),
+///   Directive::ImgSrc(Sources::from_slice(&[Source::Self_, Source::Scheme("data")])),
+///   Directive::ObjectSrc(Sources::new()),
+/// ];
+/// static POLICY: CSP<'static> = CSP::from_slice(&DIRECTIVES);
+/// static HEADER: LazyLock<String> = LazyLock::new(|| POLICY.to_string());
+///
+/// assert_eq!(*HEADER, "default-src 'self'; img-src 'self' data:; object-src 'none'");
+/// ```
+///
 /// [`Directive`]: Directive
-pub struct CSP<'a>(Vec<Directive<'a>>);
+pub struct CSP<'a>(Cow<'a, [Directive<'a>]>);
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 /// A struct to give source(s) to a [`Directive`] which might require it.
 ///
 /// # Example usage
@@ -75,9 +160,9 @@ pub struct CSP<'a>(Vec<Directive<'a>>);
 /// ```
 ///
 /// [`Directive`]: Directive
-pub struct Sources<'a>(Vec<Source<'a>>);
+pub struct Sources<'a>(Cow<'a, [Source<'a>]>);
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 /// Used for `PluginTypes` [`Directive`].
 ///
 /// # Example usage
@@ -87,9 +172,9 @@ pub struct Sources<'a>(Vec<Source<'a>>);
 ///  to get `application/x-shockwave-flash`
 ///
 /// [`Directive`]: Directive
-pub struct Plugins<'a>(Vec<(&'a str, &'a str)>);
+pub struct Plugins<'a>(Cow<'a, [(&'a str, &'a str)]>);
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 /// Used for `ReportUri` [`Directive`].
 ///
 /// # Example usage
@@ -98,15 +183,15 @@ pub struct Plugins<'a>(Vec<(&'a str, &'a str)>);
 /// ```
 ///
 /// [`Directive`]: Directive
-pub struct ReportUris<'a>(Vec<&'a str>);
+pub struct ReportUris<'a>(Cow<'a, [&'a str]>);
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 /// Used for `Sandbox` [`Directive`].
 ///
 /// [`Directive`]: Directive
-pub struct SandboxAllowedList(Vec<SandboxAllow>);
+pub struct SandboxAllowedList(Cow<'static, [SandboxAllow]>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// Used for `RequireSriFor` [`Directive`].
 ///
 /// [`Directive`]: Directive
@@ -198,7 +283,7 @@ pub enum Source<'a> {
   ReportSample,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// Optionally used for the `Sandbox` directive. Not using it but using the
 /// sandbox directive disallows everything that you could allow with the
 /// optional values.
@@ -246,7 +331,7 @@ pub enum SandboxAllow {
   TopNavigationByUserActivation,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// A CSP directive.
 pub enum Directive<'a> {
   /// Restricts the URLs which can be used in a document's `<base>` element.
@@ -277,10 +362,10 @@ pub enum Directive<'a> {
   ///
   /// - `<a>` ping,
   /// - Fetch,
-  /// - XMLHttpRequest,
+  /// - `XMLHttpRequest`,
   /// - WebSocket,
-  /// - EventSource,
-  /// - Navigator.sendBeacon().
+  /// - `EventSource`,
+  /// - `Navigator.sendBeacon()`.
   ///
   /// Note: connect-src 'self' does not resolve to websocket schemas in all browsers, more info: <https://github.com/w3c/webappsec-csp/issues/7>
   ConnectSrc(Sources<'a>),
@@ -344,7 +429,7 @@ pub enum Directive<'a> {
   /// elements.
   ///
   /// To set allowed types for `<object>`, `<embed>`, and `<applet>` elements,
-  /// use the PluginTypes.
+  /// use the `PluginTypes`.
   ///
   /// Elements controlled by object-src are perhaps coincidentally considered
   /// legacy HTML elements and aren't receiving new standardized features
@@ -407,7 +492,7 @@ pub enum Directive<'a> {
   /// preventing the execution of plugins and scripts, and enforcing a
   /// same-origin policy.
   ///
-  /// You can leave the SandboxAllowedList empty
+  /// You can leave the `SandboxAllowedList` empty
   /// (`SandboxAllowedList::new_empty()`) to disallow everything.
   Sandbox(SandboxAllowedList),
   /// Specifies valid sources for JavaScript.
@@ -442,6 +527,9 @@ pub enum Directive<'a> {
   /// the web application codebase, facilitating their monitoring and code
   /// review. This directive declares a white-list of trusted type policy
   /// names created with TrustedTypes.createPolicy from Trusted Types API.
+  ///
+  /// Unlike the values of other directives, the names are a `Vec`, so this
+  /// directive can only be empty in a `static`.
   TrustedTypes(Vec<&'a str>),
   /// Instructs user agents to treat all of a site's insecure URLs (those
   /// served over HTTP) as though they have been replaced with secure URLs
@@ -476,14 +564,21 @@ pub enum Directive<'a> {
 impl<'a> CSP<'a> {
   #[must_use]
   /// Creates a new empty CSP
-  pub fn new() -> Self {
-    Self::default()
+  pub const fn new() -> Self {
+    Self(Cow::Borrowed(&[]))
+  }
+
+  #[must_use]
+  /// Creates a CSP that borrows its directives, which works in a `const` or
+  /// `static` without allocating
+  pub const fn from_slice(directives: &'a [Directive<'a>]) -> Self {
+    Self(Cow::Borrowed(directives))
   }
 
   #[must_use]
   /// Creates a new CSP with a given directive
   pub fn new_with(directive: Directive<'a>) -> Self {
-    Self(vec![directive])
+    Self(Cow::Owned(vec![directive]))
   }
 
   #[deprecated(since = "1.0.0", note = "please use `push_borrowed` instead")]
@@ -495,7 +590,7 @@ impl<'a> CSP<'a> {
 
   /// Pushes a directive to the end of the borrowed CSP
   pub fn push_borrowed<'b>(&'b mut self, directive: Directive<'a>) -> &'b mut Self {
-    self.0.push(directive);
+    self.0.to_mut().push(directive);
     self
   }
 
@@ -510,22 +605,122 @@ impl<'a> CSP<'a> {
   #[must_use]
   /// Pushes a directive to the end of the CSP
   pub fn push(mut self, directive: Directive<'a>) -> Self {
-    self.0.push(directive);
+    self.0.to_mut().push(directive);
     self
   }
+
+  #[must_use]
+  /// Gets the first directive of the given kind, which is the one that
+  /// browsers use when there are duplicates.
+  pub fn get(&self, kind: DirectiveKind) -> Option<&Directive<'a>> {
+    self.0.iter().find(|directive| directive.kind() == kind)
+  }
+
+  /// Gets the first directive of the given kind mutably
+  pub fn get_mut(&mut self, kind: DirectiveKind) -> Option<&mut Directive<'a>> {
+    self.0.to_mut().iter_mut().find(|directive| directive.kind() == kind)
+  }
+
+  /// Removes every directive of the given kind, returning the first of them
+  pub fn remove(&mut self, kind: DirectiveKind) -> Option<Directive<'a>> {
+    let index = self.0.iter().position(|directive| directive.kind() == kind)?;
+    let removed = self.0.to_mut().remove(index);
+    self.0.to_mut().retain(|directive| directive.kind() != kind);
+    Some(removed)
+  }
+
+  /// Replaces the first directive of the same kind in place, or pushes it to
+  /// the end if there is none. Returns the replaced directive.
+  pub fn replace(&mut self, directive: Directive<'a>) -> Option<Directive<'a>> {
+    if let Some(existing) = self.get_mut(directive.kind()) {
+      return Some(std::mem::replace(existing, directive));
+    }
+
+    self.0.to_mut().push(directive);
+    None
+  }
+
+  /// Gets the entry of the given directive kind for in-place manipulation.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, Directive, DirectiveKind, Source, Sources};
+  ///
+  /// let base = CSP::new().push(Directive::DefaultSrc(Sources::new_with(Source::Self_)));
+  ///
+  /// let mut csp = base.clone();
+  /// csp
+  ///   .entry(DirectiveKind::ConnectSrc)
+  ///   .or_insert_with(|| Directive::ConnectSrc(Sources::new_with(Source::Self_)))
+  ///   .sources_mut()
+  ///   .unwrap()
+  ///   .push_borrowed(Source::Host("https://api.example.org"));
+  ///
+  /// assert_eq!(
+  ///   csp.to_string(),
+  ///   "default-src 'self'; connect-src 'self' https://api.example.org"
+  /// );
+  /// ```
+  pub const fn entry(&mut self, kind: DirectiveKind) -> Entry<'_, 'a> {
+    Entry { csp: self, kind }
+  }
+
+  /// Iterates over the directives in order
+  pub fn iter(&self) -> std::slice::Iter<'_, Directive<'a>> {
+    self.0.iter()
+  }
+
+  /// Iterates over the directives in order mutably
+  pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Directive<'a>> {
+    self.0.to_mut().iter_mut()
+  }
+}
+
+impl<'a> IntoIterator for CSP<'a> {
+  type Item = Directive<'a>;
+  type IntoIter = std::vec::IntoIter<Directive<'a>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.0.into_owned().into_iter()
+  }
+}
+
+impl<'b, 'a> IntoIterator for &'b CSP<'a> {
+  type Item = &'b Directive<'a>;
+  type IntoIter = std::slice::Iter<'b, Directive<'a>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl<'b, 'a> IntoIterator for &'b mut CSP<'a> {
+  type Item = &'b mut Directive<'a>;
+  type IntoIter = std::slice::IterMut<'b, Directive<'a>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter_mut()
+  }
 }
 
 impl<'a> Sources<'a> {
   #[must_use]
   /// Creates a new empty Sources
   pub const fn new() -> Self {
-    Self(vec![])
+    Self(Cow::Borrowed(&[]))
   }
 
   #[must_use]
   /// Creates new Sources with a source
   pub fn new_with(source: Source<'a>) -> Self {
-    Self(vec![source])
+    Self(Cow::Owned(vec![source]))
+  }
+
+  #[must_use]
+  /// Creates Sources that borrow the given sources, which works in a `const`
+  /// or `static` without allocating
+  pub const fn from_slice(sources: &'a [Source<'a>]) -> Self {
+    Self(Cow::Borrowed(sources))
   }
 
   #[deprecated(since = "1.0.0", note = "please use `push_borrowed` instead")]
@@ -537,7 +732,7 @@ impl<'a> Sources<'a> {
 
   /// Pushes a source to the end of the borrowed Sources
   pub fn push_borrowed<'b>(&'b mut self, source: Source<'a>) -> &'b mut Self {
-    self.0.push(source);
+    self.0.to_mut().push(source);
     self
   }
 
@@ -552,7 +747,7 @@ impl<'a> Sources<'a> {
   #[must_use]
   /// Pushes a source to the end of the Sources
   pub fn push(mut self, source: Source<'a>) -> Self {
-    self.0.push(source);
+    self.0.to_mut().push(source);
     self
   }
 }
@@ -561,13 +756,20 @@ impl<'a> Plugins<'a> {
   #[must_use]
   /// Creates a new Plugins with a plugin
   pub fn new_with(plugin: (&'a str, &'a str)) -> Self {
-    Self(vec![plugin])
+    Self(Cow::Owned(vec![plugin]))
   }
 
   #[must_use]
   /// Creates a new empty plugins
   pub const fn new() -> Self {
-    Self(vec![])
+    Self(Cow::Borrowed(&[]))
+  }
+
+  #[must_use]
+  /// Creates Plugins that borrow the given plugins, which works in a `const`
+  /// or `static` without allocating
+  pub const fn from_slice(plugins: &'a [(&'a str, &'a str)]) -> Self {
+    Self(Cow::Borrowed(plugins))
   }
 
   #[deprecated(since = "1.0.0", note = "please use `push_borrowed` instead")]
@@ -579,7 +781,7 @@ impl<'a> Plugins<'a> {
 
   /// Pushes a plugin to the end of the borrowed Plugins
   pub fn push_borrowed<'b>(&'b mut self, plugin: (&'a str, &'a str)) -> &'b mut Self {
-    self.0.push(plugin);
+    self.0.to_mut().push(plugin);
     self
   }
 
@@ -594,7 +796,7 @@ impl<'a> Plugins<'a> {
   #[must_use]
   /// Pushes a plugin to the end of the Plugins
   pub fn push(mut self, plugin: (&'a str, &'a str)) -> Self {
-    self.0.push(plugin);
+    self.0.to_mut().push(plugin);
     self
   }
 }
@@ -603,13 +805,20 @@ impl SandboxAllowedList {
   #[must_use]
   /// Creates a new `SandboxAllowedList` with only a certain sandbox allowance
   pub fn new_with(sandbox_allow: SandboxAllow) -> Self {
-    Self(vec![sandbox_allow])
+    Self(Cow::Owned(vec![sandbox_allow]))
   }
 
   #[must_use]
   /// Creates a new empty `SandboxAllowedList`
   pub const fn new() -> Self {
-    Self(vec![])
+    Self(Cow::Borrowed(&[]))
+  }
+
+  #[must_use]
+  /// Creates a `SandboxAllowedList` that borrows the given allowances, which
+  /// works in a `const` or `static` without allocating
+  pub const fn from_slice(sandbox_allows: &'static [SandboxAllow]) -> Self {
+    Self(Cow::Borrowed(sandbox_allows))
   }
 
   #[deprecated(since = "1.0.0", note = "please use `push_borrowed` instead")]
@@ -622,7 +831,7 @@ impl SandboxAllowedList {
   /// Pushes a sandbox allow type to the end of the borrowed
   /// `SandboxAllowedList`
   pub fn push_borrowed(&'_ mut self, sandbox_allow: SandboxAllow) -> &'_ mut Self {
-    self.0.push(sandbox_allow);
+    self.0.to_mut().push(sandbox_allow);
     self
   }
 
@@ -637,7 +846,7 @@ impl SandboxAllowedList {
   #[must_use]
   /// Pushes a sandbox allow type to the end of the `SandboxAllowedList`
   pub fn push(mut self, sandbox_allow: SandboxAllow) -> Self {
-    self.0.push(sandbox_allow);
+    self.0.to_mut().push(sandbox_allow);
     self
   }
 }
@@ -646,13 +855,20 @@ impl<'a> ReportUris<'a> {
   #[must_use]
   /// Creates a new `ReportUris` with a certain uri
   pub fn new_with(report_uri: &'a str) -> Self {
-    ReportUris(vec![report_uri])
+    Self(Cow::Owned(vec![report_uri]))
   }
 
   #[must_use]
   /// Creates a new empty `ReportUris`
   pub const fn new() -> Self {
-    ReportUris(vec![])
+    Self(Cow::Borrowed(&[]))
+  }
+
+  #[must_use]
+  /// Creates `ReportUris` that borrow the given uris, which works in a `const`
+  /// or `static` without allocating
+  pub const fn from_slice(report_uris: &'a [&'a str]) -> Self {
+    Self(Cow::Borrowed(report_uris))
   }
 
   #[deprecated(since = "1.0.0", note = "please use `push_borrowed` instead")]
@@ -664,7 +880,7 @@ impl<'a> ReportUris<'a> {
 
   /// Pushes a report uri to the end of the borrowed `ReportUris`
   pub fn push_borrowed<'b>(&'b mut self, report_uri: &'a str) -> &'b mut Self {
-    self.0.push(report_uri);
+    self.0.to_mut().push(report_uri);
     self
   }
 
@@ -679,23 +895,23 @@ impl<'a> ReportUris<'a> {
   #[must_use]
   /// Pushes a report uri to the end of the `ReportUris`
   pub fn push(mut self, report_uri: &'a str) -> Self {
-    self.0.push(report_uri);
+    self.0.to_mut().push(report_uri);
     self
   }
 }
 
-impl<'a> fmt::Display for Source<'a> {
+impl fmt::Display for Source<'_> {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      Self::Host(s) => write!(fmt, "{}", s),
-      Self::Scheme(s) => write!(fmt, "{}:", s),
+      Self::Host(s) => write!(fmt, "{s}"),
+      Self::Scheme(s) => write!(fmt, "{s}:"),
       Self::Self_ => write!(fmt, "'self'"),
       Self::UnsafeEval => write!(fmt, "'unsafe-eval'"),
       Self::WasmUnsafeEval => write!(fmt, "'wasm-unsafe-eval'"),
       Self::UnsafeHashes => write!(fmt, "'unsafe-hashes'"),
       Self::UnsafeInline => write!(fmt, "'unsafe-inline'"),
-      Self::Nonce(s) => write!(fmt, "'nonce-{}'", s),
-      Self::Hash((algo, hash)) => write!(fmt, "'{}-{}'", algo, hash),
+      Self::Nonce(s) => write!(fmt, "'nonce-{s}'"),
+      Self::Hash((algo, hash)) => write!(fmt, "'{algo}-{hash}'"),
       Self::StrictDynamic => write!(fmt, "'strict-dynamic'"),
       Self::ReportSample => write!(fmt, "'report-sample'"),
     }
@@ -738,67 +954,67 @@ impl fmt::Display for SriFor {
   }
 }
 
-impl<'a> fmt::Display for Directive<'a> {
+impl fmt::Display for Directive<'_> {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      Self::BaseUri(s) => write!(fmt, "base-uri {}", s),
+      Self::BaseUri(s) => write!(fmt, "base-uri {s}"),
       Self::BlockAllMixedContent => write!(fmt, "block-all-mixed-content"),
-      Self::ChildSrc(s) => write!(fmt, "child-src {}", s),
-      Self::ConnectSrc(s) => write!(fmt, "connect-src {}", s),
-      Self::DefaultSrc(s) => write!(fmt, "default-src {}", s),
-      Self::FontSrc(s) => write!(fmt, "font-src {}", s),
-      Self::FormAction(s) => write!(fmt, "form-action {}", s),
-      Self::FrameAncestors(s) => write!(fmt, "frame-ancestors {}", s),
-      Self::FrameSrc(s) => write!(fmt, "frame-src {}", s),
-      Self::ImgSrc(s) => write!(fmt, "img-src {}", s),
-      Self::ManifestSrc(s) => write!(fmt, "manifest-src {}", s),
-      Self::MediaSrc(s) => write!(fmt, "media-src {}", s),
-      Self::NavigateTo(s) => write!(fmt, "navigate-to {}", s),
-      Self::ObjectSrc(s) => write!(fmt, "object-src {}", s),
-      Self::PluginTypes(s) => write!(fmt, "plugin-types {}", s),
-      Self::PrefetchSrc(s) => write!(fmt, "prefetch-src {}", s),
-      Self::ReportTo(s) => write!(fmt, "report-to {}", s),
+      Self::ChildSrc(s) => write!(fmt, "child-src {s}"),
+      Self::ConnectSrc(s) => write!(fmt, "connect-src {s}"),
+      Self::DefaultSrc(s) => write!(fmt, "default-src {s}"),
+      Self::FontSrc(s) => write!(fmt, "font-src {s}"),
+      Self::FormAction(s) => write!(fmt, "form-action {s}"),
+      Self::FrameAncestors(s) => write!(fmt, "frame-ancestors {s}"),
+      Self::FrameSrc(s) => write!(fmt, "frame-src {s}"),
+      Self::ImgSrc(s) => write!(fmt, "img-src {s}"),
+      Self::ManifestSrc(s) => write!(fmt, "manifest-src {s}"),
+      Self::MediaSrc(s) => write!(fmt, "media-src {s}"),
+      Self::NavigateTo(s) => write!(fmt, "navigate-to {s}"),
+      Self::ObjectSrc(s) => write!(fmt, "object-src {s}"),
+      Self::PluginTypes(s) => write!(fmt, "plugin-types {s}"),
+      Self::PrefetchSrc(s) => write!(fmt, "prefetch-src {s}"),
+      Self::ReportTo(s) => write!(fmt, "report-to {s}"),
       Self::ReportUri(uris) => {
         write!(fmt, "report-uri ")?;
 
         for uri in &uris.0[0..uris.0.len() - 1] {
-          write!(fmt, "{} ", uri)?;
+          write!(fmt, "{uri} ")?;
         }
 
         let last = uris.0[uris.0.len() - 1];
-        write!(fmt, "{}", last)
+        write!(fmt, "{last}")
       }
-      Self::RequireSriFor(s) => write!(fmt, "require-sri-for {}", s),
+      Self::RequireSriFor(s) => write!(fmt, "require-sri-for {s}"),
       Self::Sandbox(s) => {
         if s.0.is_empty() {
           write!(fmt, "sandbox")
         } else {
-          write!(fmt, "sandbox {}", s)
+          write!(fmt, "sandbox {s}")
         }
       }
-      Self::ScriptSrc(s) => write!(fmt, "script-src {}", s),
-      Self::ScriptSrcAttr(s) => write!(fmt, "script-src-attr {}", s),
-      Self::ScriptSrcElem(s) => write!(fmt, "script-src-elem {}", s),
-      Self::StyleSrc(s) => write!(fmt, "style-src {}", s),
-      Self::StyleSrcAttr(s) => write!(fmt, "style-src-attr {}", s),
-      Self::StyleSrcElem(s) => write!(fmt, "style-src-elem {}", s),
+      Self::ScriptSrc(s) => write!(fmt, "script-src {s}"),
+      Self::ScriptSrcAttr(s) => write!(fmt, "script-src-attr {s}"),
+      Self::ScriptSrcElem(s) => write!(fmt, "script-src-elem {s}"),
+      Self::StyleSrc(s) => write!(fmt, "style-src {s}"),
+      Self::StyleSrcAttr(s) => write!(fmt, "style-src-attr {s}"),
+      Self::StyleSrcElem(s) => write!(fmt, "style-src-elem {s}"),
       Self::TrustedTypes(trusted_types) => {
         write!(fmt, "trusted-types ")?;
 
         for trusted_type in &trusted_types[0..trusted_types.len() - 1] {
-          write!(fmt, "{} ", trusted_type)?;
+          write!(fmt, "{trusted_type} ")?;
         }
 
         let last = trusted_types[trusted_types.len() - 1];
-        write!(fmt, "{}", last)
+        write!(fmt, "{last}")
       }
       Self::UpgradeInsecureRequests => write!(fmt, "upgrade-insecure-requests"),
-      Self::WorkerSrc(s) => write!(fmt, "worker-src {}", s),
+      Self::WorkerSrc(s) => write!(fmt, "worker-src {s}"),
     }
   }
 }
 
-impl<'a> fmt::Display for Plugins<'a> {
+impl fmt::Display for Plugins<'_> {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     if self.0.is_empty() {
       return write!(fmt, "");
@@ -813,18 +1029,18 @@ impl<'a> fmt::Display for Plugins<'a> {
   }
 }
 
-impl<'a> fmt::Display for Sources<'a> {
+impl fmt::Display for Sources<'_> {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     if self.0.is_empty() {
       return write!(fmt, "'none'");
     }
 
     for source in &self.0[0..self.0.len() - 1] {
-      write!(fmt, "{} ", source)?;
+      write!(fmt, "{source} ")?;
     }
 
     let last = &self.0[self.0.len() - 1];
-    write!(fmt, "{}", last)
+    write!(fmt, "{last}")
   }
 }
 
@@ -835,26 +1051,26 @@ impl fmt::Display for SandboxAllowedList {
     }
 
     for directive in &self.0[0..self.0.len() - 1] {
-      write!(fmt, "{} ", directive)?;
+      write!(fmt, "{directive} ")?;
     }
 
     let last = &self.0[self.0.len() - 1];
-    write!(fmt, "{}", last)
+    write!(fmt, "{last}")
   }
 }
 
-impl<'a> fmt::Display for CSP<'a> {
+impl fmt::Display for CSP<'_> {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
     if self.0.is_empty() {
       return write!(fmt, "");
     }
 
     for directive in &self.0[0..self.0.len() - 1] {
-      write!(fmt, "{}; ", directive)?;
+      write!(fmt, "{directive}; ")?;
     }
 
     let last = &self.0[self.0.len() - 1];
-    write!(fmt, "{}", last)
+    write!(fmt, "{last}")
   }
 }
 
@@ -885,7 +1101,7 @@ mod tests {
 
     csp.push_borrowed(Directive::FontSrc(Sources::new_with(font_src)));
 
-    println!("{}", csp);
+    println!("{csp}");
 
     let csp = csp.to_string();
 
@@ -980,4 +1196,56 @@ mod tests {
     let csp = CSP::new_with(Directive::UpgradeInsecureRequests);
     assert_eq!(csp.to_string(), "upgrade-insecure-requests");
   }
+
+  #[test]
+  fn lookup() {
+    let mut csp = CSP::new()
+      .push(Directive::DefaultSrc(Sources::new_with(Source::Self_)))
+      .push(Directive::ImgSrc(Sources::new()))
+      .push(Directive::ImgSrc(Sources::new_with(Source::Scheme("data"))));
+
+    assert_eq!(csp.get(DirectiveKind::ImgSrc), Some(&Directive::ImgSrc(Sources::new())));
+    assert_eq!(csp.get(DirectiveKind::ScriptSrc), None);
+
+    let old = csp.replace(Directive::DefaultSrc(Sources::new()));
+    assert_eq!(old, Some(Directive::DefaultSrc(Sources::new_with(Source::Self_))));
+
+    assert_eq!(
+      csp.remove(DirectiveKind::ImgSrc),
+      Some(Directive::ImgSrc(Sources::new()))
+    );
+    assert_eq!(csp.remove(DirectiveKind::ImgSrc), None);
+
+    csp
+      .entry(DirectiveKind::Sandbox)
+      .or_insert(Directive::Sandbox(SandboxAllowedList::new()));
+    let _ = csp.entry(DirectiveKind::Sandbox).and_modify(|directive| {
+      if let Directive::Sandbox(list) = directive {
+        list.push_borrowed(SandboxAllow::Forms);
+      }
+    });
+
+    let kinds: Vec<_> = csp.iter().map(Directive::kind).collect();
+    assert_eq!(kinds, [DirectiveKind::DefaultSrc, DirectiveKind::Sandbox]);
+    assert_eq!(csp.to_string(), "default-src 'none'; sandbox allow-forms");
+  }
+
+  #[test]
+  fn static_policy() {
+    static DIRECTIVES: [Directive<'static>; 2] = [
+      Directive::ScriptSrc(Sources::from_slice(&[Source::Self_, Source::StrictDynamic])),
+      Directive::Sandbox(SandboxAllowedList::from_slice(&[SandboxAllow::Scripts])),
+    ];
+    static POLICY: CSP<'static> = CSP::from_slice(&DIRECTIVES);
+
+    assert_eq!(
+      POLICY.to_string(),
+      "script-src 'self' 'strict-dynamic'; sandbox allow-scripts"
+    );
+
+    let mut csp = POLICY.clone();
+    csp.push_borrowed(Directive::ReportUri(ReportUris::from_slice(&["/report"])));
+    assert_eq!(csp.iter().count(), 3);
+    assert_eq!(POLICY.iter().count(), 2);
+  }
 }
@@ -0,0 +1,166 @@
+//! Finding common weaknesses in a policy.
+
+use std::fmt;
+
+use crate::{CSP, DirectiveKind, Source, Status};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A weakness of a policy, as found by [`CSP::lint`].
+pub enum LintWarning<'a> {
+  /// The directive is deprecated, and has been replaced by something else.
+  Deprecated(DirectiveKind),
+  /// The directive is obsolete, and browsers ignore it.
+  Obsolete(DirectiveKind),
+  /// Neither the directive nor anything it falls back to is in the policy.
+  Unrestricted(DirectiveKind),
+  /// The directive allows inline scripts through `'unsafe-inline'`.
+  UnsafeInline(DirectiveKind),
+  /// The directive allows `eval` and similar through `'unsafe-eval'`.
+  UnsafeEval(DirectiveKind),
+  /// The directive allows any host, or any URL of a scheme such as `https:`
+  /// or `data:`, which attackers can host content on too.
+  Permissive(DirectiveKind, Source<'a>),
+}
+
+impl fmt::Display for LintWarning<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Deprecated(kind) => write!(fmt, "{kind} is deprecated"),
+      Self::Obsolete(kind) => write!(fmt, "{kind} is obsolete and has no effect"),
+      Self::Unrestricted(kind) => write!(fmt, "{kind} is not restricted"),
+      Self::UnsafeInline(kind) => {
+        write!(fmt, "{kind} allows inline scripts with 'unsafe-inline'")
+      }
+      Self::UnsafeEval(kind) => write!(fmt, "{kind} allows eval with 'unsafe-eval'"),
+      Self::Permissive(kind, Source::Scheme(scheme)) => {
+        write!(fmt, "{kind} allows any {scheme}: URL")
+      }
+      Self::Permissive(kind, source) => {
+        write!(fmt, "{kind} allows any host with {source}")
+      }
+    }
+  }
+}
+
+impl<'a> CSP<'a> {
+  #[must_use]
+  /// Looks for common weaknesses: deprecated and obsolete directives,
+  /// scripts, plugins and `<base>` elements that nothing restricts, and
+  /// script and plugin sources that allow inline code, `eval` or any host.
+  ///
+  /// Sources that `'strict-dynamic'` makes browsers ignore are not reported.
+  /// What else browsers ignore can be found with [`CSP::ignored_by`].
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse("script-src 'self' https: 'unsafe-inline'; object-src 'none'").unwrap();
+  /// let warnings: Vec<_> = csp.lint().iter().map(ToString::to_string).collect();
+  ///
+  /// assert_eq!(
+  ///   warnings,
+  ///   [
+  ///     "script-src allows inline scripts with 'unsafe-inline'",
+  ///     "script-src allows any https: URL",
+  ///     "base-uri is not restricted",
+  ///   ]
+  /// );
+  /// assert!(CSP::strict_nonce_based("rAnd0m").lint().is_empty());
+  /// ```
+  pub fn lint(&self) -> Vec<LintWarning<'a>> {
+    let mut warnings = Vec::new();
+
+    for directive in self {
+      let kind = directive.kind();
+      let warning = match kind.status() {
+        Status::Standard => continue,
+        Status::Deprecated => LintWarning::Deprecated(kind),
+        Status::Obsolete => LintWarning::Obsolete(kind),
+      };
+      if !warnings.contains(&warning) {
+        warnings.push(warning);
+      }
+    }
+
+    for requested in [DirectiveKind::ScriptSrc, DirectiveKind::ObjectSrc] {
+      let Some((kind, sources)) = self.effective_directive(requested) else {
+        warnings.push(LintWarning::Unrestricted(requested));
+        continue;
+      };
+      let is_script = requested.is_script();
+
+      let mut found = Vec::new();
+      if is_script && sources.allows_inline(true) {
+        found.push(LintWarning::UnsafeInline(kind));
+      }
+      if is_script && sources.0.contains(&Source::UnsafeEval) {
+        found.push(LintWarning::UnsafeEval(kind));
+      }
+      if !sources.is_strict_dynamic(is_script) {
+        found.extend(
+          sources
+            .0
+            .iter()
+            .filter(|source| match source {
+              Source::Host(host) => *host == "*",
+              Source::Scheme(scheme) => ["http", "https", "data"]
+                .iter()
+                .any(|any| any.eq_ignore_ascii_case(scheme)),
+              _ => false,
+            })
+            .map(|source| LintWarning::Permissive(kind, source.clone())),
+        );
+      }
+
+      // Both can fall back to the same `default-src`.
+      for warning in found {
+        if !warnings.contains(&warning) {
+          warnings.push(warning);
+        }
+      }
+    }
+
+    if self.get(DirectiveKind::BaseUri).is_none() {
+      warnings.push(LintWarning::Unrestricted(DirectiveKind::BaseUri));
+    }
+
+    warnings
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn warnings() {
+    let csp = CSP::parse(
+      "default-src * 'unsafe-eval' 'unsafe-inline'; report-uri /a; report-uri /b; \
+       plugin-types application/pdf; base-uri 'self'",
+    )
+    .unwrap();
+
+    let warnings: Vec<_> = csp.lint().iter().map(ToString::to_string).collect();
+    assert_eq!(
+      warnings,
+      [
+        "report-uri is deprecated",
+        "plugin-types is obsolete and has no effect",
+        "default-src allows inline scripts with 'unsafe-inline'",
+        "default-src allows eval with 'unsafe-eval'",
+        "default-src allows any host with *",
+      ]
+    );
+
+    let warnings: Vec<_> = CSP::new().lint().iter().map(ToString::to_string).collect();
+    assert_eq!(
+      warnings,
+      [
+        "script-src is not restricted",
+        "object-src is not restricted",
+        "base-uri is not restricted"
+      ]
+    );
+  }
+}
@@ -0,0 +1,350 @@
+//! Matching request URLs against source lists, following the "Does url match
+//! source list in origin with redirect count?" algorithm of the CSP
+//! specification.
+
+use std::fmt;
+
+use crate::{
+  CSP, Source, Sources,
+  host::{HostSource, default_port, is_scheme, scheme_part_matches},
+  kind::DirectiveKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a policy does with a request, as returned by [`CSP::check_url`].
+pub enum Verdict {
+  /// The request is allowed, either because the URL matches the effective
+  /// directive or because nothing restricts it.
+  Allowed,
+  /// The request is blocked by the directive, which is the one that
+  /// effectively applies to the request, possibly through fallbacks.
+  Blocked(DirectiveKind),
+  /// The URL doesn't match the directive, but the request could still be
+  /// allowed through a nonce, a hash of the resource or `'strict-dynamic'`,
+  /// which only the element that made the request can tell.
+  Unverifiable(DirectiveKind),
+}
+
+impl fmt::Display for Verdict {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Allowed => write!(fmt, "allowed"),
+      Self::Blocked(kind) => write!(fmt, "blocked by {kind}"),
+      Self::Unverifiable(kind) => {
+        write!(
+          fmt,
+          "not matched by {kind}, unless allowed by a nonce, hash or 'strict-dynamic'"
+        )
+      }
+    }
+  }
+}
+
+impl CSP<'_> {
+  #[must_use]
+  /// Checks if the policy allows a request to a URL, which is subject to the
+  /// given directive, such as [`DirectiveKind::ScriptSrcElem`] for scripts.
+  /// The fallback list of the directive is taken into account, so the
+  /// directive that blocks the request may be another one, such as
+  /// `default-src`.
+  ///
+  /// The document is the URL of the protected document, which decides what
+  /// `'self'` and expressions without a scheme match. Returns `None` if
+  /// either isn't an absolute URL.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, DirectiveKind, Verdict};
+  ///
+  /// let csp = CSP::parse("default-src 'self'; img-src *.example.org").unwrap();
+  /// let check =
+  ///   |kind, url| csp.check_url(kind, url, "https://example.org/page").unwrap();
+  ///
+  /// assert_eq!(check(DirectiveKind::ImgSrc, "https://cdn.example.org/a.png"), Verdict::Allowed);
+  /// assert_eq!(
+  ///   check(DirectiveKind::ImgSrc, "http://cdn.example.org/a.png"),
+  ///   Verdict::Blocked(DirectiveKind::ImgSrc)
+  /// );
+  /// assert_eq!(
+  ///   check(DirectiveKind::ScriptSrcElem, "https://example.org:8443/a.js"),
+  ///   Verdict::Blocked(DirectiveKind::DefaultSrc)
+  /// );
+  /// ```
+  pub fn check_url(
+    &self,
+    kind: DirectiveKind,
+    url: &str,
+    document: &str,
+  ) -> Option<Verdict> {
+    let (url, document) = (Url::parse(url)?, Url::parse(document)?);
+
+    let Some((kind, sources)) = self.effective_directive(kind) else {
+      return Some(Verdict::Allowed);
+    };
+
+    let strict_dynamic = sources.is_strict_dynamic(kind.is_script());
+    Some(if !strict_dynamic && sources.matches_url(&url, &document) {
+      Verdict::Allowed
+    } else if strict_dynamic
+      || sources.0.iter().any(|source| {
+        matches!(source, Source::Nonce(_))
+          || (kind.is_script() && matches!(source, Source::Hash(_)))
+      })
+    {
+      Verdict::Unverifiable(kind)
+    } else {
+      Verdict::Blocked(kind)
+    })
+  }
+}
+
+impl Sources<'_> {
+  /// If any expression matches the URL, for a document of the given origin.
+  pub(crate) fn matches_url(&self, url: &Url<'_>, origin: &Url<'_>) -> bool {
+    self.0.iter().any(|source| source.matches_url(url, origin))
+  }
+}
+
+impl Source<'_> {
+  /// The "Does url match expression in origin with redirect count?"
+  /// algorithm, for requests that weren't redirected.
+  pub(crate) fn matches_url(&self, url: &Url<'_>, origin: &Url<'_>) -> bool {
+    match self {
+      Self::Host("*") => {
+        ["http", "https", "ws", "wss"].iter().any(|scheme| url.scheme_is(scheme))
+          || url.scheme_is(origin.scheme)
+      }
+      Self::Scheme(scheme) => scheme_part_matches(scheme, url.scheme),
+      Self::Host(expression) => {
+        let (Some(expression), Some(host)) = (HostSource::parse(expression), url.host)
+        else {
+          return false;
+        };
+
+        scheme_part_matches(expression.scheme.unwrap_or(origin.scheme), url.scheme)
+          && host_part_matches(expression.host, host)
+          && port_part_matches(expression.port, url)
+          && path_part_matches(expression.path, url.path)
+      }
+      Self::Self_ => {
+        let same_host = url
+          .host
+          .zip(origin.host)
+          .is_some_and(|(url, origin)| url.eq_ignore_ascii_case(origin));
+        same_host
+          && url.port == origin.port
+          && (url.scheme_is(origin.scheme)
+            || url.scheme_is("https")
+            || url.scheme_is("wss")
+            || (origin.scheme_is("http") && url.scheme_is("ws")))
+      }
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The parts of an absolute URL that source expressions are matched against.
+pub struct Url<'a> {
+  /// The scheme, without the `:`.
+  pub scheme: &'a str,
+  /// The host, which URLs such as `data:` ones don't have.
+  pub host: Option<&'a str>,
+  /// The port, if it isn't the default one of the scheme.
+  pub port: Option<&'a str>,
+  /// The path, without the query and fragment.
+  pub path: &'a str,
+}
+
+impl<'a> Url<'a> {
+  /// Splits an absolute URL into its parts, returning `None` if it isn't
+  /// one.
+  pub fn parse(url: &'a str) -> Option<Self> {
+    let (scheme, rest) = url.trim().split_once(':')?;
+    if !is_scheme(scheme) {
+      return None;
+    }
+    let without_query = |rest: &'a str| rest.split(['?', '#']).next().unwrap_or_default();
+
+    let Some(rest) = rest.strip_prefix("//") else {
+      return Some(Self { scheme, host: None, port: None, path: without_query(rest) });
+    };
+
+    let (authority, path) =
+      rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    let (host, port) = match authority.rfind(':') {
+      // The colons of IPv6 addresses are in brackets.
+      Some(colon) if !authority[colon..].contains(']') => {
+        (&authority[..colon], Some(&authority[colon + 1..]))
+      }
+      _ => (authority, None),
+    };
+
+    if host.is_empty()
+      || port.is_some_and(|port| !port.bytes().all(|b| b.is_ascii_digit()))
+    {
+      return None;
+    }
+    let port = port.filter(|port| !port.is_empty() && default_port(scheme) != Some(port));
+
+    Some(Self { scheme, host: Some(host), port, path: without_query(path) })
+  }
+
+  const fn scheme_is(&self, scheme: &str) -> bool {
+    self.scheme.eq_ignore_ascii_case(scheme)
+  }
+}
+
+fn host_part_matches(pattern: &str, host: &str) -> bool {
+  pattern.strip_prefix('*').map_or_else(
+    || pattern.eq_ignore_ascii_case(host),
+    |suffix| {
+      host.len() > suffix.len()
+        && host
+          .get(host.len() - suffix.len()..)
+          .is_some_and(|end| end.eq_ignore_ascii_case(suffix))
+    },
+  )
+}
+
+fn port_part_matches(pattern: Option<&str>, url: &Url<'_>) -> bool {
+  match pattern {
+    Some("*") => true,
+    None => url.port.is_none(),
+    Some(port) => {
+      let port = port.trim_start_matches('0');
+      url.port.map_or_else(
+        || default_port(url.scheme).is_some_and(|default| default == port),
+        |url| url.trim_start_matches('0') == port,
+      )
+    }
+  }
+}
+
+fn path_part_matches(pattern: Option<&str>, path: &str) -> bool {
+  let Some(pattern) = pattern else {
+    return true;
+  };
+  if pattern == "/" && path.is_empty() {
+    return true;
+  }
+
+  let (pattern, exact) =
+    pattern.strip_suffix('/').map_or((pattern, true), |prefix| (prefix, false));
+  let patterns: Vec<_> = pattern.split('/').collect();
+  let segments: Vec<_> = path.split('/').collect();
+
+  if patterns.len() > segments.len() || (exact && patterns.len() != segments.len()) {
+    return false;
+  }
+  patterns
+    .iter()
+    .zip(&segments)
+    .all(|(pattern, segment)| percent_decode(pattern) == percent_decode(segment))
+}
+
+fn percent_decode(segment: &str) -> Vec<u8> {
+  let bytes = segment.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    let escaped = bytes
+      .get(index + 1..index + 3)
+      .filter(|_| bytes[index] == b'%')
+      .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+    if let Some(byte) = escaped {
+      decoded.push(byte);
+      index += 3;
+    } else {
+      decoded.push(bytes[index]);
+      index += 1;
+    }
+  }
+  decoded
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn matches(expression: &str, url: &str) -> bool {
+    let policy = format!("img-src {expression}");
+    let csp = CSP::parse(&policy).unwrap();
+    let sources = csp.get(DirectiveKind::ImgSrc).unwrap().sources().unwrap();
+    sources.matches_url(
+      &Url::parse(url).unwrap(),
+      &Url::parse("https://example.org/").unwrap(),
+    )
+  }
+
+  #[test]
+  fn expressions() {
+    assert!(matches("*", "https://a.example.com/x"));
+    assert!(matches("*", "wss://a.example.com/x"));
+    assert!(!matches("*", "data:image/png,x"));
+    assert!(matches("data:", "data:image/png,x"));
+    assert!(matches("http:", "https://a.example.com"));
+    assert!(!matches("https:", "http://a.example.com"));
+
+    assert!(matches("'self'", "https://EXAMPLE.org:443/a"));
+    assert!(matches("'self'", "wss://example.org/socket"));
+    assert!(!matches("'self'", "http://example.org/a"));
+    assert!(!matches("'self'", "https://example.org:8443/a"));
+    assert!(!matches("'self'", "https://a.example.org/a"));
+
+    assert!(matches("example.org", "https://example.org/a"));
+    assert!(!matches("example.org", "http://example.org/a"));
+    assert!(matches("http://example.org", "https://example.org/a"));
+    assert!(matches("*.example.org", "https://a.b.example.org/a"));
+    assert!(!matches("*.example.org", "https://example.org/a"));
+    assert!(matches("example.org:443", "https://example.org/a"));
+    assert!(matches("example.org:*", "https://example.org:8443/a"));
+    assert!(!matches("example.org", "https://example.org:8443/a"));
+    assert!(matches("https://example.org:8443", "https://user@example.org:8443/a"));
+
+    assert!(matches("example.org/a/", "https://example.org/a/b/c?d"));
+    assert!(matches("example.org/a%20b", "https://example.org/a b"));
+    assert!(!matches("example.org/a", "https://example.org/a/b"));
+    assert!(!matches("example.org/a/b/", "https://example.org/a/"));
+    assert!(matches("example.org/", "https://example.org"));
+
+    assert!(!matches("'nonce-abc' 'unsafe-inline'", "https://example.org"));
+    assert_eq!(Url::parse("/relative"), None);
+    assert_eq!(Url::parse("https://example.org:x"), None);
+    assert_eq!(Url::parse("https://[::1]:8443/").unwrap().host, Some("[::1]"));
+  }
+
+  #[test]
+  fn verdicts() {
+    let csp = CSP::parse(
+      "default-src 'none'; script-src 'nonce-abc' 'strict-dynamic' https:; \
+       style-src 'self' 'nonce-abc'; img-src https:",
+    )
+    .unwrap();
+    let check = |kind, url| csp.check_url(kind, url, "https://example.org/").unwrap();
+
+    assert_eq!(check(DirectiveKind::ImgSrc, "https://a.example"), Verdict::Allowed);
+    assert_eq!(
+      check(DirectiveKind::ScriptSrcElem, "https://a.example/a.js"),
+      Verdict::Unverifiable(DirectiveKind::ScriptSrc)
+    );
+    assert_eq!(
+      check(DirectiveKind::StyleSrcElem, "https://example.org/a.css"),
+      Verdict::Allowed
+    );
+    assert_eq!(
+      check(DirectiveKind::StyleSrcElem, "https://a.example/a.css"),
+      Verdict::Unverifiable(DirectiveKind::StyleSrc)
+    );
+    assert_eq!(
+      check(DirectiveKind::FontSrc, "https://example.org/a.woff2"),
+      Verdict::Blocked(DirectiveKind::DefaultSrc)
+    );
+    assert_eq!(check(DirectiveKind::FormAction, "https://a.example"), Verdict::Allowed);
+    assert_eq!(
+      csp.check_url(DirectiveKind::ImgSrc, "a.png", "https://example.org"),
+      None
+    );
+  }
+}
@@ -0,0 +1,155 @@
+//! Delivering a policy through an HTML `<meta http-equiv>` element.
+
+use std::{error, fmt};
+
+use crate::{CSP, DirectiveKind};
+
+/// The directives that browsers ignore when a policy is delivered through a
+/// `<meta>` element, instead of a header.
+pub const IGNORED_IN_META: [DirectiveKind; 4] = [
+  DirectiveKind::FrameAncestors,
+  DirectiveKind::ReportTo,
+  DirectiveKind::ReportUri,
+  DirectiveKind::Sandbox,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a browser does with a policy.
+pub enum Disposition {
+  /// Blocks what the policy doesn't allow, as with the
+  /// `Content-Security-Policy` header.
+  Enforce,
+  /// Only reports what the policy doesn't allow, as with the
+  /// `Content-Security-Policy-Report-Only` header.
+  Report,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A `<meta>` element with a policy, as returned by [`CSP::to_meta_tag`].
+pub struct MetaTag {
+  /// The HTML of the element.
+  pub html: String,
+  /// The directives that were left out, as browsers ignore them in `<meta>`
+  /// elements, in the order they were in the policy.
+  pub stripped: Vec<DirectiveKind>,
+}
+
+impl fmt::Display for MetaTag {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "{}", self.html)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The reason why a policy could not be turned into a `<meta>` element.
+pub enum MetaError {
+  /// Browsers don't support report-only policies in `<meta>` elements.
+  ReportOnly,
+}
+
+impl fmt::Display for MetaError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::ReportOnly => {
+        write!(fmt, "report-only policies can't be delivered in a <meta> element")
+      }
+    }
+  }
+}
+
+impl error::Error for MetaError {}
+
+impl CSP<'_> {
+  /// Renders the policy as a `<meta http-equiv="Content-Security-Policy">`
+  /// element, for when it can't be sent as a header, such as on static hosts.
+  ///
+  /// The directives that browsers ignore in `<meta>` elements, listed in
+  /// [`IGNORED_IN_META`], are left out and returned with the element, so
+  /// they can be reported or sent as a header after all. The policy only
+  /// applies to content after the element, so it should be the first one in
+  /// the `<head>`.
+  ///
+  /// # Errors
+  /// If the disposition is [`Disposition::Report`], which browsers don't
+  /// support in `<meta>` elements.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, DirectiveKind, Disposition};
+  ///
+  /// let csp = CSP::parse("default-src 'self'; frame-ancestors 'none'").unwrap();
+  /// let meta = csp.to_meta_tag(Disposition::Enforce).unwrap();
+  ///
+  /// assert_eq!(
+  ///   meta.html,
+  ///   r#"<meta http-equiv="Content-Security-Policy" content="default-src 'self'">"#
+  /// );
+  /// assert_eq!(meta.stripped, [DirectiveKind::FrameAncestors]);
+  /// assert!(csp.to_meta_tag(Disposition::Report).is_err());
+  /// ```
+  pub fn to_meta_tag(&self, disposition: Disposition) -> Result<MetaTag, MetaError> {
+    if disposition == Disposition::Report {
+      return Err(MetaError::ReportOnly);
+    }
+
+    let mut csp = self.clone();
+    let mut stripped = Vec::new();
+    for directive in self {
+      let kind = directive.kind();
+      if IGNORED_IN_META.contains(&kind) && !stripped.contains(&kind) {
+        stripped.push(kind);
+        csp.remove(kind);
+      }
+    }
+
+    let html = format!(
+      r#"<meta http-equiv="Content-Security-Policy" content="{}">"#,
+      escape_attribute(&csp.to_string())
+    );
+    Ok(MetaTag { html, stripped })
+  }
+}
+
+/// Escapes a value for a double-quoted HTML attribute.
+fn escape_attribute(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for char in value.chars() {
+    match char {
+      '&' => escaped.push_str("&amp;"),
+      '"' => escaped.push_str("&quot;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      char => escaped.push(char),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Directive, ReportUris, SandboxAllowedList, Source, Sources};
+
+  #[test]
+  fn escaping() {
+    let csp = CSP::new()
+      .push(Directive::ImgSrc(Sources::new_with(Source::Host(
+        "https://example.org/?a=1&b=\"><script>",
+      ))))
+      .push(Directive::ReportUri(ReportUris::new_with("/report")))
+      .push(Directive::Sandbox(SandboxAllowedList::new()))
+      .push(Directive::ReportUri(ReportUris::new_with("/other")));
+
+    let meta = csp.to_meta_tag(Disposition::Enforce).unwrap();
+    assert_eq!(
+      meta.to_string(),
+      "<meta http-equiv=\"Content-Security-Policy\" \
+       content=\"img-src https://example.org/?a=1&amp;b=&quot;&gt;&lt;script&gt;\">"
+    );
+    assert_eq!(meta.stripped, [DirectiveKind::ReportUri, DirectiveKind::Sandbox]);
+    assert_eq!(
+      CSP::new().to_meta_tag(Disposition::Report).unwrap_err().to_string(),
+      "report-only policies can't be delivered in a <meta> element"
+    );
+  }
+}
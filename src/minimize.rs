@@ -0,0 +1,157 @@
+//! Making policies shorter without changing what they allow.
+
+use crate::{CSP, Level, kind::DirectiveKind};
+
+#[derive(Debug, Clone)]
+/// The result of [`CSP::minimize`].
+pub struct Minimized<'a> {
+  /// The minimized policy.
+  pub policy: CSP<'a>,
+  /// The length of the original policy as a string, in bytes.
+  pub original_len: usize,
+  /// The length of the minimized policy as a string, in bytes.
+  pub minimized_len: usize,
+}
+
+impl Minimized<'_> {
+  /// How many bytes shorter the minimized policy is.
+  #[must_use]
+  pub const fn saved_bytes(&self) -> usize {
+    self.original_len.saturating_sub(self.minimized_len)
+  }
+}
+
+impl<'a> CSP<'a> {
+  /// Produces a shorter policy that allows exactly the same things, for when
+  /// the header grows large enough for proxies to truncate it.
+  ///
+  /// On top of what [`CSP::normalize`] does, directives are dropped when the
+  /// directive they would fall back to, such as `default-src`, results in the
+  /// same effective sources for everything that uses them. Every step is
+  /// checked with the same logic as [`CSP::subsumes`], for how browsers of
+  /// each [`Level`] [`interpret`](CSP::interpret) the policy, so fallbacks
+  /// for older browsers such as those of [`CSP::with_compat_fallbacks`] are
+  /// kept.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse(
+  ///   "default-src 'self' https://cdn.example.org; img-src https://cdn.example.org 'self'; \
+  ///    script-src 'self' https://cdn.example.org https://cdn.example.org/js/",
+  /// )
+  /// .unwrap();
+  /// let minimized = csp.minimize();
+  ///
+  /// assert_eq!(minimized.policy.to_string(), "default-src 'self' https://cdn.example.org");
+  /// assert_eq!(minimized.saved_bytes(), 111);
+  /// ```
+  #[must_use]
+  pub fn minimize(&self) -> Minimized<'a> {
+    let mut policy = self.clone().normalize();
+
+    // The most specific directives go first, as the ones that they fall back
+    // to might only become redundant after them.
+    let mut kinds: Vec<_> = DirectiveKind::ALL
+      .into_iter()
+      .filter(|kind| kind.fallback_list().len() > 1)
+      .collect();
+    kinds.sort_by_key(|kind| std::cmp::Reverse(kind.fallback_list().len()));
+
+    for kind in kinds {
+      let Some(index) = policy.0.iter().position(|directive| directive.kind() == kind)
+      else {
+        continue;
+      };
+
+      let mut without = policy.clone();
+      without.0.to_mut().remove(index);
+
+      let is_equivalent = Level::ALL.into_iter().all(|level| {
+        without
+          .interpret(level)
+          .policy
+          .is_equivalent_for(&policy.interpret(level).policy, kind)
+      });
+      if is_equivalent {
+        policy = without;
+      }
+    }
+
+    Minimized {
+      original_len: self.to_string().len(),
+      minimized_len: policy.to_string().len(),
+      policy,
+    }
+  }
+
+  /// If the effective sources of everything that uses the given directive are
+  /// the same in both policies.
+  fn is_equivalent_for(&self, other: &CSP<'_>, removed: DirectiveKind) -> bool {
+    DirectiveKind::ALL
+      .into_iter()
+      .filter(|kind| kind.fallback_list().contains(&removed))
+      .all(|kind| match (self.effective_sources(kind), other.effective_sources(kind)) {
+        (Some(this), Some(other)) => {
+          this.is_subsumed_by(other, kind.is_script())
+            && other.is_subsumed_by(this, kind.is_script())
+        }
+        (this, other) => this.is_none() && other.is_none(),
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_equivalent(original: &CSP<'_>, minimized: &CSP<'_>) {
+    assert!(original.subsumes(minimized), "{minimized} allows less than {original}");
+    assert!(minimized.subsumes(original), "{minimized} allows more than {original}");
+  }
+
+  #[test]
+  fn fallbacks() {
+    let csp = CSP::parse(
+      "default-src 'self'; script-src 'self' 'nonce-a'; script-src-elem 'nonce-a' 'self'; \
+       script-src-attr 'self'; img-src 'self'; object-src 'none'; base-uri 'self'",
+    )
+    .unwrap();
+    let minimized = csp.minimize();
+
+    assert_eq!(
+      minimized.policy.to_string(),
+      "base-uri 'self'; default-src 'self'; object-src 'none'; script-src 'self' 'nonce-a'; \
+       script-src-attr 'self'"
+    );
+    assert_equivalent(&csp, &minimized.policy);
+  }
+
+  #[test]
+  fn keeps_differing() {
+    let csp = CSP::parse("script-src 'self'; default-src 'none'; script-src-elem 'self'")
+      .unwrap();
+    let minimized = csp.minimize();
+
+    assert_eq!(minimized.policy.to_string(), "default-src 'none'; script-src 'self'");
+    assert_equivalent(&csp, &minimized.policy);
+
+    let csp = CSP::parse("default-src 'none'; img-src 'self'").unwrap();
+    assert_eq!(csp.minimize().saved_bytes(), 0);
+  }
+
+  #[test]
+  fn keeps_compat_fallbacks() {
+    let csp = CSP::parse("default-src 'self' 'nonce-a'; script-src 'self' 'nonce-a'")
+      .unwrap()
+      .with_compat_fallbacks();
+    let minimized = csp.minimize();
+
+    assert_eq!(
+      minimized.policy.to_string(),
+      "default-src 'self' 'nonce-a'; script-src 'self' 'unsafe-inline' 'nonce-a'"
+    );
+    assert_equivalent(&csp, &minimized.policy);
+  }
+}
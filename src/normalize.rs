@@ -0,0 +1,388 @@
+//! Bringing policies into a canonical form, and comparing them in it.
+
+use std::{
+  cmp::Ordering,
+  hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher},
+  mem,
+};
+
+use crate::{CSP, Directive, Source, Sources};
+
+impl<'a> CSP<'a> {
+  /// Brings the policy into a canonical form, so that policies that only
+  /// differ in their formatting end up the same.
+  ///
+  /// - Only the first of duplicate directives is kept, as browsers do.
+  /// - Directives are sorted by name, and their values into a canonical order.
+  /// - Duplicate values are removed.
+  /// - Sources that are covered by others in the same list are removed, such
+  ///   as `https://example.org` when `https:` is present, or
+  ///   `'wasm-unsafe-eval'` when `'unsafe-eval'` is present.
+  /// - Well known schemes and hash algorithms are lower-cased. Keywords
+  ///   always are when turned into a string.
+  ///
+  /// `PartialEq`, `Eq` and `Hash` for [`CSP`] compare policies in this form,
+  /// so they can be used as keys in maps. They do so without normalizing
+  /// either policy.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse("img-src https://example.org HTTPS: 'SELF' 'self'; base-uri 'none'");
+  /// let csp = csp.unwrap().normalize();
+  ///
+  /// assert_eq!(csp.to_string(), "base-uri 'none'; img-src 'self' https:");
+  /// assert_eq!(csp, CSP::parse("base-uri 'none'; img-src https: 'self'").unwrap());
+  /// ```
+  #[must_use]
+  pub fn normalize(mut self) -> Self {
+    let mut directives: Vec<Directive<'a>> = Vec::with_capacity(self.0.len());
+    for directive in self.0.into_owned() {
+      if !directives.iter().any(|existing| existing.kind() == directive.kind()) {
+        directives.push(directive);
+      }
+    }
+    directives.sort_by_key(Directive::kind);
+
+    for directive in &mut directives {
+      directive.normalize();
+    }
+
+    self.0 = directives.into();
+    self
+  }
+}
+
+impl Directive<'_> {
+  fn normalize(&mut self) {
+    match self {
+      Self::PluginTypes(plugins) => sort_and_dedup(plugins.0.to_mut()),
+      Self::ReportUri(uris) => sort_and_dedup(uris.0.to_mut()),
+      Self::Sandbox(list) => sort_and_dedup(list.0.to_mut()),
+      Self::TrustedTypes(names) => sort_and_dedup(names),
+      Self::BlockAllMixedContent
+      | Self::ReportTo(_)
+      | Self::RequireSriFor(_)
+      | Self::UpgradeInsecureRequests => {}
+      _ => {
+        if let Some(sources) = self.sources_mut() {
+          sources.normalize();
+        }
+      }
+    }
+  }
+}
+
+impl Sources<'_> {
+  fn normalize(&mut self) {
+    for source in self.0.to_mut() {
+      match source {
+        Source::Scheme(scheme) => *scheme = canonical_case(scheme, KNOWN_SCHEMES),
+        Source::Hash((algorithm, _)) => {
+          *algorithm = canonical_case(algorithm, &["sha256", "sha384", "sha512"]);
+        }
+        _ => {}
+      }
+    }
+
+    self.0.to_mut().sort_by(Source::canonical_cmp);
+    self.0.to_mut().dedup();
+
+    if self.0.contains(&Source::UnsafeEval) {
+      self.0.to_mut().retain(|source| *source != Source::WasmUnsafeEval);
+    }
+
+    let mut index = 0;
+    while index < self.0.len() {
+      let source = &self.0[index];
+      let covered = self
+        .0
+        .iter()
+        .enumerate()
+        .any(|(other, covering)| other != index && source.is_subsumed_by(covering));
+
+      if covered {
+        self.0.to_mut().remove(index);
+      } else {
+        index += 1;
+      }
+    }
+  }
+}
+
+const KNOWN_SCHEMES: &[&str] =
+  &["blob", "data", "filesystem", "ftp", "http", "https", "mediastream", "ws", "wss"];
+
+/// Replaces the string with a lower-case static one if it is known.
+fn canonical_case<'a>(value: &'a str, known: &[&'static str]) -> &'a str {
+  known.iter().find(|known| known.eq_ignore_ascii_case(value)).copied().unwrap_or(value)
+}
+
+fn sort_and_dedup<T: Ord>(values: &mut Vec<T>) {
+  values.sort();
+  values.dedup();
+}
+
+impl Source<'_> {
+  /// The position of this kind of source in the canonical order.
+  const fn rank(&self) -> u8 {
+    match self {
+      Self::Self_ => 0,
+      Self::UnsafeEval => 1,
+      Self::WasmUnsafeEval => 2,
+      Self::UnsafeHashes => 3,
+      Self::UnsafeInline => 4,
+      Self::StrictDynamic => 5,
+      Self::ReportSample => 6,
+      Self::Nonce(_) => 7,
+      Self::Hash(_) => 8,
+      Self::Scheme(_) => 9,
+      Self::Host(_) => 10,
+    }
+  }
+
+  fn canonical_cmp(&self, other: &Source<'_>) -> Ordering {
+    let lowercase = |value: &str| value.to_ascii_lowercase();
+
+    self.rank().cmp(&other.rank()).then_with(|| match (self, other) {
+      (Self::Nonce(a), Source::Nonce(b)) => a.cmp(b),
+      (Self::Hash((a_algorithm, a)), Source::Hash((b_algorithm, b))) => {
+        lowercase(a_algorithm).cmp(&lowercase(b_algorithm)).then_with(|| a.cmp(b))
+      }
+      (Self::Scheme(a), Source::Scheme(b)) => lowercase(a).cmp(&lowercase(b)),
+      (Self::Host(a), Source::Host(b)) => {
+        let ((a_origin, a_path), (b_origin, b_path)) = (split_path(a), split_path(b));
+        lowercase(a_origin).cmp(&lowercase(b_origin)).then_with(|| a_path.cmp(b_path))
+      }
+      _ => Ordering::Equal,
+    })
+  }
+}
+
+/// Splits a host source into the case-insensitive part before the path, and
+/// the case-sensitive path.
+fn split_path(host: &str) -> (&str, &str) {
+  let start = host.find("://").map_or(0, |index| index + 3);
+  host.split_at(host[start..].find('/').map_or(host.len(), |index| start + index))
+}
+
+impl PartialEq for Source<'_> {
+  /// Compares sources as browsers would, so schemes, hosts and hash algorithms
+  /// are case-insensitive, while paths, nonces and hashes are not.
+  fn eq(&self, other: &Self) -> bool {
+    self.rank() == other.rank() && self.canonical_cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Source<'_> {}
+
+impl Hash for Source<'_> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    let lowercase = |value: &str| value.to_ascii_lowercase();
+
+    mem::discriminant(self).hash(state);
+    match self {
+      Self::Host(host) => {
+        let (origin, path) = split_path(host);
+        lowercase(origin).hash(state);
+        path.hash(state);
+      }
+      Self::Scheme(scheme) => lowercase(scheme).hash(state),
+      Self::Nonce(nonce) => nonce.hash(state),
+      Self::Hash((algorithm, hash)) => {
+        lowercase(algorithm).hash(state);
+        hash.hash(state);
+      }
+      _ => {}
+    }
+  }
+}
+
+impl<'a> CSP<'a> {
+  /// The directives that browsers use, leaving out later duplicates.
+  fn first_of_kinds(&self) -> impl Iterator<Item = &Directive<'a>> {
+    self.0.iter().enumerate().filter_map(|(index, directive)| {
+      let is_first =
+        !self.0[..index].iter().any(|earlier| earlier.kind() == directive.kind());
+      is_first.then_some(directive)
+    })
+  }
+}
+
+impl Directive<'_> {
+  /// If both directives are the same once normalized.
+  fn normalized_eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::PluginTypes(a), Self::PluginTypes(b)) => same_set(&a.0.iter(), &b.0.iter()),
+      (Self::ReportUri(a), Self::ReportUri(b)) => same_set(&a.0.iter(), &b.0.iter()),
+      (Self::Sandbox(a), Self::Sandbox(b)) => same_set(&a.0.iter(), &b.0.iter()),
+      (Self::TrustedTypes(a), Self::TrustedTypes(b)) => same_set(&a.iter(), &b.iter()),
+      _ => match (self.sources(), other.sources()) {
+        (Some(a), Some(b)) => {
+          self.kind() == other.kind() && same_set(&a.canonical(), &b.canonical())
+        }
+        _ => self == other,
+      },
+    }
+  }
+
+  /// Hashes the directive as it would be once normalized.
+  fn normalized_hash<H: Hasher>(&self, state: &mut H) {
+    self.kind().hash(state);
+    match self {
+      Self::PluginTypes(plugins) => set_hash(&plugins.0.iter()).hash(state),
+      Self::ReportUri(uris) => set_hash(&uris.0.iter()).hash(state),
+      Self::Sandbox(list) => set_hash(&list.0.iter()).hash(state),
+      Self::TrustedTypes(names) => set_hash(&names.iter()).hash(state),
+      _ => match self.sources() {
+        Some(sources) => set_hash(&sources.canonical()).hash(state),
+        None => self.hash(state),
+      },
+    }
+  }
+}
+
+impl<'a> Sources<'a> {
+  /// The sources that normalizing keeps, in any order and with duplicates.
+  fn canonical(&self) -> impl Iterator<Item = &Source<'a>> + Clone {
+    let has_unsafe_eval = self.0.contains(&Source::UnsafeEval);
+
+    self.0.iter().filter(move |source| {
+      // Of two sources that cover each other, normalizing keeps the one that
+      // comes later in the canonical order.
+      let covered = self.0.iter().any(|covering| {
+        covering != *source
+          && source.is_subsumed_by(covering)
+          && (!covering.is_subsumed_by(source)
+            || source.canonical_cmp(covering) == Ordering::Less)
+      });
+      !(covered || has_unsafe_eval && **source == Source::WasmUnsafeEval)
+    })
+  }
+}
+
+/// If both lists have the same values, ignoring order and duplicates.
+fn same_set<'i, T, I>(a: &I, b: &I) -> bool
+where
+  T: PartialEq + 'i,
+  I: Iterator<Item = &'i T> + Clone,
+{
+  a.clone().all(|value| b.clone().any(|other| value == other))
+    && b.clone().all(|value| a.clone().any(|other| value == other))
+}
+
+/// Hashes the values of a list, ignoring order and duplicates.
+fn set_hash<'i, T, I>(values: &I) -> u64
+where
+  T: Hash + PartialEq + 'i,
+  I: Iterator<Item = &'i T> + Clone,
+{
+  values
+    .clone()
+    .enumerate()
+    .filter(|(index, value)| {
+      !values.clone().take(*index).any(|earlier| earlier == *value)
+    })
+    .map(|(_, value)| BuildHasherDefault::<DefaultHasher>::default().hash_one(value))
+    .fold(0, u64::wrapping_add)
+}
+
+impl PartialEq for CSP<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    let covers = |a: &Self, b: &Self| {
+      a.first_of_kinds().all(|directive| {
+        b.get(directive.kind()).is_some_and(|other| directive.normalized_eq(other))
+      })
+    };
+    covers(self, other) && covers(other, self)
+  }
+}
+
+impl Eq for CSP<'_> {}
+
+impl Hash for CSP<'_> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    let mut directives: Vec<&Directive<'_>> = self.first_of_kinds().collect();
+    directives.sort_by_key(|directive| directive.kind());
+    for directive in directives {
+      directive.normalized_hash(state);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use super::*;
+
+  #[test]
+  fn canonical_form() {
+    let csp = CSP::parse(
+      "script-src https://b.example.org 'nonce-b' 'unsafe-eval' 'wasm-unsafe-eval' \
+       'nonce-a' HTTP: https://A.example.org/Path 'self'; \
+       sandbox allow-scripts allow-forms allow-scripts; \
+       default-src 'self'; default-src *; \
+       img-src *.example.org a.example.org:443 https://x.example.org",
+    )
+    .unwrap()
+    .normalize();
+
+    assert_eq!(
+      csp.to_string(),
+      "default-src 'self'; \
+       img-src *.example.org a.example.org:443; \
+       sandbox allow-forms allow-scripts; \
+       script-src 'self' 'unsafe-eval' 'nonce-a' 'nonce-b' http:"
+    );
+  }
+
+  #[test]
+  fn equality_and_hashing() {
+    let a = CSP::parse("img-src HTTPS: 'self'; script-src 'SHA256-abc'").unwrap();
+    let b = CSP::parse("script-src 'sha256-abc'; img-src 'self' https: https:").unwrap();
+    let c = CSP::parse("script-src 'sha256-ABC'; img-src 'self' https:").unwrap();
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_eq!(
+      Source::Host("HTTPS://Example.org/a"),
+      Source::Host("https://example.org/a")
+    );
+    assert_ne!(
+      Source::Host("https://example.org/A"),
+      Source::Host("https://example.org/a")
+    );
+
+    let set: HashSet<_> = [a, b, c].into_iter().collect();
+    assert_eq!(set.len(), 2);
+  }
+
+  #[test]
+  fn equality_without_normalizing() {
+    let hash =
+      |csp: &CSP<'_>| BuildHasherDefault::<DefaultHasher>::default().hash_one(csp);
+    let policies = [
+      "script-src 'self' 'self' 'unsafe-eval' 'wasm-unsafe-eval'; script-src *",
+      "script-src 'unsafe-eval' 'self'",
+      "img-src https://a.example.org:443 https://a.example.org https:",
+      "img-src https:",
+      "sandbox allow-scripts allow-forms allow-scripts; trusted-types a b a",
+      "trusted-types b a; sandbox allow-forms allow-scripts",
+      "upgrade-insecure-requests; report-to main",
+    ];
+
+    for a in policies {
+      let a = CSP::parse(a).unwrap();
+      for b in policies {
+        let b = CSP::parse(b).unwrap();
+        let normalized_eq = a.clone().normalize().0 == b.clone().normalize().0;
+        assert_eq!(a == b, normalized_eq, "{a} == {b}");
+        if normalized_eq {
+          assert_eq!(hash(&a), hash(&b), "{a} == {b}");
+        }
+      }
+    }
+  }
+}
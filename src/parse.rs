@@ -0,0 +1,378 @@
+//! Turning header strings back into the typed structures.
+
+use std::{error, fmt};
+
+use crate::{
+  CSP, Directive, Plugins, ReportUris, SandboxAllow, SandboxAllowedList, Source, Sources,
+  SriFor,
+  host::{HostSource, is_scheme},
+  kind::DirectiveKind,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a string could not be parsed into a [`CSP`].
+pub enum ParseError {
+  /// The directive name is not one that this crate knows about.
+  UnknownDirective(String),
+  /// A directive requires a value, but was given none.
+  MissingValue(&'static str),
+  /// A value is not valid for the directive it was given to.
+  InvalidValue {
+    /// The name of the directive.
+    directive: &'static str,
+    /// The offending value.
+    value: String,
+  },
+  /// The directive is valid, but not allowed where the policy is used.
+  NotAllowed(&'static str),
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::UnknownDirective(name) => write!(fmt, "unknown directive `{name}`"),
+      Self::MissingValue(directive) => write!(fmt, "`{directive}` requires a value"),
+      Self::InvalidValue { directive, value } => {
+        write!(fmt, "invalid value `{value}` for `{directive}`")
+      }
+      Self::NotAllowed(directive) => write!(fmt, "`{directive}` is not allowed here"),
+    }
+  }
+}
+
+impl error::Error for ParseError {}
+
+impl<'a> CSP<'a> {
+  /// Parses a policy from its serialized form, such as the value of a
+  /// `Content-Security-Policy` header.
+  ///
+  /// The returned policy borrows from the given string. As browsers do,
+  /// empty and unknown directives are skipped, so that policies with newer
+  /// directives are still accepted, and only the first of duplicate
+  /// directives is kept. Directive names and keywords are matched
+  /// case-insensitively.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let csp = CSP::parse("default-src 'SELF'; img-src * data:;").unwrap();
+  ///
+  /// assert_eq!(csp.to_string(), "default-src 'self'; img-src * data:");
+  /// ```
+  ///
+  /// # Errors
+  /// If any of the values of a known directive are invalid.
+  pub fn parse(policy: &'a str) -> Result<Self, ParseError> {
+    Self::parse_with(policy, false)
+  }
+
+  /// Parses a policy like [`CSP::parse`], but fails on directives that this
+  /// crate doesn't know about instead of skipping them. This is meant for
+  /// policies that are written by hand, such as in configuration files,
+  /// where an unknown directive is most likely a typo.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, ParseError};
+  ///
+  /// assert_eq!(
+  ///   CSP::parse_strict("scirpt-src 'self'; img-src *"),
+  ///   Err(ParseError::UnknownDirective("scirpt-src".to_owned()))
+  /// );
+  /// assert_eq!(
+  ///   CSP::parse("scirpt-src 'self'; img-src *").unwrap().to_string(),
+  ///   "img-src *"
+  /// );
+  /// ```
+  ///
+  /// # Errors
+  /// If a directive is unknown, or any of its values are invalid.
+  pub fn parse_strict(policy: &'a str) -> Result<Self, ParseError> {
+    Self::parse_with(policy, true)
+  }
+
+  fn parse_with(policy: &'a str, strict: bool) -> Result<Self, ParseError> {
+    let mut csp = Self::new();
+
+    for directive in policy.split(';') {
+      let mut tokens = directive.split_ascii_whitespace();
+      let Some(name) = tokens.next() else {
+        continue;
+      };
+
+      let Some(kind) = DirectiveKind::from_name(name) else {
+        if strict {
+          return Err(ParseError::UnknownDirective(name.to_owned()));
+        }
+        continue;
+      };
+      let directive = parse_directive(kind, tokens)?;
+
+      if csp.get(kind).is_none() {
+        csp.push_borrowed(directive);
+      }
+    }
+
+    Ok(csp)
+  }
+
+  /// Parses the value of a [`SEC_REQUIRED_CSP`] request header, which is the
+  /// policy that an embedder requires an embedded document to enforce.
+  ///
+  /// Reporting directives are not allowed in required policies, as the
+  /// embedded document would otherwise learn about the embedder's endpoints.
+  ///
+  /// # Errors
+  /// If the value can't be parsed, or contains `report-uri` or `report-to`.
+  pub fn parse_required(header_value: &'a str) -> Result<Self, ParseError> {
+    let csp = Self::parse(header_value)?;
+
+    for kind in [DirectiveKind::ReportUri, DirectiveKind::ReportTo] {
+      if csp.get(kind).is_some() {
+        return Err(ParseError::NotAllowed(kind.name()));
+      }
+    }
+
+    Ok(csp)
+  }
+}
+
+/// Name of the request header that an embedder uses to tell the embedded
+/// document which policy it requires it to enforce, as a part of CSP Embedded
+/// Enforcement.
+pub const SEC_REQUIRED_CSP: &str = "Sec-Required-CSP";
+
+/// Name of the response header that an embedded document can use to accept
+/// any policy that the listed origins require of it, as a part of CSP Embedded
+/// Enforcement.
+pub const ALLOW_CSP_FROM: &str = "Allow-CSP-From";
+
+/// Parses the values of a single directive.
+pub fn parse_directive<'a>(
+  kind: DirectiveKind,
+  mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<Directive<'a>, ParseError> {
+  let name = kind.name();
+  let invalid =
+    |value: &str| ParseError::InvalidValue { directive: name, value: value.to_owned() };
+
+  Ok(match kind {
+    DirectiveKind::BlockAllMixedContent | DirectiveKind::UpgradeInsecureRequests => {
+      if let Some(value) = tokens.next() {
+        return Err(invalid(value));
+      }
+      if kind == DirectiveKind::BlockAllMixedContent {
+        Directive::BlockAllMixedContent
+      } else {
+        Directive::UpgradeInsecureRequests
+      }
+    }
+    DirectiveKind::PluginTypes => {
+      let mut plugins = Plugins::new();
+      for token in tokens {
+        let plugin = token.split_once('/').ok_or_else(|| invalid(token))?;
+        plugins.push_borrowed(plugin);
+      }
+      Directive::PluginTypes(plugins)
+    }
+    DirectiveKind::ReportTo => {
+      let group = tokens.next().ok_or(ParseError::MissingValue(name))?;
+      if let Some(value) = tokens.next() {
+        return Err(invalid(value));
+      }
+      Directive::ReportTo(group)
+    }
+    DirectiveKind::ReportUri => {
+      let uris = ReportUris(tokens.collect());
+      if uris.0.is_empty() {
+        return Err(ParseError::MissingValue(name));
+      }
+      Directive::ReportUri(uris)
+    }
+    DirectiveKind::RequireSriFor => {
+      let (mut script, mut style) = (false, false);
+      for token in tokens {
+        if token.eq_ignore_ascii_case("script") {
+          script = true;
+        } else if token.eq_ignore_ascii_case("style") {
+          style = true;
+        } else {
+          return Err(invalid(token));
+        }
+      }
+      Directive::RequireSriFor(match (script, style) {
+        (true, true) => SriFor::ScriptStyle,
+        (true, false) => SriFor::Script,
+        (false, true) => SriFor::Style,
+        (false, false) => return Err(ParseError::MissingValue(name)),
+      })
+    }
+    DirectiveKind::Sandbox => {
+      let mut list = SandboxAllowedList::new();
+      for token in tokens {
+        list.push_borrowed(SandboxAllow::parse(token).ok_or_else(|| invalid(token))?);
+      }
+      Directive::Sandbox(list)
+    }
+    DirectiveKind::TrustedTypes => {
+      let policies: Vec<_> = tokens.collect();
+      if policies.is_empty() {
+        return Err(ParseError::MissingValue(name));
+      }
+      Directive::TrustedTypes(policies)
+    }
+    _ => {
+      let mut sources = Sources::new();
+      for token in tokens {
+        if token.eq_ignore_ascii_case("'none'") {
+          continue;
+        }
+        sources.push_borrowed(Source::parse(token).ok_or_else(|| invalid(token))?);
+      }
+      kind.with_sources(sources).expect("the remaining kinds have sources")
+    }
+  })
+}
+
+impl<'a> Source<'a> {
+  /// Parses a single source expression, returning `None` for invalid ones.
+  ///
+  /// `'none'` is not a source expression by itself, but the absence of them.
+  pub(crate) fn parse(expression: &'a str) -> Option<Self> {
+    if let Some(keyword) =
+      expression.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\''))
+    {
+      return Self::parse_keyword(keyword);
+    }
+
+    if let Some(scheme) = expression.strip_suffix(':') {
+      if is_scheme(scheme) {
+        return Some(Self::Scheme(scheme));
+      }
+    }
+
+    HostSource::parse(expression).map(|_| Self::Host(expression))
+  }
+
+  fn parse_keyword(keyword: &'a str) -> Option<Self> {
+    let keywords = [
+      ("self", Self::Self_),
+      ("unsafe-eval", Self::UnsafeEval),
+      ("wasm-unsafe-eval", Self::WasmUnsafeEval),
+      ("unsafe-hashes", Self::UnsafeHashes),
+      ("unsafe-inline", Self::UnsafeInline),
+      ("strict-dynamic", Self::StrictDynamic),
+      ("report-sample", Self::ReportSample),
+    ];
+    if let Some((_, source)) =
+      keywords.into_iter().find(|(name, _)| name.eq_ignore_ascii_case(keyword))
+    {
+      return Some(source);
+    }
+
+    let (prefix, value) = keyword.split_once('-')?;
+    if value.is_empty() || !value.bytes().all(is_base64_byte) {
+      return None;
+    }
+
+    if prefix.eq_ignore_ascii_case("nonce") {
+      return Some(Self::Nonce(value));
+    }
+    ["sha256", "sha384", "sha512"]
+      .into_iter()
+      .find(|algorithm| algorithm.eq_ignore_ascii_case(prefix))
+      .map(|algorithm| Self::Hash((algorithm, value)))
+  }
+}
+
+const fn is_base64_byte(byte: u8) -> bool {
+  byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'-' | b'_' | b'=')
+}
+
+impl SandboxAllow {
+  /// Parses a sandbox keyword such as `allow-scripts`.
+  pub(crate) fn parse(keyword: &str) -> Option<Self> {
+    let allows = [
+      Self::DownloadsWithoutUserActivation,
+      Self::Forms,
+      Self::Modals,
+      Self::OrientationLock,
+      Self::PointerLock,
+      Self::Popups,
+      Self::PopupsToEscapeSandbox,
+      Self::Presentation,
+      Self::SameOrigin,
+      Self::Scripts,
+      Self::StorageAccessByUserActivation,
+      Self::TopNavigation,
+      Self::TopNavigationByUserActivation,
+    ];
+    allows.into_iter().find(|allow| allow.to_string().eq_ignore_ascii_case(keyword))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let policy = "default-src 'self'; img-src 'self' https: http://shields.io; \
+                  script-src 'sha256-1234a' 'nonce-5678b' 'strict-dynamic'; \
+                  object-src 'none'; sandbox allow-scripts allow-forms; \
+                  report-uri https://r1.example.org https://r2.example.org; \
+                  require-sri-for script style; upgrade-insecure-requests";
+
+    assert_eq!(CSP::parse(policy).unwrap().to_string(), policy);
+  }
+
+  #[test]
+  fn lenient_parts() {
+    let csp = CSP::parse(" ; SCRIPT-SRC 'Self' 'none' ;script-src https:; ").unwrap();
+    assert_eq!(csp.to_string(), "script-src 'self'");
+
+    let csp =
+      CSP::parse("scirpt-src 'self'; fenced-frame-src https:; img-src *").unwrap();
+    assert_eq!(csp.to_string(), "img-src *");
+  }
+
+  #[test]
+  fn strict() {
+    assert_eq!(
+      CSP::parse_strict("img-src *; scirpt-src 'self'").unwrap_err(),
+      ParseError::UnknownDirective("scirpt-src".to_owned())
+    );
+    assert_eq!(
+      CSP::parse_strict(" ; SCRIPT-SRC 'Self' ;script-src https:; ").unwrap().to_string(),
+      "script-src 'self'"
+    );
+    assert_eq!(
+      CSP::parse_strict("img-src 'self").unwrap_err(),
+      ParseError::InvalidValue { directive: "img-src", value: "'self".to_owned() }
+    );
+  }
+
+  #[test]
+  fn errors() {
+    assert_eq!(
+      CSP::parse("img-src 'self").unwrap_err(),
+      ParseError::InvalidValue { directive: "img-src", value: "'self".to_owned() }
+    );
+    assert_eq!(
+      CSP::parse("sandbox allow-everything").unwrap_err(),
+      ParseError::InvalidValue {
+        directive: "sandbox",
+        value: "allow-everything".to_owned()
+      }
+    );
+    assert_eq!(
+      CSP::parse("report-uri").unwrap_err(),
+      ParseError::MissingValue("report-uri")
+    );
+    assert_eq!(
+      CSP::parse_required("default-src 'self'; report-to main").unwrap_err(),
+      ParseError::NotAllowed("report-to")
+    );
+  }
+}
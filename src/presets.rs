@@ -0,0 +1,214 @@
+//! Secure starting points for common kinds of applications.
+
+use crate::{CSP, Directive, Source, Sources};
+
+impl<'a> CSP<'a> {
+  /// The strict policy recommended by browser vendors, for applications that
+  /// render their HTML on the server and can add a fresh nonce to every
+  /// `<script>` element of every response.
+  ///
+  /// Protects against injected scripts, since an attacker can't guess the
+  /// nonce, even when the application loads scripts from hosts that host
+  /// other content too. Scripts that are trusted through the nonce can load
+  /// further scripts thanks to `'strict-dynamic'`. Doesn't protect against
+  /// injected styles or markup that doesn't run scripts, and inline event
+  /// handlers such as `onclick` have to be moved into scripts.
+  ///
+  /// `https:` and `'unsafe-inline'` are fallbacks for browsers that don't
+  /// support `'strict-dynamic'` or nonces, and are ignored by those that do.
+  /// `object-src 'none'` blocks plugins, and `base-uri 'none'` stops `<base>`
+  /// elements from changing where relative script URLs point to.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// assert_eq!(
+  ///   CSP::strict_nonce_based("rAnd0m").to_string(),
+  ///   "script-src 'nonce-rAnd0m' 'strict-dynamic' https: 'unsafe-inline'; \
+  ///    object-src 'none'; base-uri 'none'"
+  /// );
+  /// ```
+  #[must_use]
+  pub fn strict_nonce_based(nonce: &'a str) -> Self {
+    Self::strict(Sources::new_with(Source::Nonce(nonce)))
+  }
+
+  /// The strict policy recommended by browser vendors, for applications that
+  /// are served as static files and know the hashes of their inline scripts,
+  /// given as pairs of algorithms, such as `sha256`, and base64 hashes.
+  ///
+  /// Protects against injected scripts like
+  /// [`strict_nonce_based`](Self::strict_nonce_based), except that only the
+  /// inline scripts with the given hashes are trusted. External scripts have
+  /// to be loaded by those inline scripts, as `'strict-dynamic'` ignores
+  /// the hosts that they could otherwise be allowed from.
+  #[must_use]
+  pub fn strict_hash_based(hashes: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+    let mut sources = Sources::new();
+    for hash in hashes {
+      sources.push_borrowed(Source::Hash(hash));
+    }
+
+    Self::strict(sources)
+  }
+
+  fn strict(trusted: Sources<'a>) -> Self {
+    Self::new()
+      .push(Directive::ScriptSrc(
+        trusted
+          .push(Source::StrictDynamic)
+          .push(Source::Scheme("https"))
+          .push(Source::UnsafeInline),
+      ))
+      .push(Directive::ObjectSrc(Sources::new()))
+      .push(Directive::BaseUri(Sources::new()))
+  }
+
+  /// A policy for responses that are never rendered as documents, such as
+  /// those of JSON APIs.
+  ///
+  /// Protects against a response being used for cross-site scripting when
+  /// it is opened directly, such as one that reflects input, or framed for
+  /// clickjacking, as it allows nothing to be loaded or embedded.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// assert_eq!(CSP::api_only().to_string(), "default-src 'none'; frame-ancestors 'none'");
+  /// ```
+  #[must_use]
+  pub fn api_only() -> Self {
+    Self::new()
+      .push(Directive::DefaultSrc(Sources::new()))
+      .push(Directive::FrameAncestors(Sources::new()))
+  }
+
+  /// A policy for static sites that serve everything from their own origin,
+  /// without inline scripts or styles.
+  ///
+  /// Protects against injected scripts and styles, including inline ones,
+  /// against data being sent to other origins through resources or forms,
+  /// and against clickjacking. Images can also come from `data:` URLs, which
+  /// can't run scripts. Sites with inline scripts should use
+  /// [`strict_hash_based`](Self::strict_hash_based) for them.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// assert_eq!(
+  ///   CSP::static_site().to_string(),
+  ///   "default-src 'self'; img-src 'self' data:; object-src 'none'; base-uri 'none'; \
+  ///    form-action 'self'; frame-ancestors 'none'"
+  /// );
+  /// ```
+  #[must_use]
+  pub fn static_site() -> Self {
+    Self::new()
+      .push(Directive::DefaultSrc(Sources::new_with(Source::Self_)))
+      .push(Directive::ImgSrc(
+        Sources::new_with(Source::Self_).push(Source::Scheme("data")),
+      ))
+      .push(Directive::ObjectSrc(Sources::new()))
+      .push(Directive::BaseUri(Sources::new()))
+      .push(Directive::FormAction(Sources::new_with(Source::Self_)))
+      .push(Directive::FrameAncestors(Sources::new()))
+  }
+
+  /// A policy for single-page applications whose bundled scripts and styles
+  /// are served from their own origin, and that talk to APIs on it.
+  ///
+  /// Protects like [`static_site`](Self::static_site), while also allowing
+  /// images from `blob:` URLs that the application creates, and `fetch` and
+  /// WebSocket connections to its own origin. Hosts of other APIs can be
+  /// added to `connect-src` with [`CSP::entry`]. Styles that are injected at
+  /// runtime by CSS-in-JS libraries need a nonce in `style-src`.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// assert_eq!(
+  ///   CSP::spa().to_string(),
+  ///   "default-src 'self'; img-src 'self' data: blob:; connect-src 'self'; \
+  ///    object-src 'none'; base-uri 'none'; form-action 'self'; frame-ancestors 'none'"
+  /// );
+  /// ```
+  #[must_use]
+  pub fn spa() -> Self {
+    Self::new()
+      .push(Directive::DefaultSrc(Sources::new_with(Source::Self_)))
+      .push(Directive::ImgSrc(
+        Sources::new_with(Source::Self_)
+          .push(Source::Scheme("data"))
+          .push(Source::Scheme("blob")),
+      ))
+      .push(Directive::ConnectSrc(Sources::new_with(Source::Self_)))
+      .push(Directive::ObjectSrc(Sources::new()))
+      .push(Directive::BaseUri(Sources::new()))
+      .push(Directive::FormAction(Sources::new_with(Source::Self_)))
+      .push(Directive::FrameAncestors(Sources::new()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{DirectiveKind, expand::FETCH_DIRECTIVES};
+
+  fn parse(policy: &str) -> CSP<'_> {
+    CSP::parse(policy).unwrap()
+  }
+
+  #[test]
+  fn strict() {
+    let nonce = CSP::strict_nonce_based("abc");
+    let hash = CSP::strict_hash_based([("sha256", "abc=")]);
+
+    for csp in [&nonce, &hash] {
+      // The fallbacks are there, but ignored along with `'unsafe-inline'`.
+      let scripts = csp.effective_sources(DirectiveKind::ScriptSrcElem).unwrap();
+      assert!(scripts.is_strict_dynamic(true));
+      assert!(scripts.expressions().next().is_some());
+      assert!(!scripts.allows_inline(true));
+    }
+
+    assert!(nonce.subsumes(&parse(
+      "script-src 'nonce-abc' 'strict-dynamic'; object-src 'none'; base-uri 'none'"
+    )));
+    assert!(!nonce.subsumes(&parse("script-src 'nonce-other' 'strict-dynamic'")));
+    assert!(hash.subsumes(&parse("script-src 'sha256-abc=' 'strict-dynamic'")));
+    assert!(!hash.subsumes(&parse("script-src 'sha256-other=' 'strict-dynamic'")));
+  }
+
+  #[test]
+  fn restrictive() {
+    let api_only = CSP::api_only();
+    let static_site = CSP::static_site();
+    let spa = CSP::spa();
+
+    for kind in FETCH_DIRECTIVES {
+      let sources = api_only.effective_sources(kind).unwrap();
+      assert!(sources.0.is_empty(), "{kind}");
+    }
+
+    for csp in [&static_site, &spa] {
+      for kind in [DirectiveKind::ScriptSrcElem, DirectiveKind::StyleSrcAttr] {
+        let sources = csp.effective_sources(kind).unwrap();
+        assert!(!sources.allows_inline(kind.is_script()));
+        assert!(
+          sources.is_subsumed_by(&Sources::new_with(Source::Self_), kind.is_script())
+        );
+      }
+      assert!(
+        csp
+          .subsumes(&parse("object-src 'none'; base-uri 'none'; frame-ancestors 'none'"))
+      );
+    }
+
+    assert!(static_site.subsumes(&spa));
+    assert!(!spa.subsumes(&static_site));
+  }
+}
@@ -0,0 +1,385 @@
+//! Base policies with named overlays, such as for development and production.
+
+use std::{error, fmt};
+
+use crate::{CSP, Change, ChangeKind, Directive, DirectiveKind, Item, Source, compare};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why an overlay of a [`PolicyProfile`] could not be applied.
+pub enum ProfileError<'a> {
+  /// No overlay with this name was added.
+  Unknown(String),
+  /// The named overlay adds or removes a source of a directive that takes no
+  /// sources, such as `sandbox`.
+  NoSources(&'a str, DirectiveKind),
+}
+
+impl fmt::Display for ProfileError<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Unknown(name) => write!(fmt, "unknown overlay `{name}`"),
+      Self::NoSources(name, kind) => {
+        write!(fmt, "overlay `{name}`: {kind} doesn't take sources")
+      }
+    }
+  }
+}
+
+impl error::Error for ProfileError<'_> {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A single change that an [`Overlay`] makes to the base policy.
+pub enum Adjustment<'a> {
+  /// Adds a source to a directive. A missing directive is first written out
+  /// with the sources that it inherited.
+  AddSource(DirectiveKind, Source<'a>),
+  /// Removes a source from a directive. A missing directive is first written
+  /// out with the sources that it inherited.
+  RemoveSource(DirectiveKind, Source<'a>),
+  /// Adds a directive, replacing any existing one of the same kind.
+  AddDirective(Directive<'a>),
+  /// Removes every directive of the kind.
+  RemoveDirective(DirectiveKind),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A named set of [`Adjustment`]s to the base policy of a [`PolicyProfile`].
+pub struct Overlay<'a> {
+  name: &'a str,
+  production: bool,
+  adjustments: Vec<Adjustment<'a>>,
+}
+
+impl<'a> Overlay<'a> {
+  #[must_use]
+  /// Creates a new overlay without any adjustments
+  pub const fn new(name: &'a str) -> Self {
+    Self { name, production: false, adjustments: Vec::new() }
+  }
+
+  #[must_use]
+  /// Marks the overlay as one used in production, which
+  /// [`PolicyProfile::lint`] holds to a higher standard
+  pub const fn production(mut self) -> Self {
+    self.production = true;
+    self
+  }
+
+  #[must_use]
+  /// Pushes an adjustment to the end of the overlay
+  pub fn push(mut self, adjustment: Adjustment<'a>) -> Self {
+    self.adjustments.push(adjustment);
+    self
+  }
+
+  #[must_use]
+  /// Pushes an [`Adjustment::AddSource`]
+  pub fn add_source(self, kind: DirectiveKind, source: Source<'a>) -> Self {
+    self.push(Adjustment::AddSource(kind, source))
+  }
+
+  #[must_use]
+  /// Pushes an [`Adjustment::RemoveSource`]
+  pub fn remove_source(self, kind: DirectiveKind, source: Source<'a>) -> Self {
+    self.push(Adjustment::RemoveSource(kind, source))
+  }
+
+  #[must_use]
+  /// Pushes an [`Adjustment::AddDirective`]
+  pub fn add_directive(self, directive: Directive<'a>) -> Self {
+    self.push(Adjustment::AddDirective(directive))
+  }
+
+  #[must_use]
+  /// Pushes an [`Adjustment::RemoveDirective`]
+  pub fn remove_directive(self, kind: DirectiveKind) -> Self {
+    self.push(Adjustment::RemoveDirective(kind))
+  }
+
+  #[must_use]
+  /// The name of the overlay
+  pub const fn name(&self) -> &'a str {
+    self.name
+  }
+
+  #[must_use]
+  /// If the overlay is used in production
+  pub const fn is_production(&self) -> bool {
+    self.production
+  }
+
+  #[must_use]
+  /// The adjustments of the overlay, in the order they are applied
+  pub fn adjustments(&self) -> &[Adjustment<'a>] {
+    &self.adjustments
+  }
+
+  /// Applies the adjustments to a policy in order.
+  ///
+  /// # Errors
+  /// Returns [`ProfileError::NoSources`] without changing the policy if an
+  /// adjustment adds or removes a source of a directive that takes none.
+  pub fn apply(&self, csp: &mut CSP<'a>) -> Result<(), ProfileError<'a>> {
+    let without_sources =
+      self.adjustments.iter().find_map(|adjustment| match adjustment {
+        Adjustment::AddSource(kind, _) | Adjustment::RemoveSource(kind, _)
+          if !kind.has_sources() =>
+        {
+          Some(*kind)
+        }
+        _ => None,
+      });
+    if let Some(kind) = without_sources {
+      return Err(ProfileError::NoSources(self.name, kind));
+    }
+
+    for adjustment in &self.adjustments {
+      match adjustment {
+        Adjustment::AddSource(kind, source) => {
+          if let Some(sources) = write_out(csp, *kind).and_then(Directive::sources_mut) {
+            sources.push_borrowed(source.clone());
+          }
+        }
+        Adjustment::RemoveSource(kind, source) => {
+          if let Some(sources) = write_out(csp, *kind).and_then(Directive::sources_mut) {
+            sources.0.to_mut().retain(|existing| existing != source);
+          }
+        }
+        Adjustment::AddDirective(directive) => {
+          csp.remove(directive.kind());
+          csp.push_borrowed(directive.clone());
+        }
+        Adjustment::RemoveDirective(kind) => {
+          csp.remove(*kind);
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Gets the directive, first adding it with its inherited sources if it's
+/// missing.
+fn write_out<'c, 'a>(
+  csp: &'c mut CSP<'a>,
+  kind: DirectiveKind,
+) -> Option<&'c mut Directive<'a>> {
+  if csp.get(kind).is_none() {
+    let inherited = csp.effective_sources(kind).cloned().unwrap_or_default();
+    csp.push_borrowed(kind.with_sources(inherited)?);
+  }
+
+  csp.get_mut(kind)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A base policy with named overlays, of which one is picked at runtime.
+///
+/// # Example usage
+/// ```rust
+/// use csp::{CSP, DirectiveKind, Overlay, PolicyProfile, Source};
+///
+/// let profile = PolicyProfile::new(CSP::parse("default-src 'self'").unwrap())
+///   .overlay(
+///     Overlay::new("dev")
+///       .add_source(DirectiveKind::ConnectSrc, Source::Host("ws://localhost:*"))
+///       .add_source(DirectiveKind::ScriptSrc, Source::UnsafeEval),
+///   )
+///   .overlay(Overlay::new("prod").production());
+///
+/// assert_eq!(
+///   profile.resolve("dev").unwrap().to_string(),
+///   "default-src 'self'; connect-src 'self' ws://localhost:*; script-src 'self' 'unsafe-eval'"
+/// );
+/// assert_eq!(profile.resolve("prod").unwrap().to_string(), "default-src 'self'");
+/// assert!(profile.lint().unwrap().is_empty());
+/// ```
+pub struct PolicyProfile<'a> {
+  base: CSP<'a>,
+  overlays: Vec<Overlay<'a>>,
+}
+
+impl<'a> PolicyProfile<'a> {
+  #[must_use]
+  /// Creates a new profile without any overlays
+  pub const fn new(base: CSP<'a>) -> Self {
+    Self { base, overlays: Vec::new() }
+  }
+
+  #[must_use]
+  /// Adds an overlay, replacing any existing one with the same name
+  pub fn overlay(mut self, overlay: Overlay<'a>) -> Self {
+    self.overlays.retain(|existing| existing.name != overlay.name);
+    self.overlays.push(overlay);
+    self
+  }
+
+  #[must_use]
+  /// The base policy
+  pub const fn base(&self) -> &CSP<'a> {
+    &self.base
+  }
+
+  #[must_use]
+  /// Gets the overlay with the given name
+  pub fn get(&self, name: &str) -> Option<&Overlay<'a>> {
+    self.overlays.iter().find(|overlay| overlay.name == name)
+  }
+
+  /// The base policy with the named overlay applied.
+  ///
+  /// # Errors
+  /// Returns [`ProfileError::Unknown`] if there is no such overlay, and
+  /// [`ProfileError::NoSources`] if it can't be applied.
+  pub fn resolve(&self, name: &str) -> Result<CSP<'a>, ProfileError<'a>> {
+    let overlay = self.get(name).ok_or_else(|| ProfileError::Unknown(name.to_owned()))?;
+    let mut csp = self.base.clone();
+    overlay.apply(&mut csp)?;
+    Ok(csp)
+  }
+
+  /// Checks that no production overlay makes the policy allow `'unsafe-*'`
+  /// keywords that the base policy doesn't.
+  ///
+  /// An overlay can do so by adding them, by removing a directive so that one
+  /// with them applies instead, or by removing the nonces, hashes or
+  /// `'strict-dynamic'` that made browsers ignore `'unsafe-inline'`.
+  ///
+  /// # Errors
+  /// Returns [`ProfileError::NoSources`] if an overlay can't be applied.
+  pub fn lint(&self) -> Result<Vec<ProfileViolation<'a>>, ProfileError<'a>> {
+    let mut violations = Vec::new();
+
+    for overlay in self.overlays.iter().filter(|overlay| overlay.production) {
+      let mut csp = self.base.clone();
+      overlay.apply(&mut csp)?;
+
+      violations.extend(
+        compare(&self.base, &csp)
+          .loosenings
+          .into_iter()
+          .filter(|change| {
+            // Other sources that are added don't turn on the keywords.
+            let adds_other = matches!(
+              &change.kind,
+              ChangeKind::Added(Item::Source(source)) if !UNSAFE.contains(source)
+            );
+            !adds_other
+              && UNSAFE.iter().any(|source| {
+                in_effect(&csp, change.directive, source)
+                  && !in_effect(&self.base, change.directive, source)
+              })
+          })
+          .map(|change| ProfileViolation { overlay: overlay.name, change }),
+      );
+    }
+
+    Ok(violations)
+  }
+}
+
+const UNSAFE: [Source<'static>; 4] = [
+  Source::UnsafeEval,
+  Source::UnsafeHashes,
+  Source::UnsafeInline,
+  Source::WasmUnsafeEval,
+];
+
+/// If the policy has the source for the directive, and browsers don't ignore
+/// it because of other sources.
+fn in_effect(csp: &CSP<'_>, kind: DirectiveKind, source: &Source<'_>) -> bool {
+  csp.effective_sources(kind).is_some_and(|sources| match source {
+    Source::UnsafeInline => sources.allows_inline(kind.is_script()),
+    _ => sources.0.contains(source),
+  })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A production overlay that makes the policy allow an `'unsafe-*'` keyword,
+/// as found by [`PolicyProfile::lint`].
+pub struct ProfileViolation<'a> {
+  /// The name of the overlay.
+  pub overlay: &'a str,
+  /// The change that the overlay makes to the base policy.
+  pub change: Change<'a>,
+}
+
+impl fmt::Display for ProfileViolation<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "production overlay `{}`: {}", self.overlay, self.change)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Sources;
+
+  #[test]
+  fn adjustments() {
+    let profile = PolicyProfile::new(
+      CSP::parse("default-src 'self'; img-src 'self' data:; object-src 'none'").unwrap(),
+    )
+    .overlay(
+      Overlay::new("embed")
+        .remove_source(DirectiveKind::ImgSrc, Source::Scheme("data"))
+        .remove_directive(DirectiveKind::ObjectSrc)
+        .add_directive(Directive::FrameAncestors(Sources::new_with(Source::Host(
+          "https://partner.example.org",
+        )))),
+    )
+    .overlay(Overlay::new("sandbox").add_source(DirectiveKind::Sandbox, Source::Self_));
+
+    assert_eq!(
+      profile.resolve("embed").unwrap().to_string(),
+      "default-src 'self'; img-src 'self'; frame-ancestors https://partner.example.org"
+    );
+    assert_eq!(
+      profile.resolve("missing"),
+      Err(ProfileError::Unknown("missing".to_owned()))
+    );
+    assert_eq!(
+      profile.resolve("sandbox"),
+      Err(ProfileError::NoSources("sandbox", DirectiveKind::Sandbox))
+    );
+  }
+
+  #[test]
+  fn production_lint() {
+    let profile = PolicyProfile::new(
+      CSP::parse("default-src 'self' 'unsafe-inline'; script-src 'self'").unwrap(),
+    )
+    .overlay(Overlay::new("dev").add_source(DirectiveKind::ScriptSrc, Source::UnsafeEval))
+    .overlay(
+      Overlay::new("prod")
+        .production()
+        .remove_directive(DirectiveKind::ScriptSrc)
+        .add_source(DirectiveKind::ConnectSrc, Source::Host("https://api.example.org")),
+    );
+
+    let violations: Vec<_> =
+      profile.lint().unwrap().iter().map(ToString::to_string).collect();
+    assert_eq!(
+      violations,
+      ["production overlay `prod`: script-src: removed, falls back to default-src, \
+         adding 'unsafe-inline'"]
+    );
+  }
+
+  #[test]
+  fn disabled_sources_lint() {
+    let profile =
+      PolicyProfile::new(CSP::parse("script-src 'nonce-a' 'unsafe-inline'").unwrap())
+        .overlay(
+          Overlay::new("prod")
+            .production()
+            .remove_source(DirectiveKind::ScriptSrc, Source::Nonce("a"))
+            .add_source(DirectiveKind::ScriptSrc, Source::Host("https://example.org")),
+        );
+
+    let violations: Vec<_> =
+      profile.lint().unwrap().iter().map(ToString::to_string).collect();
+    assert_eq!(violations, ["production overlay `prod`: script-src: removed 'nonce-a'"]);
+  }
+}
@@ -0,0 +1,434 @@
+//! Named policies that extend each other, looked up by route.
+
+use std::{error, fmt};
+
+use crate::{CSP, Directive, DirectiveKind, Source, Sources, SriFor};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a policy in a [`PolicyRegistry`] could not be resolved.
+pub enum RegistryError<'a> {
+  /// No policy with this name was defined.
+  Unknown(String),
+  /// Policies extend each other in a cycle, listed from the first policy
+  /// back to it.
+  Cycle(Vec<String>),
+  /// Merging the named policy with the ones it extends makes browsers ignore
+  /// a source that one of them has.
+  Conflict(String, MergeConflict<'a>),
+}
+
+impl fmt::Display for RegistryError<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Unknown(name) => write!(fmt, "unknown policy `{name}`"),
+      Self::Cycle(names) => {
+        write!(fmt, "policies extend each other: {}", names.join(" -> "))
+      }
+      Self::Conflict(name, conflict) => write!(fmt, "policy `{name}`: {conflict}"),
+    }
+  }
+}
+
+impl error::Error for RegistryError<'_> {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A source that merging policies makes browsers ignore.
+///
+/// This happens because of a source that the other policy adds to the same
+/// directive: a nonce, a hash or `'strict-dynamic'` disables
+/// `'unsafe-inline'`, and `'strict-dynamic'` also disables hosts, schemes and
+/// `'self'`.
+pub struct MergeConflict<'a> {
+  /// The directive with both sources.
+  pub directive: DirectiveKind,
+  /// The source that is ignored, which allowed something before the merge.
+  pub disabled: Source<'a>,
+  /// The source that disables it.
+  pub by: Source<'a>,
+}
+
+impl fmt::Display for MergeConflict<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "{}: {} disables {}", self.directive, self.by, self.disabled)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition<'a> {
+  name: &'a str,
+  extends: Vec<&'a str>,
+  policy: CSP<'a>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// A set of named policies that can extend each other, and the routes that
+/// they are used for.
+///
+/// A policy that extends others is merged into them in order: values that it
+/// adds to a directive are added to what the directive had, including what it
+/// inherited through fallbacks such as `default-src`.
+///
+/// Since a nonce, a hash or `'strict-dynamic'` makes browsers ignore some of
+/// the other sources of a directive, a union of sources doesn't always allow
+/// everything that each policy allows. Such policies can't be resolved, with
+/// a [`RegistryError::Conflict`].
+///
+/// # Example usage
+/// ```rust
+/// use csp::{CSP, PolicyRegistry};
+///
+/// let registry = PolicyRegistry::new()
+///   .define("base", &[], CSP::parse("default-src 'self'; object-src 'none'").unwrap())
+///   .define("payments", &["base"], CSP::parse("script-src https://js.stripe.com").unwrap())
+///   .route("/", "base")
+///   .route("/checkout", "payments");
+///
+/// assert_eq!(
+///   registry.resolve_path("/checkout/cart").unwrap().unwrap().to_string(),
+///   "default-src 'self'; object-src 'none'; script-src 'self' https://js.stripe.com"
+/// );
+/// assert_eq!(registry.route_for("/checkouts"), Some("base"));
+/// ```
+pub struct PolicyRegistry<'a> {
+  definitions: Vec<Definition<'a>>,
+  routes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> PolicyRegistry<'a> {
+  #[must_use]
+  /// Creates a new empty registry
+  pub const fn new() -> Self {
+    Self { definitions: Vec::new(), routes: Vec::new() }
+  }
+
+  #[must_use]
+  /// Defines a policy that extends the named ones, replacing any existing
+  /// definition with the same name
+  pub fn define(mut self, name: &'a str, extends: &[&'a str], policy: CSP<'a>) -> Self {
+    self.definitions.retain(|definition| definition.name != name);
+    self.definitions.push(Definition { name, extends: extends.to_vec(), policy });
+    self
+  }
+
+  #[must_use]
+  /// Uses the named policy for request paths under the prefix, replacing any
+  /// existing route with the same prefix
+  pub fn route(mut self, prefix: &'a str, name: &'a str) -> Self {
+    self.routes.retain(|(existing, _)| *existing != prefix);
+    self.routes.push((prefix, name));
+    self
+  }
+
+  /// Resolves the named policy, merging it into the ones it extends.
+  ///
+  /// # Errors
+  /// If the policy or any policy it extends is not defined, if they extend
+  /// each other in a cycle, or if merging them makes browsers ignore some of
+  /// their sources.
+  pub fn resolve(&self, name: &str) -> Result<CSP<'a>, RegistryError<'a>> {
+    self.resolve_with(name, &mut Vec::new())
+  }
+
+  fn resolve_with(
+    &self,
+    name: &str,
+    stack: &mut Vec<&'a str>,
+  ) -> Result<CSP<'a>, RegistryError<'a>> {
+    let definition = self
+      .definitions
+      .iter()
+      .find(|definition| definition.name == name)
+      .ok_or_else(|| RegistryError::Unknown(name.to_owned()))?;
+
+    if let Some(start) = stack.iter().position(|existing| *existing == definition.name) {
+      let mut cycle: Vec<String> =
+        stack[start..].iter().map(|name| (*name).to_owned()).collect();
+      cycle.push(definition.name.to_owned());
+      return Err(RegistryError::Cycle(cycle));
+    }
+
+    stack.push(definition.name);
+    let mut csp = CSP::new();
+    let mut conflicts = Vec::new();
+    for parent in &definition.extends {
+      let parent = self.resolve_with(parent, stack)?;
+      conflicts.extend(merge(&mut csp, &parent));
+    }
+    stack.pop();
+
+    conflicts.extend(merge(&mut csp, &definition.policy));
+    conflicts.into_iter().next().map_or(Ok(csp), |conflict| {
+      Err(RegistryError::Conflict(definition.name.to_owned(), conflict))
+    })
+  }
+
+  /// Resolves every defined policy and checks that every route uses one, such
+  /// as when starting up.
+  ///
+  /// # Errors
+  /// The first error that resolving a policy or route runs into.
+  pub fn check(&self) -> Result<(), RegistryError<'a>> {
+    for definition in &self.definitions {
+      self.resolve(definition.name)?;
+    }
+    for (_, name) in &self.routes {
+      self.resolve(name)?;
+    }
+    Ok(())
+  }
+
+  #[must_use]
+  /// The name of the policy for a request path, from the route with the
+  /// longest prefix that it is under. Prefixes only match whole path
+  /// segments, so `/admin` matches `/admin/users` but not `/administrator`.
+  pub fn route_for(&self, path: &str) -> Option<&'a str> {
+    self
+      .routes
+      .iter()
+      .filter(|(prefix, _)| is_under(path, prefix))
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, name)| *name)
+  }
+
+  /// Resolves the policy for a request path, if any route matches it.
+  ///
+  /// # Errors
+  /// If the policy of the route can't be resolved.
+  pub fn resolve_path(&self, path: &str) -> Result<Option<CSP<'a>>, RegistryError<'a>> {
+    self.route_for(path).map(|name| self.resolve(name)).transpose()
+  }
+}
+
+fn is_under(path: &str, prefix: &str) -> bool {
+  path
+    .strip_prefix(prefix)
+    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+}
+
+/// Merges the directives of `other` into `csp`, adding the sources of each
+/// directive that `other` has to those it had in `csp`, including through
+/// fallbacks.
+///
+/// The result allows everything that either of them allows for those
+/// directives, except for the sources that are returned, which browsers
+/// ignore along with the ones they conflict with.
+pub fn merge<'a>(csp: &mut CSP<'a>, other: &CSP<'a>) -> Vec<MergeConflict<'a>> {
+  // What directives inherit is decided before `other`'s own fallbacks, such
+  // as its `default-src`, are merged in.
+  let before = csp.clone();
+
+  for directive in other {
+    let kind = directive.kind();
+
+    if csp.get(kind).is_none() {
+      let inherited = before.effective_sources(kind).cloned();
+      match (
+        inherited.and_then(|sources| kind.with_sources(sources)),
+        directive.sources(),
+      ) {
+        (Some(inherited), Some(_)) => csp.push_borrowed(inherited),
+        _ => csp.push_borrowed(directive.clone()),
+      };
+    }
+
+    if let Some(existing) = csp.get_mut(kind) {
+      merge_directive(existing, directive);
+    }
+  }
+
+  let mut conflicts = Vec::new();
+  for directive in other {
+    let kind = directive.kind();
+    let Some(merged) = csp.get(kind).and_then(Directive::sources) else {
+      continue;
+    };
+    for sources in
+      [before.effective_sources(kind), directive.sources()].into_iter().flatten()
+    {
+      union(&mut conflicts, &disabled(kind, sources, merged));
+    }
+  }
+  conflicts
+}
+
+/// The sources that browsers ignore in `merged`, a union that includes
+/// `sources`, but not in `sources` alone.
+fn disabled<'a>(
+  kind: DirectiveKind,
+  sources: &Sources<'a>,
+  merged: &Sources<'a>,
+) -> Vec<MergeConflict<'a>> {
+  let is_script = kind.is_script();
+  let mut conflicts = Vec::new();
+
+  let strict_dynamic = merged.is_strict_dynamic(is_script);
+
+  if sources.allows_inline(is_script) && !merged.allows_inline(is_script) {
+    let by = if strict_dynamic {
+      Some(&Source::StrictDynamic)
+    } else {
+      merged.0.iter().find(|source| matches!(source, Source::Nonce(_) | Source::Hash(_)))
+    };
+    if let Some(by) = by {
+      conflicts.push(MergeConflict {
+        directive: kind,
+        disabled: Source::UnsafeInline,
+        by: by.clone(),
+      });
+    }
+  }
+  if strict_dynamic && !sources.is_strict_dynamic(is_script) {
+    for source in sources.0.iter() {
+      if matches!(source, Source::Host(_) | Source::Scheme(_) | Source::Self_) {
+        conflicts.push(MergeConflict {
+          directive: kind,
+          disabled: source.clone(),
+          by: Source::StrictDynamic,
+        });
+      }
+    }
+  }
+  conflicts
+}
+
+fn merge_directive<'a>(existing: &mut Directive<'a>, other: &Directive<'a>) {
+  match (existing, other) {
+    (Directive::PluginTypes(existing), Directive::PluginTypes(other)) => {
+      union(existing.0.to_mut(), &other.0);
+    }
+    (Directive::ReportUri(existing), Directive::ReportUri(other)) => {
+      union(existing.0.to_mut(), &other.0);
+    }
+    (Directive::Sandbox(existing), Directive::Sandbox(other)) => {
+      union(existing.0.to_mut(), &other.0);
+    }
+    (Directive::TrustedTypes(existing), Directive::TrustedTypes(other)) => {
+      union(existing, other);
+    }
+    (Directive::RequireSriFor(existing), Directive::RequireSriFor(other)) => {
+      if existing != other {
+        *existing = SriFor::ScriptStyle;
+      }
+    }
+    (Directive::ReportTo(existing), Directive::ReportTo(other)) => *existing = other,
+    (existing, other) => {
+      if let (Some(sources), Some(other)) = (existing.sources_mut(), other.sources()) {
+        union(sources.0.to_mut(), &other.0);
+      }
+    }
+  }
+}
+
+fn union<T: PartialEq + Clone>(values: &mut Vec<T>, others: &[T]) {
+  for other in others {
+    if !values.contains(other) {
+      values.push(other.clone());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy(policy: &'static str) -> CSP<'static> {
+    CSP::parse(policy).unwrap()
+  }
+
+  #[test]
+  fn inheritance() {
+    let registry = PolicyRegistry::new()
+      .define(
+        "base",
+        &[],
+        policy("default-src 'self'; script-src 'self'; sandbox allow-forms"),
+      )
+      .define("analytics", &["base"], policy("connect-src https://a.example.org"))
+      .define(
+        "admin",
+        &["base", "analytics"],
+        policy("script-src 'nonce-a'; sandbox allow-scripts; report-to admin"),
+      );
+
+    assert_eq!(
+      registry.resolve("admin").unwrap().to_string(),
+      "default-src 'self'; script-src 'self' 'nonce-a'; sandbox allow-forms allow-scripts; \
+       connect-src 'self' https://a.example.org; report-to admin"
+    );
+    assert!(registry.check().is_ok());
+  }
+
+  #[test]
+  fn errors() {
+    let registry = PolicyRegistry::new()
+      .define("a", &["b"], CSP::new())
+      .define("b", &["c"], CSP::new())
+      .define("c", &["a"], CSP::new())
+      .define("d", &["missing"], CSP::new());
+
+    assert_eq!(
+      registry.resolve("a").unwrap_err().to_string(),
+      "policies extend each other: a -> b -> c -> a"
+    );
+    assert_eq!(
+      registry.resolve("d").unwrap_err(),
+      RegistryError::Unknown("missing".to_owned())
+    );
+    assert!(registry.check().is_err());
+  }
+
+  #[test]
+  fn conflicts() {
+    let registry = PolicyRegistry::new()
+      .define("legacy", &[], policy("script-src 'self' 'unsafe-inline'"))
+      .define("nonce", &["legacy"], policy("script-src 'nonce-a'"))
+      .define("dynamic", &[], policy("script-src 'strict-dynamic' 'nonce-a'"))
+      .define("both", &["legacy", "dynamic"], CSP::new())
+      .define("styles", &["legacy"], policy("style-src 'nonce-a'"));
+
+    assert_eq!(
+      registry.resolve("nonce").unwrap_err().to_string(),
+      "policy `nonce`: script-src: 'nonce-a' disables 'unsafe-inline'"
+    );
+
+    let mut csp = policy("script-src 'self' 'unsafe-inline'");
+    let conflicts = merge(&mut csp, &policy("script-src 'strict-dynamic' 'nonce-a'"));
+    assert_eq!(
+      conflicts.iter().map(ToString::to_string).collect::<Vec<_>>(),
+      [
+        "script-src: 'strict-dynamic' disables 'unsafe-inline'",
+        "script-src: 'strict-dynamic' disables 'self'"
+      ]
+    );
+    assert_eq!(
+      registry.resolve("both"),
+      Err(RegistryError::Conflict(
+        "both".to_owned(),
+        MergeConflict {
+          directive: DirectiveKind::ScriptSrc,
+          disabled: Source::UnsafeInline,
+          by: Source::StrictDynamic,
+        }
+      ))
+    );
+    assert!(registry.resolve("styles").is_ok());
+  }
+
+  #[test]
+  fn routes() {
+    let registry = PolicyRegistry::new()
+      .define("base", &[], CSP::new())
+      .route("/", "base")
+      .route("/admin", "admin")
+      .route("/api/", "api");
+
+    assert_eq!(registry.route_for("/"), Some("base"));
+    assert_eq!(registry.route_for("/admin"), Some("admin"));
+    assert_eq!(registry.route_for("/admin/users"), Some("admin"));
+    assert_eq!(registry.route_for("/administrator"), Some("base"));
+    assert_eq!(registry.route_for("/api/v1"), Some("api"));
+    assert_eq!(registry.route_for("/api"), Some("base"));
+    assert!(registry.resolve_path("/admin").is_err());
+    assert_eq!(PolicyRegistry::new().resolve_path("/"), Ok(None));
+  }
+}
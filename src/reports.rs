@@ -0,0 +1,202 @@
+//! Reading the violation reports that browsers send, to allow what they
+//! report.
+
+use std::{error, fmt};
+
+use crate::{
+  DirectiveKind, PageSources, Source,
+  json::{self, Value},
+  matching::Url,
+  site::source_of,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a string could not be read as violation reports.
+pub enum ReportError {
+  /// The string is not valid JSON, with the byte offset of the error.
+  Json(usize),
+}
+
+impl fmt::Display for ReportError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Json(offset) => write!(fmt, "invalid JSON at byte {offset}"),
+    }
+  }
+}
+
+impl error::Error for ReportError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A violation report, as sent to `report-uri` or `report-to` endpoints.
+pub struct ViolationReport {
+  /// The URL of the document that the policy protects.
+  pub document_url: String,
+  /// The URL of the blocked resource, or a keyword such as `inline` or
+  /// `eval` for inline content and code evaluation.
+  pub blocked_url: String,
+  /// The name of the directive that blocked the resource.
+  pub effective_directive: String,
+}
+
+impl ViolationReport {
+  /// Reads reports from a JSON document, which can be a single report, an
+  /// array of them, or one report per line. Both the `application/csp-report`
+  /// format of `report-uri` and the `csp-violation` reports of `report-to`
+  /// are read, and anything else is skipped.
+  ///
+  /// # Errors
+  /// If the string is not JSON, or JSON on every line.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{PageSources, ViolationReport};
+  ///
+  /// let reports = ViolationReport::parse(
+  ///   r#"[
+  ///     {"csp-report": {"document-uri": "https://example.org/",
+  ///       "blocked-uri": "https://cdn.example.net/lib.js",
+  ///       "effective-directive": "script-src-elem"}},
+  ///     {"type": "csp-violation", "body": {"documentURL": "https://example.org/",
+  ///       "blockedURL": "inline", "effectiveDirective": "style-src-elem"}}
+  ///   ]"#,
+  /// )
+  /// .unwrap();
+  /// assert_eq!(reports.len(), 2);
+  ///
+  /// let mut sources = PageSources::default();
+  /// assert!(sources.add_report(&reports[0]));
+  /// assert!(!sources.add_report(&reports[1]));
+  /// assert_eq!(
+  ///   sources.policy().to_string(),
+  ///   "default-src 'none'; script-src https://cdn.example.net; base-uri 'none'; \
+  ///    form-action 'none'"
+  /// );
+  /// ```
+  pub fn parse(json: &str) -> Result<Vec<Self>, ReportError> {
+    let documents = match json::parse(json) {
+      Ok(document) => vec![document],
+      Err(offset) => {
+        let lines = json.lines().filter(|line| !line.trim().is_empty());
+        lines
+          .map(json::parse)
+          .collect::<Result<_, _>>()
+          .map_err(|_| ReportError::Json(offset))?
+      }
+    };
+
+    let mut reports = Vec::new();
+    for document in &documents {
+      match document {
+        Value::Array(values) => reports.extend(values.iter().filter_map(Self::from_json)),
+        value => reports.extend(Self::from_json(value)),
+      }
+    }
+    Ok(reports)
+  }
+
+  fn from_json(value: &Value) -> Option<Self> {
+    fn string<'v>(value: &'v Value, name: &str) -> Option<&'v str> {
+      value.get(name).and_then(Value::as_str)
+    }
+
+    let (report, [document, blocked, effective, violated]) =
+      if let Some(report) = value.get("csp-report") {
+        (
+          report,
+          ["document-uri", "blocked-uri", "effective-directive", "violated-directive"],
+        )
+      } else if string(value, "type") == Some("csp-violation") {
+        let body = value.get("body")?;
+        (body, ["documentURL", "blockedURL", "effectiveDirective", "violatedDirective"])
+      } else {
+        return None;
+      };
+
+    // Older browsers only send the violated directive, with its value.
+    let directive = string(report, effective)
+      .or_else(|| string(report, violated)?.split_whitespace().next())?;
+
+    Some(Self {
+      document_url: string(report, document)?.to_owned(),
+      blocked_url: string(report, blocked).unwrap_or_default().to_owned(),
+      effective_directive: directive.to_owned(),
+    })
+  }
+}
+
+impl PageSources {
+  /// Adds the source that would have allowed what a report says was
+  /// blocked, returning if there was one. Inline content and code
+  /// evaluation are not allowed this way, as they are better fixed with
+  /// hashes and nonces, or by moving the code into files, than with
+  /// `'unsafe-*'` keywords. Neither are `frame-ancestors` violations, as
+  /// their reports don't say which ancestor was blocked.
+  ///
+  /// As with [`HarPolicy`](crate::HarPolicy), `script-src` and `style-src`
+  /// are used instead of `script-src-elem` and `style-src-elem`.
+  pub fn add_report(&mut self, report: &ViolationReport) -> bool {
+    let kind = match DirectiveKind::from_name(&report.effective_directive) {
+      Some(DirectiveKind::ScriptSrcElem) => DirectiveKind::ScriptSrc,
+      Some(DirectiveKind::StyleSrcElem) => DirectiveKind::StyleSrc,
+      Some(kind) if kind.has_sources() && kind != DirectiveKind::FrameAncestors => kind,
+      _ => return false,
+    };
+
+    let blocked = report.blocked_url.as_str();
+    let source = match (Url::parse(blocked), Url::parse(&report.document_url)) {
+      (Some(url), Some(document)) if Source::Self_.matches_url(&url, &document) => {
+        Some(Source::Self_.to_string())
+      }
+      (Some(_), _) => source_of(blocked),
+      // Some browsers only report the scheme of `data:` and `blob:` URLs.
+      (None, _) if matches!(blocked, "data" | "blob") => Some(format!("{blocked}:")),
+      (None, _) => None,
+    };
+
+    let Some(source) = source else {
+      return false;
+    };
+    self.sources.entry(kind).or_default().insert(source);
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formats() {
+    let reports = ViolationReport::parse(
+      r#"[
+        {"type": "csp-violation", "body": {"documentURL": "https://example.org/",
+          "blockedURL": "wss://example.org/live", "effectiveDirective": "connect-src"}},
+        {"type": "deprecation", "body": {}},
+        {"type": "csp-violation", "body": {"documentURL": "https://example.org/",
+          "blockedURL": "eval", "effectiveDirective": "script-src"}},
+        {"csp-report": {"document-uri": "https://example.org/", "blocked-uri": "data",
+          "violated-directive": "img-src 'self'"}},
+        {"csp-report": {"document-uri": "https://example.org/",
+          "blocked-uri": "https://fonts.example.net:8443/a.woff2",
+          "effective-directive": "font-src"}},
+        {"csp-report": {"document-uri": "https://example.org/",
+          "blocked-uri": "https://example.org/", "effective-directive": "frame-ancestors"}}
+      ]"#,
+    )
+    .unwrap();
+    assert_eq!(reports.len(), 5);
+    assert_eq!(reports[2].effective_directive, "img-src");
+
+    let mut sources = PageSources::default();
+    let added: Vec<_> = reports.iter().map(|report| sources.add_report(report)).collect();
+    assert_eq!(added, [true, false, true, true, false]);
+    assert_eq!(
+      sources.policy().to_string(),
+      "default-src 'none'; connect-src 'self'; font-src https://fonts.example.net:8443; \
+       img-src data:; base-uri 'none'; form-action 'none'"
+    );
+
+    assert_eq!(ViolationReport::parse("{} {}"), Err(ReportError::Json(3)));
+  }
+}
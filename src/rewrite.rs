@@ -0,0 +1,159 @@
+//! Rewriting sources across every directive of a policy at once.
+
+use crate::{CSP, DirectiveKind, Source, expand::FETCH_DIRECTIVES};
+
+impl<'a> CSP<'a> {
+  /// Passes every source of every directive that takes [`Sources`] through
+  /// the given function, replacing it with what the function returns.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, Source};
+  ///
+  /// let mut csp = CSP::parse("img-src http: 'self'; media-src http:").unwrap();
+  /// csp.map_sources(|source| match source {
+  ///   Source::Scheme("http") => Source::Scheme("https"),
+  ///   source => source,
+  /// });
+  ///
+  /// assert_eq!(csp.to_string(), "img-src https: 'self'; media-src https:");
+  /// ```
+  ///
+  /// [`Sources`]: crate::Sources
+  pub fn map_sources<F>(&mut self, mut map: F) -> &mut Self
+  where
+    F: FnMut(Source<'a>) -> Source<'a>,
+  {
+    for sources in self.iter_mut().filter_map(|directive| directive.sources_mut()) {
+      sources.0 = sources.0.iter().cloned().map(&mut map).collect();
+    }
+    self
+  }
+
+  /// Replaces every `Source::Host` that is equal to `old` with `new`, such as
+  /// when moving from one CDN to another.
+  ///
+  /// Whole host source expressions are compared, so schemes, ports and paths
+  /// have to match too. Schemes and hosts are compared case-insensitively.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let mut csp =
+  ///   CSP::parse("script-src https://staging.example.org; img-src https://staging.example.org")
+  ///     .unwrap();
+  /// csp.replace_host("https://staging.example.org", "https://www.example.org");
+  ///
+  /// assert_eq!(
+  ///   csp.to_string(),
+  ///   "script-src https://www.example.org; img-src https://www.example.org"
+  /// );
+  /// ```
+  pub fn replace_host(&mut self, old: &str, new: &'a str) -> &mut Self {
+    let old = Source::Host(old);
+    self.map_sources(|source| if source == old { Source::Host(new) } else { source })
+  }
+
+  /// Removes the source from every directive that takes [`Sources`].
+  ///
+  /// Directives that are left without any sources become `'none'`, instead of
+  /// being removed and falling back to something else.
+  ///
+  /// [`Sources`]: crate::Sources
+  pub fn remove_source_everywhere(&mut self, source: &Source<'_>) -> &mut Self {
+    for sources in self.iter_mut().filter_map(|directive| directive.sources_mut()) {
+      sources.0.to_mut().retain(|existing| existing != source);
+    }
+    self
+  }
+
+  /// Adds the source to every fetch directive that currently inherits its
+  /// sources from `default-src`, by writing those directives out with
+  /// `default-src`'s sources and the new one.
+  ///
+  /// `default-src` itself is left as it is, so it stays tight for the fetch
+  /// directives that browsers might add later. Does nothing if there is no
+  /// `default-src`.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, Source};
+  ///
+  /// let mut csp = CSP::parse("default-src 'self'; script-src 'self'; child-src 'none'").unwrap();
+  /// csp.push_source_to_inherited(Source::Host("https://cdn.example.org"));
+  ///
+  /// assert_eq!(
+  ///   csp.get(csp::DirectiveKind::ImgSrc).unwrap().to_string(),
+  ///   "img-src 'self' https://cdn.example.org"
+  /// );
+  /// // Script elements inherit from `script-src`, and frames from `child-src`.
+  /// assert!(csp.get(csp::DirectiveKind::ScriptSrcElem).is_none());
+  /// assert!(csp.get(csp::DirectiveKind::FrameSrc).is_none());
+  /// ```
+  pub fn push_source_to_inherited(&mut self, source: Source<'a>) -> &mut Self {
+    let Some(default_sources) =
+      self.get(DirectiveKind::DefaultSrc).and_then(|d| d.sources())
+    else {
+      return self;
+    };
+    let default_sources = default_sources.clone().push(source);
+
+    // Decided up front, as the added directives would otherwise shadow
+    // `default-src` for the ones that fall back to them.
+    let inheriting: Vec<_> = FETCH_DIRECTIVES
+      .into_iter()
+      .filter(|kind| {
+        kind
+          .fallback_list()
+          .iter()
+          .find(|kind| self.get(**kind).is_some())
+          .is_some_and(|kind| *kind == DirectiveKind::DefaultSrc)
+      })
+      .collect();
+
+    for kind in inheriting {
+      self.0.to_mut().extend(kind.with_sources(default_sources.clone()));
+    }
+    self
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remove_everywhere() {
+    let mut csp = CSP::parse(
+      "default-src 'self' 'unsafe-inline'; style-src 'UNSAFE-INLINE'; sandbox allow-scripts",
+    )
+    .unwrap();
+    csp.remove_source_everywhere(&Source::UnsafeInline);
+
+    assert_eq!(
+      csp.to_string(),
+      "default-src 'self'; style-src 'none'; sandbox allow-scripts"
+    );
+  }
+
+  #[test]
+  fn inherited() {
+    let mut csp =
+      CSP::parse("default-src 'none'; img-src 'self'; script-src 'self'").unwrap();
+    let before = csp.clone();
+    csp.push_source_to_inherited(Source::Host("https://cdn.example.org"));
+
+    assert_eq!(
+      csp.get(DirectiveKind::FrameSrc).unwrap().to_string(),
+      "frame-src https://cdn.example.org"
+    );
+    assert!(crate::compare(&before, &csp).loosenings.iter().all(|change| {
+      !matches!(change.directive, DirectiveKind::ImgSrc | DirectiveKind::ScriptSrc)
+    }));
+
+    let mut csp = CSP::parse("img-src 'self'").unwrap();
+    csp.push_source_to_inherited(Source::Self_);
+    assert_eq!(csp.to_string(), "img-src 'self'");
+  }
+}
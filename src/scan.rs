@@ -0,0 +1,430 @@
+//! Finding the inline scripts and styles of HTML documents, to allow them by
+//! their hashes.
+
+use crate::{
+  CSP, Directive, HashAlgorithm, Source, Sources,
+  registry::{MergeConflict, merge},
+};
+
+/// Elements whose content is text, so tags in them are not elements.
+pub const RAW_TEXT: [&str; 9] = [
+  "iframe", "noembed", "noframes", "noscript", "script", "style", "textarea", "title",
+  "xmp",
+];
+
+/// The `type`s of `<script>` elements that browsers run as scripts.
+const SCRIPT_TYPES: [&str; 8] = [
+  "",
+  "module",
+  "application/ecmascript",
+  "application/javascript",
+  "application/x-javascript",
+  "text/ecmascript",
+  "text/javascript",
+  "text/jscript",
+];
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The SHA-256 hashes of the inline scripts and styles of HTML documents, as
+/// found by [`InlineHashes::scan`].
+///
+/// # Example usage
+/// ```rust
+/// use csp::{CSP, InlineHashes};
+///
+/// let hashes = InlineHashes::scan_with_attributes(
+///   "<script>alert(1)</script><button onclick=\"go()\">Go</button>",
+/// );
+/// let mut csp = CSP::parse("default-src 'self'").unwrap();
+///
+/// assert!(hashes.merge_into(&mut csp).is_empty());
+/// assert_eq!(
+///   csp.to_string(),
+///   "default-src 'self'; script-src-elem 'self' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='; \
+///    script-src-attr 'self' 'unsafe-hashes' 'sha256-5KYv+PUboo5h+0+YAtGRPbwv5d/QxzHslP4YGnUaxRw='"
+/// );
+/// ```
+pub struct InlineHashes {
+  /// Hashes of the content of inline `<script>` elements.
+  pub script_elements: Vec<String>,
+  /// Hashes of the content of `<style>` elements.
+  pub style_elements: Vec<String>,
+  /// Hashes of event handler attributes, such as `onclick`.
+  pub script_attributes: Vec<String>,
+  /// Hashes of `style` attributes.
+  pub style_attributes: Vec<String>,
+}
+
+impl InlineHashes {
+  #[must_use]
+  /// Hashes the inline `<script>` and `<style>` elements of a document.
+  ///
+  /// Scripts with a `src`, or a `type` that browsers don't run, such as
+  /// JSON data, are skipped, as are elements in comments and in elements
+  /// whose content is text, such as `<textarea>`. The scanner is lenient
+  /// rather than a full HTML parser, so documents that browsers only parse
+  /// by recovering from errors may give different results.
+  pub fn scan(html: &str) -> Self {
+    let mut hashes = Self::default();
+    hashes.scan_document(html, false);
+    hashes
+  }
+
+  #[must_use]
+  /// Hashes the inline elements like [`scan`](Self::scan), as well as event
+  /// handler and `style` attributes, which can only be allowed by their
+  /// hashes along with `'unsafe-hashes'`.
+  ///
+  /// Attribute values are hashed after decoding character references, such
+  /// as `&amp;`, as browsers do. Only the numeric ones and those for `&`,
+  /// `<`, `>`, `"` and `'` are decoded.
+  pub fn scan_with_attributes(html: &str) -> Self {
+    let mut hashes = Self::default();
+    hashes.scan_document(html, true);
+    hashes
+  }
+
+  /// Adds the hashes of another document, such as another page of a site.
+  pub fn extend(&mut self, other: &Self) {
+    for (hashes, others) in [
+      (&mut self.script_elements, &other.script_elements),
+      (&mut self.style_elements, &other.style_elements),
+      (&mut self.script_attributes, &other.script_attributes),
+      (&mut self.style_attributes, &other.style_attributes),
+    ] {
+      for hash in others {
+        push_unique(hashes, hash.clone());
+      }
+    }
+  }
+
+  #[must_use]
+  /// If no inline scripts or styles were found.
+  pub const fn is_empty(&self) -> bool {
+    self.script_elements.is_empty()
+      && self.style_elements.is_empty()
+      && self.script_attributes.is_empty()
+      && self.style_attributes.is_empty()
+  }
+
+  #[must_use]
+  /// The hashes as directives: `script-src-elem` and `style-src-elem` for the
+  /// elements, and `script-src-attr` and `style-src-attr` with
+  /// `'unsafe-hashes'` for the attributes. Only directives with hashes are
+  /// included.
+  pub fn directives(&self) -> Vec<Directive<'_>> {
+    let mut directives = Vec::new();
+    if !self.script_elements.is_empty() {
+      directives.push(Directive::ScriptSrcElem(sources(&self.script_elements, false)));
+    }
+    if !self.style_elements.is_empty() {
+      directives.push(Directive::StyleSrcElem(sources(&self.style_elements, false)));
+    }
+    if !self.script_attributes.is_empty() {
+      directives.push(Directive::ScriptSrcAttr(sources(&self.script_attributes, true)));
+    }
+    if !self.style_attributes.is_empty() {
+      directives.push(Directive::StyleSrcAttr(sources(&self.style_attributes, true)));
+    }
+    directives
+  }
+
+  /// Adds the [`directives`](Self::directives) to a policy, on top of what
+  /// each of them inherited in it, such as the hosts of `script-src`.
+  ///
+  /// Returns the sources of the policy that browsers ignore once it has
+  /// hashes, which is `'unsafe-inline'` if it had it, so inline content
+  /// without a hash is no longer allowed.
+  pub fn merge_into<'a>(&'a self, csp: &mut CSP<'a>) -> Vec<MergeConflict<'a>> {
+    let mut hashes = CSP::new();
+    for directive in self.directives() {
+      hashes.push_borrowed(directive);
+    }
+    merge(csp, &hashes)
+  }
+
+  fn scan_document(&mut self, html: &str, attributes: bool) {
+    walk(html, |tag, content| {
+      if attributes {
+        for (name, value) in &tag.attributes {
+          if name.len() > 2
+            && name.get(..2).is_some_and(|on| on.eq_ignore_ascii_case("on"))
+          {
+            push_unique(&mut self.script_attributes, hash(&decode(value)));
+          } else if name.eq_ignore_ascii_case("style") {
+            push_unique(&mut self.style_attributes, hash(&decode(value)));
+          }
+        }
+      }
+
+      if tag.is("script") {
+        let content = content.unwrap_or_default();
+        if tag.is_script() && tag.attribute("src").is_none() && !content.is_empty() {
+          push_unique(&mut self.script_elements, hash(content));
+        }
+      } else if tag.is("style") {
+        push_unique(&mut self.style_elements, hash(content.unwrap_or_default()));
+      }
+    });
+  }
+}
+
+/// Calls `visit` with every start tag of a document, along with the content
+/// of elements whose content is text, such as `<script>`. Tags in comments
+/// and in such content are skipped.
+pub fn walk<'a>(html: &'a str, mut visit: impl FnMut(&Tag<'a>, Option<&'a str>)) {
+  let mut rest = html;
+
+  while let Some(start) = rest.find('<') {
+    rest = &rest[start..];
+
+    if let Some(comment) = rest.strip_prefix("<!--") {
+      rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+      continue;
+    }
+    if !rest[1..].starts_with(|char: char| char.is_ascii_alphabetic()) {
+      rest = rest[1..].find('>').map_or("", |end| &rest[end + 2..]);
+      continue;
+    }
+
+    let (tag, after) = Tag::parse(&rest[1..]);
+    rest = after;
+
+    // Browsers ignore the `/` of `<script />`, so its content still follows.
+    let content = if RAW_TEXT.iter().any(|name| tag.is(name)) {
+      let (content, after) = raw_text(rest, tag.name);
+      rest = after;
+      Some(content)
+    } else {
+      None
+    };
+    visit(&tag, content);
+  }
+}
+
+/// A start tag.
+pub struct Tag<'a> {
+  pub name: &'a str,
+  pub attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Tag<'a> {
+  /// Parses a start tag after its `<`, returning it and the rest of the
+  /// document after its `>`.
+  pub fn parse(html: &'a str) -> (Self, &'a str) {
+    let end = html
+      .find(|char: char| char.is_ascii_whitespace() || char == '/' || char == '>')
+      .unwrap_or(html.len());
+    let mut tag = Tag { name: &html[..end], attributes: Vec::new() };
+    let mut rest = &html[end..];
+
+    loop {
+      rest = rest.trim_start_matches(|char: char| char.is_ascii_whitespace());
+      if let Some(after) = rest.strip_prefix('>') {
+        return (tag, after);
+      }
+      if let Some(after) = rest.strip_prefix('/') {
+        rest = after;
+        continue;
+      }
+      if rest.is_empty() {
+        return (tag, rest);
+      }
+
+      // The first character is part of the name, even if it is a `=`.
+      let first = rest.chars().next().map_or(0, char::len_utf8);
+      let end = rest[first..]
+        .find(|char: char| char.is_ascii_whitespace() || matches!(char, '/' | '>' | '='))
+        .map_or(rest.len(), |end| end + first);
+      let name = &rest[..end];
+      rest = rest[end..].trim_start_matches(|char: char| char.is_ascii_whitespace());
+
+      let mut value = "";
+      if let Some(after) = rest.strip_prefix('=') {
+        rest = after.trim_start_matches(|char: char| char.is_ascii_whitespace());
+        let (parsed, after) = if let Some(quote @ ('"' | '\'')) = rest.chars().next() {
+          let quoted = &rest[1..];
+          quoted
+            .find(quote)
+            .map_or((quoted, ""), |end| (&quoted[..end], &quoted[end + 1..]))
+        } else {
+          let end = rest
+            .find(|char: char| char.is_ascii_whitespace() || char == '>')
+            .unwrap_or(rest.len());
+          (&rest[..end], &rest[end..])
+        };
+        value = parsed;
+        rest = after;
+      }
+
+      if !tag.attributes.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+        tag.attributes.push((name, value));
+      }
+    }
+  }
+
+  /// If the tag has the given (ASCII case-insensitive) name.
+  pub const fn is(&self, name: &str) -> bool {
+    self.name.eq_ignore_ascii_case(name)
+  }
+
+  /// If the tag is of a `<script>` element that browsers run as a script,
+  /// rather than one with data, such as JSON.
+  pub fn is_script(&self) -> bool {
+    self.is("script")
+      && self.attribute("type").is_none_or(|kind| {
+        SCRIPT_TYPES.iter().any(|script| script.eq_ignore_ascii_case(kind.trim()))
+      })
+  }
+
+  pub fn attribute(&self, name: &str) -> Option<&'a str> {
+    self
+      .attributes
+      .iter()
+      .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+      .map(|(_, value)| *value)
+  }
+}
+
+/// Splits off the text content of an element, returning it and the rest of
+/// the document after its end tag.
+fn raw_text<'a>(html: &'a str, name: &str) -> (&'a str, &'a str) {
+  let mut offset = 0;
+  while let Some(start) = html[offset..].find("</").map(|start| start + offset) {
+    let after = &html[start + 2..];
+    let is_end =
+      after.get(..name.len()).is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+        && after[name.len()..].starts_with(|char: char| {
+          char.is_ascii_whitespace() || char == '/' || char == '>'
+        });
+    if is_end {
+      let rest = &after[name.len()..];
+      return (&html[..start], rest.find('>').map_or("", |end| &rest[end + 1..]));
+    }
+    offset = start + 2;
+  }
+  (html, "")
+}
+
+/// Decodes the character references that inline code commonly contains.
+pub fn decode(value: &str) -> String {
+  let mut decoded = String::with_capacity(value.len());
+  let mut rest = value;
+
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let reference = rest[1..].find(';').map(|end| &rest[1..=end]);
+    let char = reference.and_then(|reference| match reference {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      _ => {
+        let number = reference.strip_prefix('#')?;
+        let code = match number.strip_prefix(['x', 'X']) {
+          Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+          None => number.parse().ok()?,
+        };
+        char::from_u32(code)
+      }
+    });
+
+    if let (Some(char), Some(reference)) = (char, reference) {
+      decoded.push(char);
+      rest = &rest[reference.len() + 2..];
+    } else {
+      decoded.push('&');
+      rest = &rest[1..];
+    }
+  }
+
+  decoded.push_str(rest);
+  decoded
+}
+
+fn sources(hashes: &[String], unsafe_hashes: bool) -> Sources<'_> {
+  let mut sources = Sources::new();
+  if unsafe_hashes {
+    sources.push_borrowed(Source::UnsafeHashes);
+  }
+  for hash in hashes {
+    sources.push_borrowed(Source::Hash((HashAlgorithm::Sha256.name(), hash)));
+  }
+  sources
+}
+
+fn hash(content: &str) -> String {
+  HashAlgorithm::Sha256.digest(content.as_bytes())
+}
+
+fn push_unique(hashes: &mut Vec<String>, hash: String) {
+  if !hashes.contains(&hash) {
+    hashes.push(hash);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn elements() {
+    let hashes = InlineHashes::scan_with_attributes(
+      "<!doctype html><HTML><head>\
+       <SCRIPT type=module>alert(1)</script >\
+       <script src=\"/app.js\"></script>\
+       <script type=\"application/ld+json\">{}</script>\
+       <script></script>\
+       <!-- <script>alert(2)</script> -->\
+       <style media=print>body { color: red }</style>\
+       </head><body>\
+       <textarea><script>alert(3)</script></textarea>\
+       <a href=\"#\" onclick='go(\"&amp;\")' style=color:red>a</a>\
+       <b onmouseover=\"go(&quot;&#38;&quot;)\">b</b>\
+       <script>alert(1)</script>\
+       </body></HTML>",
+    );
+
+    assert_eq!(hashes.script_elements, ["bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI="]);
+    assert_eq!(hashes.style_elements, ["kl6HQb5peP+QG0x7FWklMRxR/HYq4xozK9Oa6BWSDQA="]);
+    assert_eq!(
+      hashes.script_attributes,
+      ["+J7AMLNSaBg1N7oZeQoPJ/q44P5OCPoMfX3adx2Bh5I="]
+    );
+    assert_eq!(hashes.style_attributes, ["8f935d27GvUutRyY9yWScUMiFUk4WTdZURISiYfPOeQ="]);
+    assert_eq!(
+      InlineHashes::scan("<b style=color:red onclick=go()>"),
+      InlineHashes::default()
+    );
+  }
+
+  #[test]
+  fn directives() {
+    let mut hashes = InlineHashes::scan("<style>a{}</style>");
+    hashes.extend(&InlineHashes::scan("<script>a()</script><style>a{}</style>"));
+
+    let mut csp =
+      CSP::parse("default-src 'self'; script-src 'self' https://cdn.example.org")
+        .unwrap();
+    assert!(hashes.merge_into(&mut csp).is_empty());
+
+    assert_eq!(
+      csp.to_string(),
+      "default-src 'self'; script-src 'self' https://cdn.example.org; \
+       script-src-elem 'self' https://cdn.example.org \
+       'sha256-qVpDBgj7bpq5hMAcGp3AOc79J3Y1Z4HvySTwKrWDoy4='; \
+       style-src-elem 'self' 'sha256-X1RutGBrXCt9KkSaXMK7tHftWiRscFHOhxsS8tv8hBk='"
+    );
+    assert!(InlineHashes::default().directives().is_empty());
+
+    let mut csp = CSP::parse("style-src 'self' 'unsafe-inline'").unwrap();
+    assert_eq!(
+      hashes.merge_into(&mut csp)[0].to_string(),
+      "style-src-elem: 'sha256-X1RutGBrXCt9KkSaXMK7tHftWiRscFHOhxsS8tv8hBk=' disables \
+       'unsafe-inline'"
+    );
+  }
+}
@@ -0,0 +1,555 @@
+//! Generating policies for static sites from their built files.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  CSP, Directive, DirectiveKind, InlineHashes, Source, Sources,
+  parse::parse_directive,
+  scan::{Tag, decode, walk},
+};
+
+/// Calls in scripts whose first argument is a URL, and the directive that
+/// governs it.
+const SCRIPT_CALLS: [(&str, DirectiveKind); 8] = [
+  ("fetch(", DirectiveKind::ConnectSrc),
+  ("WebSocket(", DirectiveKind::ConnectSrc),
+  ("EventSource(", DirectiveKind::ConnectSrc),
+  ("sendBeacon(", DirectiveKind::ConnectSrc),
+  ("Worker(", DirectiveKind::WorkerSrc),
+  ("importScripts(", DirectiveKind::ScriptSrc),
+  ("import(", DirectiveKind::ScriptSrc),
+  ("from ", DirectiveKind::ScriptSrc),
+];
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The sources that a page or a whole site loads resources from, and the
+/// hashes of its inline scripts and styles.
+///
+/// Finding URLs is best-effort: the ones in markup and stylesheets are
+/// found reliably, while scripts are only searched for string literals
+/// passed to calls such as `fetch`. Anything else has to be added by hand.
+///
+/// # Example usage
+/// ```rust
+/// use csp::PageSources;
+///
+/// let mut page = PageSources::default();
+/// page.scan_html(
+///   "<link rel=stylesheet href=/main.css><img src=\"https://images.example.org/a.png\">\
+///    <script>fetch('/api')</script>",
+/// );
+/// page.scan_css("@font-face { src: url(https://fonts.example.org/a.woff2) }");
+///
+/// assert_eq!(
+///   page.policy().to_string(),
+///   "default-src 'none'; connect-src 'self'; font-src https://fonts.example.org; \
+///    img-src https://images.example.org; style-src 'self'; base-uri 'none'; form-action 'none'; \
+///    script-src-elem 'sha256-XP0yLg1cSpl//lYZnQEzpcDxd3XcQYC8fHLOeJZUe+8='"
+/// );
+/// ```
+pub struct PageSources {
+  /// The sources of each directive, as they appear in a policy.
+  pub sources: BTreeMap<DirectiveKind, BTreeSet<String>>,
+  /// The hashes of inline scripts and styles, including event handlers and
+  /// `style` attributes.
+  pub hashes: InlineHashes,
+}
+
+impl PageSources {
+  /// Adds the resources that an HTML document loads, and its inline scripts
+  /// and styles.
+  pub fn scan_html(&mut self, html: &str) {
+    self.hashes.extend(&InlineHashes::scan_with_attributes(html));
+
+    walk(html, |tag, content| {
+      for (kind, url) in urls(tag) {
+        self.add(kind, &url);
+      }
+
+      if let Some(style) = tag.attribute("style") {
+        self.scan_css(&decode(style));
+      }
+      match content {
+        Some(content) if tag.is("style") => self.scan_css(content),
+        Some(content) if tag.is_script() => self.scan_js(content),
+        _ => {}
+      }
+    });
+  }
+
+  /// Adds the resources that a stylesheet loads: `@import`s, the fonts of
+  /// `@font-face` rules, and images from any other `url()`.
+  pub fn scan_css(&mut self, css: &str) {
+    // Lowercasing ASCII keeps the offsets the same.
+    let lowercase = css.to_ascii_lowercase();
+
+    let mut font_faces = Vec::new();
+    for (start, _) in lowercase.match_indices("@font-face") {
+      let end = lowercase[start..].find('}').map_or(css.len(), |end| start + end);
+      font_faces.push(start..end);
+    }
+
+    let mut imports = Vec::new();
+    for (start, _) in lowercase.match_indices("@import") {
+      let rest = css[start + "@import".len()..].trim_start();
+      let quote = rest.chars().next().filter(|char| matches!(char, '"' | '\''));
+      if let Some(url) = quote.and_then(|quote| rest[1..].split(quote).next()) {
+        self.add(DirectiveKind::StyleSrc, url);
+      } else if rest.get(..4).is_some_and(|url| url.eq_ignore_ascii_case("url(")) {
+        imports.push(css.len() - rest.len());
+      }
+    }
+
+    for (start, _) in lowercase.match_indices("url(") {
+      let value = &css[start + "url(".len()..];
+      let value = value[..value.find(')').unwrap_or(value.len())].trim();
+      let url = value.trim_matches(|char| char == '"' || char == '\'');
+
+      let kind = if imports.contains(&start) {
+        DirectiveKind::StyleSrc
+      } else if font_faces.iter().any(|range| range.contains(&start)) {
+        DirectiveKind::FontSrc
+      } else {
+        DirectiveKind::ImgSrc
+      };
+      self.add(kind, url);
+    }
+  }
+
+  /// Adds the URLs that a script passes as string literals to calls such as
+  /// `fetch`, `new WebSocket`, `new Worker` and `import`.
+  pub fn scan_js(&mut self, js: &str) {
+    for (call, kind) in SCRIPT_CALLS {
+      for (start, _) in js.match_indices(call) {
+        let rest = js[start + call.len()..].trim_start();
+        let Some(quote) =
+          rest.chars().next().filter(|char| matches!(char, '"' | '\'' | '`'))
+        else {
+          continue;
+        };
+
+        let literal = &rest[1..];
+        if let Some(url) = literal.find(quote).map(|end| &literal[..end]) {
+          if !url.contains("${") {
+            self.add(kind, url);
+          }
+        }
+      }
+    }
+  }
+
+  /// Adds the source of a URL to a directive: its origin, `'self'` for
+  /// relative URLs, or its scheme for `data:` and `blob:` URLs. Other URLs,
+  /// such as `javascript:` ones, are skipped.
+  pub fn add(&mut self, kind: DirectiveKind, url: &str) {
+    if let Some(source) = source_of(url) {
+      self.sources.entry(kind).or_default().insert(source);
+    }
+  }
+
+  /// Adds everything from another page, such as when generating one policy
+  /// for a whole site.
+  pub fn extend(&mut self, other: &Self) {
+    for (kind, sources) in &other.sources {
+      self.sources.entry(*kind).or_default().extend(sources.iter().cloned());
+    }
+    self.hashes.extend(&other.hashes);
+  }
+
+  #[must_use]
+  /// A policy that only allows the sources and hashes that were found.
+  ///
+  /// Everything else is blocked by `default-src 'none'`, along with
+  /// `base-uri` and `form-action` if there were no `<base>` elements or
+  /// forms. `frame-ancestors` has to be added separately, as it can't be
+  /// told from the files.
+  pub fn policy(&self) -> CSP<'_> {
+    let mut csp = CSP::new_with(Directive::DefaultSrc(Sources::new()));
+    for (kind, sources) in &self.sources {
+      if let Ok(directive) = parse_directive(*kind, sources.iter().map(String::as_str)) {
+        csp.push_borrowed(directive);
+      }
+    }
+
+    for kind in [DirectiveKind::BaseUri, DirectiveKind::FormAction] {
+      if csp.get(kind).is_none() {
+        if let Some(directive) = kind.with_sources(Sources::new()) {
+          csp.push_borrowed(directive);
+        }
+      }
+    }
+
+    self.hashes.merge_into(&mut csp);
+    csp
+  }
+}
+
+/// The URLs that an element loads, and the directives that govern them.
+fn urls(tag: &Tag<'_>) -> Vec<(DirectiveKind, String)> {
+  let attribute = |name| tag.attribute(name).map(decode);
+  let mut urls = Vec::new();
+  let mut push = |kind, url: Option<String>| {
+    if let Some(url) = url {
+      urls.push((kind, url));
+    }
+  };
+
+  let srcset =
+    || attribute("srcset").map(|srcset| candidates(&srcset)).unwrap_or_default();
+
+  match tag.name.to_ascii_lowercase().as_str() {
+    "script" if tag.is_script() => push(DirectiveKind::ScriptSrc, attribute("src")),
+    "img" => {
+      push(DirectiveKind::ImgSrc, attribute("src"));
+      for url in srcset() {
+        push(DirectiveKind::ImgSrc, Some(url));
+      }
+    }
+    "source" => {
+      push(DirectiveKind::MediaSrc, attribute("src"));
+      for url in srcset() {
+        push(DirectiveKind::ImgSrc, Some(url));
+      }
+    }
+    "audio" | "track" => push(DirectiveKind::MediaSrc, attribute("src")),
+    "video" => {
+      push(DirectiveKind::MediaSrc, attribute("src"));
+      push(DirectiveKind::ImgSrc, attribute("poster"));
+    }
+    "input" => push(DirectiveKind::ImgSrc, attribute("src")),
+    "iframe" | "frame" => push(DirectiveKind::FrameSrc, attribute("src")),
+    "object" => push(DirectiveKind::ObjectSrc, attribute("data")),
+    "embed" => push(DirectiveKind::ObjectSrc, attribute("src")),
+    "base" => push(DirectiveKind::BaseUri, attribute("href")),
+    // Forms without an action are submitted to the page itself.
+    "form" => {
+      let action = attribute("action").filter(|action| !action.is_empty());
+      push(DirectiveKind::FormAction, Some(action.unwrap_or_else(|| "/".to_owned())));
+    }
+    "link" => {
+      let rel = attribute("rel").unwrap_or_default().to_ascii_lowercase();
+      let has = |value| rel.split_ascii_whitespace().any(|rel| rel == value);
+      let kind = if has("stylesheet") {
+        Some(DirectiveKind::StyleSrc)
+      } else if has("icon") || has("apple-touch-icon") {
+        Some(DirectiveKind::ImgSrc)
+      } else if has("manifest") {
+        Some(DirectiveKind::ManifestSrc)
+      } else if has("modulepreload") {
+        Some(DirectiveKind::ScriptSrc)
+      } else if has("preload") {
+        match attribute("as").unwrap_or_default().to_ascii_lowercase().as_str() {
+          "script" => Some(DirectiveKind::ScriptSrc),
+          "style" => Some(DirectiveKind::StyleSrc),
+          "font" => Some(DirectiveKind::FontSrc),
+          "image" => Some(DirectiveKind::ImgSrc),
+          "fetch" => Some(DirectiveKind::ConnectSrc),
+          _ => None,
+        }
+      } else {
+        None
+      };
+      if let Some(kind) = kind {
+        push(kind, attribute("href"));
+      }
+    }
+    _ => {}
+  }
+
+  urls
+}
+
+/// The URLs of the candidates of a `srcset` attribute, which can contain
+/// commas themselves, such as `data:` URLs.
+fn candidates(srcset: &str) -> Vec<String> {
+  let mut urls = Vec::new();
+  let mut rest = srcset;
+  loop {
+    rest =
+      rest.trim_start_matches(|char: char| char.is_ascii_whitespace() || char == ',');
+    if rest.is_empty() {
+      return urls;
+    }
+
+    let end = rest.find(|char: char| char.is_ascii_whitespace()).unwrap_or(rest.len());
+    let url = &rest[..end];
+    if let Some(url) = url.strip_suffix(',') {
+      urls.push(url.trim_end_matches(',').to_owned());
+      rest = &rest[end..];
+    } else {
+      urls.push(url.to_owned());
+      rest = rest[end..].split_once(',').map_or("", |(_, rest)| rest);
+    }
+  }
+}
+
+/// The source expression that allows a URL, if any.
+pub fn source_of(url: &str) -> Option<String> {
+  let url = url.trim();
+  if url.is_empty() || url.starts_with('#') {
+    return None;
+  }
+  if let Some(rest) = url.strip_prefix("//") {
+    return origin("https", rest);
+  }
+
+  let scheme = url.split_once(':').map(|(scheme, _)| scheme).filter(|scheme| {
+    scheme.starts_with(|char: char| char.is_ascii_alphabetic())
+      && scheme
+        .chars()
+        .all(|char| char.is_ascii_alphanumeric() || matches!(char, '+' | '-' | '.'))
+  });
+  let Some(scheme) = scheme else {
+    return Some(Source::Self_.to_string());
+  };
+
+  let scheme = scheme.to_ascii_lowercase();
+  match scheme.as_str() {
+    "http" | "https" | "ws" | "wss" => {
+      origin(&scheme, url[scheme.len() + 1..].strip_prefix("//")?)
+    }
+    "data" | "blob" => Some(format!("{scheme}:")),
+    _ => None,
+  }
+}
+
+fn origin(scheme: &str, rest: &str) -> Option<String> {
+  let authority = rest.split(['/', '?', '#']).next()?;
+  let host = authority.rsplit('@').next()?;
+  (!host.is_empty()).then(|| format!("{scheme}://{}", host.to_ascii_lowercase()))
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// The sources of every page in a directory of built files, such as the
+/// output of a static site generator.
+pub struct SiteScan {
+  /// The sources of each HTML page, by its path relative to the directory.
+  /// They include what the stylesheets and scripts that the page loads from
+  /// the directory load in turn.
+  pub pages: BTreeMap<PathBuf, PageSources>,
+  /// The sources of every stylesheet and script in the directory, including
+  /// those that no page loads directly.
+  pub assets: PageSources,
+}
+
+impl SiteScan {
+  /// Scans every `.html`, `.htm`, `.css`, `.js` and `.mjs` file in the
+  /// directory and its subdirectories. Symbolic links are not followed, and
+  /// files that pages link to are only read if they are in the directory.
+  ///
+  /// # Errors
+  /// If the directory or a file in it can't be read.
+  pub fn scan(root: impl AsRef<Path>) -> io::Result<Self> {
+    let root = &root.as_ref().canonicalize()?;
+    let mut files = Vec::new();
+    find_files(root, &mut files)?;
+
+    let mut scan = Self::default();
+    for path in files {
+      let extension = path.extension().and_then(|extension| extension.to_str());
+      match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("html" | "htm") => {
+          let html = fs::read_to_string(&path)?;
+          let mut page = PageSources::default();
+          page.scan_html(&html);
+          scan_linked(root, &path, &html, &mut page);
+
+          let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+          scan.pages.insert(relative, page);
+        }
+        Some("css") => scan.assets.scan_css(&fs::read_to_string(&path)?),
+        Some("js" | "mjs") => scan.assets.scan_js(&fs::read_to_string(&path)?),
+        _ => {}
+      }
+    }
+
+    Ok(scan)
+  }
+
+  #[must_use]
+  /// The sources of the whole site, for one policy that works on every page.
+  pub fn site(&self) -> PageSources {
+    let mut site = self.assets.clone();
+    for page in self.pages.values() {
+      site.extend(page);
+    }
+    site
+  }
+}
+
+fn find_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+      find_files(&entry.path(), files)?;
+    } else if file_type.is_file() {
+      files.push(entry.path());
+    }
+  }
+  Ok(())
+}
+
+/// Scans the stylesheets and scripts that a page loads from the directory,
+/// which has to be canonical. Ones that can't be read, such as because they
+/// are generated on the fly, are skipped, as are ones outside the directory.
+fn scan_linked(root: &Path, page: &Path, html: &str, sources: &mut PageSources) {
+  let mut linked = Vec::new();
+  walk(html, |tag, _| {
+    for (kind, url) in urls(tag) {
+      if matches!(kind, DirectiveKind::ScriptSrc | DirectiveKind::StyleSrc)
+        && source_of(&url).as_deref() == Some("'self'")
+      {
+        linked.push((kind, url));
+      }
+    }
+  });
+
+  for (kind, url) in linked {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = path.strip_prefix('/').map_or_else(
+      || page.parent().unwrap_or(root).join(path),
+      |absolute| root.join(absolute),
+    );
+
+    let Ok(path) = path.canonicalize() else {
+      continue;
+    };
+    if !path.starts_with(root) {
+      continue;
+    }
+
+    if let Ok(content) = fs::read_to_string(path) {
+      if kind == DirectiveKind::StyleSrc {
+        sources.scan_css(&content);
+      } else {
+        sources.scan_js(&content);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sources() {
+    let mut page = PageSources::default();
+    page.scan_html(
+      "<base href=/docs/><form><input type=image src=//cdn.example.org/go.png></form>\
+       <img src=a.png srcset=\"https://a.example.org/1x.png 1x, data:image/png;base64,AA 2x\">\
+       <video poster=/p.png><source src=https://media.example.org/a.mp4></video>\
+       <a href=https://elsewhere.example.org style=\"background: url('blob:x')\">a</a>\
+       <script type=application/json>fetch('https://json.example.org')</script>\
+       <script type=module>\
+         import a from 'https://esm.example.org/a.js';\
+         new WebSocket(\"wss://live.example.org:8443/socket\");\
+         fetch(`https://api.example.org/${id}`);\
+       </script>\
+       <link rel=preload as=font href=https://user@Fonts.example.org/a.woff2>\
+       <iframe src=\"javascript:void 0\"></iframe>",
+    );
+    page.scan_css("@import 'https://css.example.org/a.css'; @IMPORT url(b.css);");
+
+    let sources: Vec<_> = page
+      .sources
+      .iter()
+      .map(|(kind, sources)| {
+        format!("{kind} {}", Vec::from_iter(sources.clone()).join(" "))
+      })
+      .collect();
+    assert_eq!(
+      sources,
+      [
+        "base-uri 'self'",
+        "connect-src wss://live.example.org:8443",
+        "font-src https://fonts.example.org",
+        "form-action 'self'",
+        "img-src 'self' blob: data: https://a.example.org https://cdn.example.org",
+        "media-src https://media.example.org",
+        "script-src https://esm.example.org",
+        "style-src 'self' https://css.example.org",
+      ]
+    );
+  }
+
+  #[test]
+  fn directory() {
+    let root = std::env::temp_dir().join(format!("csp-site-{}", std::process::id()));
+    fs::create_dir_all(root.join("blog")).unwrap();
+    fs::write(root.join("index.html"), "<link rel=stylesheet href=/main.css>").unwrap();
+    fs::write(
+      root.join("blog/post.html"),
+      "<script src=app.js></script><style>p { color: red }</style>",
+    )
+    .unwrap();
+    fs::write(
+      root.join("main.css"),
+      "body { background: url(https://img.example.org/a) }",
+    )
+    .unwrap();
+    fs::write(root.join("blog/app.js"), "fetch('https://api.example.org')").unwrap();
+    fs::write(root.join("unused.js"), "new Worker('/worker.js')").unwrap();
+
+    let scan = SiteScan::scan(&root);
+    fs::remove_dir_all(&root).unwrap();
+    let scan = scan.unwrap();
+
+    let policies: Vec<_> = scan
+      .pages
+      .iter()
+      .map(|(path, page)| format!("{}: {}", path.display(), page.policy()))
+      .collect();
+    assert_eq!(
+      policies,
+      [
+        "blog/post.html: default-src 'none'; connect-src https://api.example.org; \
+         script-src 'self'; base-uri 'none'; form-action 'none'; \
+         style-src-elem 'sha256-ngewhhP73WDIbgwseeu52VAAJgKdGUsu1IUQQsAm8m4='",
+        "index.html: default-src 'none'; img-src https://img.example.org; style-src 'self'; \
+         base-uri 'none'; form-action 'none'",
+      ]
+    );
+    assert_eq!(
+      scan.site().policy().to_string(),
+      "default-src 'none'; connect-src https://api.example.org; img-src https://img.example.org; \
+       script-src 'self'; style-src 'self'; worker-src 'self'; base-uri 'none'; \
+       form-action 'none'; style-src-elem 'self' \
+       'sha256-ngewhhP73WDIbgwseeu52VAAJgKdGUsu1IUQQsAm8m4='"
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn outside_directory() {
+    let temp = std::env::temp_dir().join(format!("csp-outside-{}", std::process::id()));
+    let (root, outside) = (temp.join("site"), temp.join("outside"));
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(
+      root.join("index.html"),
+      "<script src=/../outside/a.js></script><script src=../outside/a.js></script>\
+       <script src=/linked.js></script>",
+    )
+    .unwrap();
+    fs::write(outside.join("a.js"), "fetch('https://secret.example.org')").unwrap();
+    fs::write(outside.join("page.html"), "<img src=https://img.example.org>").unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("outside")).unwrap();
+    std::os::unix::fs::symlink(outside.join("a.js"), root.join("linked.js")).unwrap();
+
+    let scan = SiteScan::scan(&root);
+    fs::remove_dir_all(&temp).unwrap();
+    let scan = scan.unwrap();
+
+    assert_eq!(scan.pages.keys().collect::<Vec<_>>(), [Path::new("index.html")]);
+    assert_eq!(
+      scan.site().policy().to_string(),
+      "default-src 'none'; script-src 'self'; base-uri 'none'; form-action 'none'"
+    );
+  }
+}
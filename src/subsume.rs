@@ -0,0 +1,250 @@
+//! The policy subsumption algorithm of CSP Embedded Enforcement.
+
+use crate::{
+  CSP, Directive, Source, Sources, SriFor,
+  host::{HostSource, scheme_part_matches},
+  kind::DirectiveKind,
+};
+
+impl CSP<'_> {
+  /// Checks if this policy is at least as strict as the `required` one, which
+  /// is how CSP Embedded Enforcement decides if a document's returned policy
+  /// satisfies the policy its embedder asked for with the
+  /// [`SEC_REQUIRED_CSP`](crate::SEC_REQUIRED_CSP) header.
+  ///
+  /// Every directive of the required policy is compared against the one
+  /// that effectively applies in this policy, taking `default-src` and the
+  /// other fallbacks into account. Reporting directives are ignored.
+  ///
+  /// Some answers would depend on the origin of the protected document, such
+  /// as if `'self'` is subsumed by a host. In those cases this errs on the
+  /// side of returning `false`.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::CSP;
+  ///
+  /// let required = CSP::parse("script-src https://example.org 'self'").unwrap();
+  ///
+  /// let returned = CSP::parse("default-src 'self'; report-to main").unwrap();
+  /// assert!(returned.subsumes(&required));
+  ///
+  /// let returned = CSP::parse("img-src 'self'").unwrap();
+  /// assert!(!returned.subsumes(&required));
+  /// ```
+  #[must_use]
+  pub fn subsumes(&self, required: &CSP<'_>) -> bool {
+    DirectiveKind::ALL.into_iter().all(|kind| self.subsumes_kind(required, kind))
+  }
+
+  fn subsumes_kind(&self, required: &CSP<'_>, kind: DirectiveKind) -> bool {
+    match kind {
+      // What these allow is fully checked through the directives that fall
+      // back to them, and reporting doesn't change what a document can do.
+      DirectiveKind::DefaultSrc
+      | DirectiveKind::ChildSrc
+      | DirectiveKind::ReportTo
+      | DirectiveKind::ReportUri => return true,
+      _ if kind.has_sources() => {
+        return match (self.effective_sources(kind), required.effective_sources(kind)) {
+          (_, None) => true,
+          (None, Some(_)) => false,
+          (Some(returned), Some(required)) => {
+            returned.is_subsumed_by(required, kind.is_script())
+          }
+        };
+      }
+      _ => {}
+    }
+
+    let Some(required) = required.get(kind) else {
+      return true;
+    };
+
+    match (self.get(kind), required) {
+      (Some(Directive::Sandbox(returned)), Directive::Sandbox(required)) => {
+        returned.0.iter().all(|allow| required.0.contains(allow))
+      }
+      (Some(Directive::PluginTypes(returned)), Directive::PluginTypes(required)) => {
+        returned.0.iter().all(|(kind, subtype)| {
+          required.0.iter().any(|(required_kind, required_subtype)| {
+            kind.eq_ignore_ascii_case(required_kind)
+              && subtype.eq_ignore_ascii_case(required_subtype)
+          })
+        })
+      }
+      (Some(Directive::TrustedTypes(returned)), Directive::TrustedTypes(required)) => {
+        required.contains(&"*") || returned.iter().all(|name| required.contains(name))
+      }
+      (Some(Directive::RequireSriFor(returned)), Directive::RequireSriFor(required)) => {
+        let script = |for_: &SriFor| matches!(for_, SriFor::Script | SriFor::ScriptStyle);
+        let style = |for_: &SriFor| matches!(for_, SriFor::Style | SriFor::ScriptStyle);
+
+        (script(returned) || !script(required)) && (style(returned) || !style(required))
+      }
+      (returned, _) => returned.is_some(),
+    }
+  }
+}
+
+impl Sources<'_> {
+  /// If everything these sources allow is also allowed by the `required`
+  /// sources, when used for a directive that is or isn't about scripts.
+  pub(crate) fn is_subsumed_by(&self, required: &Sources<'_>, is_script: bool) -> bool {
+    if self.allows_inline(is_script) && !required.allows_inline(is_script) {
+      return false;
+    }
+
+    let keywords_ok = self.0.iter().all(|source| match source {
+      Source::UnsafeEval | Source::UnsafeHashes => required.0.contains(source),
+      Source::WasmUnsafeEval => {
+        required.0.contains(&Source::WasmUnsafeEval)
+          || required.0.contains(&Source::UnsafeEval)
+      }
+      // Nonces and hashes only allow a subset of what `'unsafe-inline'` allows.
+      Source::Nonce(nonce) => {
+        required.allows_inline(is_script) || required.0.contains(&Source::Nonce(nonce))
+      }
+      Source::Hash((algorithm, hash)) => {
+        required.allows_inline(is_script)
+          || required.0.iter().any(|source| {
+            matches!(source, Source::Hash((required_algorithm, required_hash))
+              if algorithm.eq_ignore_ascii_case(required_algorithm) && hash == required_hash)
+          })
+      }
+      _ => true,
+    });
+    if !keywords_ok {
+      return false;
+    }
+
+    // With `'strict-dynamic'` the trust of nonced and hashed scripts propagates
+    // to whatever they load, and all the other expressions are ignored.
+    match (self.is_strict_dynamic(is_script), required.is_strict_dynamic(is_script)) {
+      (true, required_strict_dynamic) => required_strict_dynamic,
+      (false, true) => self.expressions().next().is_none(),
+      (false, false) => self.expressions().all(|expression| {
+        required.expressions().any(|required| expression.is_subsumed_by(required))
+      }),
+    }
+  }
+
+  /// If `'unsafe-inline'` is present and not neutralized by nonces, hashes or
+  /// `'strict-dynamic'`.
+  pub(crate) fn allows_inline(&self, is_script: bool) -> bool {
+    self.0.contains(&Source::UnsafeInline)
+      && !self.is_strict_dynamic(is_script)
+      && !self.0.iter().any(|source| matches!(source, Source::Nonce(_) | Source::Hash(_)))
+  }
+
+  pub(crate) fn is_strict_dynamic(&self, is_script: bool) -> bool {
+    is_script && self.0.contains(&Source::StrictDynamic)
+  }
+
+  /// The sources that are matched against URLs.
+  pub(crate) fn expressions(&self) -> impl Iterator<Item = &Source<'_>> {
+    self.0.iter().filter(|source| {
+      matches!(source, Source::Host(_) | Source::Scheme(_) | Source::Self_)
+    })
+  }
+}
+
+impl Source<'_> {
+  /// If every URL that this source expression matches is also matched by the
+  /// other one. Only meaningful for hosts, schemes and `'self'`.
+  pub(crate) fn is_subsumed_by(&self, other: &Source<'_>) -> bool {
+    match (self, other) {
+      (Self::Self_, Source::Self_) => true,
+      (Self::Host(host), Source::Host(other)) => {
+        match (HostSource::parse(host), HostSource::parse(other)) {
+          (Some(host), Some(other)) => host.is_subsumed_by(&other),
+          _ => false,
+        }
+      }
+      (Self::Host(host), Source::Scheme(scheme)) => match HostSource::parse(host) {
+        Some(HostSource { scheme: Some(host_scheme), .. }) => {
+          scheme_part_matches(scheme, host_scheme)
+        }
+        // Without a scheme, a host matches both `http` and `https` URLs.
+        Some(HostSource { scheme: None, .. }) => {
+          scheme_part_matches(scheme, "http") && scheme_part_matches(scheme, "https")
+        }
+        None => false,
+      },
+      (Self::Scheme(scheme), Source::Scheme(other)) => scheme_part_matches(other, scheme),
+      // A lone `*` matches all URLs with network schemes.
+      (Self::Scheme(scheme), Source::Host("*")) => ["http", "https", "ws", "wss"]
+        .into_iter()
+        .any(|network| network.eq_ignore_ascii_case(scheme)),
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn subsumes(returned: &str, required: &str) -> bool {
+    CSP::parse(returned).unwrap().subsumes(&CSP::parse(required).unwrap())
+  }
+
+  #[test]
+  fn fallbacks() {
+    assert!(subsumes("default-src 'none'", "script-src 'self'; img-src *"));
+    assert!(subsumes(
+      "default-src 'none'; script-src 'self'",
+      "default-src 'self'; img-src *"
+    ));
+    assert!(!subsumes("script-src 'self'", "default-src 'self'"));
+    assert!(subsumes("script-src-elem 'self'; script-src 'none'", "script-src 'self'"));
+    assert!(!subsumes("default-src 'self'; worker-src *", "script-src 'self'"));
+  }
+
+  #[test]
+  fn source_lists() {
+    assert!(subsumes("img-src https://a.example.org", "img-src https://*.example.org"));
+    assert!(subsumes("img-src https://a.example.org", "img-src https:"));
+    assert!(subsumes("img-src https:", "img-src *"));
+    assert!(!subsumes("img-src data:", "img-src *"));
+    assert!(!subsumes("img-src 'self'", "img-src https://example.org"));
+    assert!(!subsumes("img-src 'self'", "img-src 'none'"));
+    assert!(subsumes("img-src 'none'", "img-src 'self'"));
+  }
+
+  #[test]
+  fn keywords() {
+    assert!(!subsumes("script-src 'unsafe-inline'", "script-src 'self'"));
+    assert!(subsumes("script-src 'sha256-abc'", "script-src 'unsafe-inline'"));
+    assert!(subsumes(
+      "script-src 'unsafe-inline' 'nonce-abc'",
+      "script-src 'nonce-abc' 'unsafe-inline'"
+    ));
+    assert!(!subsumes("script-src 'nonce-abc'", "script-src 'nonce-def'"));
+    assert!(subsumes("script-src 'nonce-abc'", "script-src 'unsafe-inline'"));
+    assert!(!subsumes("script-src 'nonce-abc'", "script-src 'unsafe-inline' 'sha256-a'"));
+    assert!(subsumes("script-src 'wasm-unsafe-eval'", "script-src 'unsafe-eval'"));
+    assert!(!subsumes("script-src 'unsafe-eval'", "script-src 'wasm-unsafe-eval'"));
+    assert!(!subsumes("script-src 'nonce-a' 'strict-dynamic'", "script-src 'nonce-a' *"));
+    assert!(subsumes(
+      "script-src 'nonce-a' 'strict-dynamic' https:",
+      "script-src 'nonce-a' 'strict-dynamic'"
+    ));
+    assert!(!subsumes(
+      "script-src 'nonce-a' https:",
+      "script-src 'nonce-a' 'strict-dynamic'"
+    ));
+  }
+
+  #[test]
+  fn other_directives() {
+    assert!(subsumes("sandbox", "sandbox allow-scripts"));
+    assert!(!subsumes("sandbox allow-scripts", "sandbox"));
+    assert!(!subsumes("img-src 'none'", "sandbox"));
+    assert!(!subsumes("img-src 'none'", "upgrade-insecure-requests"));
+    assert!(subsumes("require-sri-for script style", "require-sri-for script"));
+    assert!(!subsumes("require-sri-for style", "require-sri-for script"));
+    assert!(subsumes("trusted-types one", "trusted-types one two"));
+    assert!(subsumes("frame-ancestors 'none'", "frame-ancestors 'self'; report-to main"));
+  }
+}
@@ -0,0 +1,226 @@
+//! The status of directives in the specification, and which browser engines
+//! support them and keyword sources.
+//!
+//! Engine support is loaded from a table that is embedded into the crate,
+//! `src/support.txt`, which documents its own format.
+
+use std::{fmt, sync::OnceLock};
+
+use crate::{CSP, DirectiveKind, Ignored, Source};
+
+const DATA: &str = include_str!("support.txt");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The status of a directive in the specifications.
+pub enum Status {
+  /// Part of the current specification.
+  Standard,
+  /// Still supported, but replaced by something else.
+  Deprecated,
+  /// Removed from the specification, or never shipped by browsers, so it has
+  /// no effect.
+  Obsolete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A browser engine.
+pub enum Engine {
+  /// The engine of Chrome, Edge and other Chromium-based browsers.
+  Blink,
+  /// The engine of Firefox.
+  Gecko,
+  /// The engine of Safari, and of every browser on iOS.
+  WebKit,
+}
+
+impl Engine {
+  /// Every engine, in the order of the columns of the support table.
+  pub const ALL: [Self; 3] = [Self::Blink, Self::Gecko, Self::WebKit];
+}
+
+impl fmt::Display for Engine {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Blink => write!(fmt, "Blink"),
+      Self::Gecko => write!(fmt, "Gecko"),
+      Self::WebKit => write!(fmt, "WebKit"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// How well an engine supports a directive or source.
+pub enum Support {
+  /// Supported as specified.
+  Yes,
+  /// Supported, but with parts that are missing or behave differently.
+  Partial,
+  /// Ignored.
+  No,
+}
+
+impl DirectiveKind {
+  #[must_use]
+  /// The status of the directive in the specifications.
+  pub const fn status(self) -> Status {
+    match self {
+      Self::BlockAllMixedContent | Self::ReportUri => Status::Deprecated,
+      Self::NavigateTo | Self::PluginTypes | Self::PrefetchSrc | Self::RequireSriFor => {
+        Status::Obsolete
+      }
+      _ => Status::Standard,
+    }
+  }
+
+  #[must_use]
+  /// How well the engine supports the directive.
+  pub fn support(self, engine: Engine) -> Support {
+    lookup(self.name(), engine)
+  }
+}
+
+impl Source<'_> {
+  #[must_use]
+  /// How well the engine supports the source expression.
+  pub fn support(&self, engine: Engine) -> Support {
+    match self {
+      Self::Host(_) | Self::Scheme(_) => Support::Yes,
+      Self::Nonce(_) => lookup("'nonce-<value>'", engine),
+      Self::Hash(_) => lookup("'<algorithm>-<hash>'", engine),
+      keyword => lookup(&keyword.to_string(), engine),
+    }
+  }
+}
+
+impl<'a> CSP<'a> {
+  #[must_use]
+  /// The parts of the policy that the engine ignores, because it doesn't
+  /// support them or because keywords it supports make it ignore them, such
+  /// as `'unsafe-inline'` next to a nonce. Parts that are only partially
+  /// supported are not included.
+  ///
+  /// # Example usage
+  /// ```rust
+  /// use csp::{CSP, Engine};
+  ///
+  /// let csp = CSP::parse("default-src 'self'; prefetch-src 'self'; report-to main").unwrap();
+  /// let ignored: Vec<_> = csp.ignored_by(Engine::Gecko).iter().map(ToString::to_string).collect();
+  ///
+  /// assert_eq!(ignored, ["prefetch-src is not supported", "report-to is not supported"]);
+  /// ```
+  pub fn ignored_by(&self, engine: Engine) -> Vec<Ignored<'a>> {
+    self
+      .interpret_with(
+        |kind| kind.support(engine) != Support::No,
+        |source| source.support(engine) != Support::No,
+      )
+      .1
+  }
+}
+
+fn lookup(name: &str, engine: Engine) -> Support {
+  table()
+    .iter()
+    .find(|(existing, _)| *existing == name)
+    .map_or(Support::Yes, |(_, support)| support[engine as usize])
+}
+
+fn table() -> &'static [(&'static str, [Support; 3])] {
+  static TABLE: OnceLock<Vec<(&'static str, [Support; 3])>> = OnceLock::new();
+
+  // The embedded table is checked to parse by the tests.
+  TABLE.get_or_init(|| parse(DATA).unwrap_or_default())
+}
+
+fn parse(data: &str) -> Option<Vec<(&str, [Support; 3])>> {
+  let mut rows = Vec::new();
+
+  for line in data.lines().map(str::trim) {
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let mut columns = line.split_whitespace();
+    let name = columns.next()?;
+    let mut support = [Support::No; 3];
+    for support in &mut support {
+      *support = match columns.next()? {
+        "yes" => Support::Yes,
+        "partial" => Support::Partial,
+        "no" => Support::No,
+        _ => return None,
+      };
+    }
+    if columns.next().is_some() {
+      return None;
+    }
+
+    rows.push((name, support));
+  }
+
+  Some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn embedded() {
+    let rows = parse(DATA).unwrap();
+    assert_eq!(rows.len(), table().len());
+
+    let keywords = [
+      Source::Self_,
+      Source::UnsafeEval,
+      Source::WasmUnsafeEval,
+      Source::UnsafeHashes,
+      Source::UnsafeInline,
+      Source::StrictDynamic,
+      Source::ReportSample,
+    ];
+    let names: Vec<String> = DirectiveKind::ALL
+      .iter()
+      .map(ToString::to_string)
+      .chain(keywords.iter().map(ToString::to_string))
+      .chain(["'nonce-<value>'".to_owned(), "'<algorithm>-<hash>'".to_owned()])
+      .collect();
+
+    assert_eq!(rows.len(), names.len());
+    for name in &names {
+      assert!(rows.iter().any(|(existing, _)| existing == name), "{name}");
+    }
+
+    // Obsolete directives are exactly the ones that no engine supports.
+    for kind in DirectiveKind::ALL {
+      let unsupported =
+        Engine::ALL.iter().all(|engine| kind.support(*engine) == Support::No);
+      assert_eq!(unsupported, kind.status() == Status::Obsolete, "{kind}");
+    }
+  }
+
+  #[test]
+  fn ignored() {
+    let csp = CSP::parse(
+      "script-src 'nonce-abc' 'unsafe-inline'; trusted-types default; \
+       plugin-types application/pdf; require-sri-for script",
+    )
+    .unwrap();
+
+    let describe = |engine| -> Vec<String> {
+      csp.ignored_by(engine).iter().map(ToString::to_string).collect()
+    };
+    assert_eq!(
+      describe(Engine::Blink),
+      [
+        "script-src: 'unsafe-inline' is ignored because of nonces or hashes",
+        "plugin-types is not supported",
+        "require-sri-for is not supported",
+      ]
+    );
+    assert_eq!(describe(Engine::WebKit), describe(Engine::Blink));
+    assert_eq!(CSP::strict_nonce_based("abc").ignored_by(Engine::Gecko).len(), 2);
+    assert_eq!(parse("img-src yes maybe no"), None);
+    assert_eq!(parse("img-src yes yes"), None);
+  }
+}
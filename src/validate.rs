@@ -0,0 +1,940 @@
+//! Checking and building policies at compile time, for the
+//! [`csp!`](crate::csp) macro.
+//!
+//! This mirrors [`CSP::parse`](crate::CSP::parse) with `const fn`s that work
+//! on byte ranges, as most string methods are not available in constants. It
+//! is stricter than the parser where leniency would only hide typos.
+//!
+//! As constants can't allocate, a policy is built into one array for each
+//! type of value, which the directives then borrow slices of. The `count`s
+//! give the lengths of the arrays.
+
+use std::mem;
+
+use crate::{
+  Directive, Plugins, ReportUris, SandboxAllow, SandboxAllowedList, Source, SriFor,
+  kind::DirectiveKind,
+};
+
+const KEYWORDS: [&str; 8] = [
+  "none",
+  "self",
+  "unsafe-eval",
+  "wasm-unsafe-eval",
+  "unsafe-hashes",
+  "unsafe-inline",
+  "strict-dynamic",
+  "report-sample",
+];
+
+const SANDBOX_ALLOWS: [&str; 13] = [
+  "allow-downloads-without-user-activation",
+  "allow-forms",
+  "allow-modals",
+  "allow-orientation-lock",
+  "allow-pointer-lock",
+  "allow-popups",
+  "allow-popups-to-escape-sandbox",
+  "allow-presentation",
+  "allow-same-origin",
+  "allow-scripts",
+  "allow-storage-access-by-user-activation",
+  "allow-top-navigation",
+  "allow-top-navigation-by-user-activation",
+];
+
+/// Checks a policy for [`csp!`](crate::csp).
+///
+/// # Panics
+/// With a description of the first problem in the policy, which is a compile
+/// error when evaluated in a constant.
+pub const fn validate(policy: &str) {
+  if let Err(problem) = check(policy) {
+    let mut buffer = [0; 256];
+    panic!("{}", problem.describe(policy.as_bytes(), &mut buffer));
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The first problem in a policy, with the byte ranges of the directive name
+/// and of the value that it is about.
+pub struct Problem {
+  message: &'static str,
+  name: (usize, usize),
+  value: Option<(usize, usize)>,
+}
+
+impl Problem {
+  /// Writes a description such as ``invalid host source: `a_b.org` in
+  /// `img-src` `` into the buffer, as strings can't be formatted in
+  /// constants. Only the message is used if it doesn't fit.
+  const fn describe<'b>(&self, policy: &[u8], buffer: &'b mut [u8]) -> &'b str {
+    let name = slice(policy, self.name);
+    let parts: [&[u8]; 6] = match self.value {
+      Some(value) => {
+        [self.message.as_bytes(), b": `", slice(policy, value), b"` in `", name, b"`"]
+      }
+      None => [self.message.as_bytes(), b": `", name, b"`", b"", b""],
+    };
+
+    let mut len = 0;
+    let mut part = 0;
+    while part < parts.len() {
+      let bytes = parts[part];
+      if len + bytes.len() > buffer.len() {
+        return self.message;
+      }
+      let mut index = 0;
+      while index < bytes.len() {
+        buffer[len] = bytes[index];
+        len += 1;
+        index += 1;
+      }
+      part += 1;
+    }
+
+    match core::str::from_utf8(buffer.split_at(len).0) {
+      Ok(description) => description,
+      Err(_) => self.message,
+    }
+  }
+}
+
+/// Checks a whole policy, returning the first problem.
+pub const fn check(policy: &str) -> Result<(), Problem> {
+  let bytes = policy.as_bytes();
+  let mut seen = [false; DirectiveKind::ALL.len()];
+
+  let mut start = 0;
+  while start <= bytes.len() {
+    let mut end = start;
+    while end < bytes.len() && bytes[end] != b';' {
+      end += 1;
+    }
+
+    if let Err(problem) = check_directive(bytes, start, end, &mut seen) {
+      return Err(problem);
+    }
+    start = end + 1;
+  }
+
+  Ok(())
+}
+
+const fn check_directive(
+  bytes: &[u8],
+  start: usize,
+  end: usize,
+  seen: &mut [bool; DirectiveKind::ALL.len()],
+) -> Result<(), Problem> {
+  let (name_start, name_end) = next_token(bytes, start, end);
+  if name_start == name_end {
+    return Ok(());
+  }
+  let name = (name_start, name_end);
+
+  let index = kind_index(bytes, name_start, name_end);
+  if index == DirectiveKind::ALL.len() {
+    return Err(Problem { message: "unknown directive", name, value: None });
+  }
+  if seen[index] {
+    return Err(Problem {
+      message: "duplicate directive, browsers would ignore all but the first",
+      name,
+      value: None,
+    });
+  }
+  seen[index] = true;
+  let kind = DirectiveKind::ALL[index];
+
+  let mut count = 0;
+  let mut position = name_end;
+  loop {
+    let (token_start, token_end) = next_token(bytes, position, end);
+    if token_start == token_end {
+      break;
+    }
+    position = token_end;
+    count += 1;
+    let value = Some((token_start, token_end));
+
+    let valid = match kind {
+      DirectiveKind::BlockAllMixedContent | DirectiveKind::UpgradeInsecureRequests => {
+        return Err(Problem { message: "directive takes no values", name, value });
+      }
+      DirectiveKind::PluginTypes => find(bytes, token_start, token_end, b'/') < token_end,
+      DirectiveKind::ReportTo => count == 1,
+      DirectiveKind::ReportUri | DirectiveKind::TrustedTypes => true,
+      DirectiveKind::RequireSriFor => {
+        eq_ignore_case(bytes, token_start, token_end, "script")
+          || eq_ignore_case(bytes, token_start, token_end, "style")
+      }
+      DirectiveKind::Sandbox => {
+        any_eq_ignore_case(bytes, token_start, token_end, &SANDBOX_ALLOWS)
+      }
+      _ => match check_source(bytes, token_start, token_end) {
+        Ok(()) => true,
+        Err(message) => return Err(Problem { message, name, value }),
+      },
+    };
+    if !valid {
+      return Err(Problem { message: "invalid value", name, value });
+    }
+  }
+
+  let requires_value = matches!(
+    kind,
+    DirectiveKind::ReportTo
+      | DirectiveKind::ReportUri
+      | DirectiveKind::RequireSriFor
+      | DirectiveKind::TrustedTypes
+  );
+  if requires_value && count == 0 {
+    return Err(Problem { message: "directive requires a value", name, value: None });
+  }
+
+  Ok(())
+}
+
+/// The index of the directive name in [`DirectiveKind::ALL`], or its length if
+/// the name is unknown.
+const fn kind_index(bytes: &[u8], start: usize, end: usize) -> usize {
+  let mut index = 0;
+  while index < DirectiveKind::ALL.len()
+    && !eq_ignore_case(bytes, start, end, DirectiveKind::ALL[index].name())
+  {
+    index += 1;
+  }
+  index
+}
+
+const fn check_source(
+  bytes: &[u8],
+  start: usize,
+  end: usize,
+) -> Result<(), &'static str> {
+  if bytes[start] == b'\'' {
+    if end - start < 2 || bytes[end - 1] != b'\'' {
+      return Err("quoted keyword is missing its closing quote");
+    }
+    return check_keyword(bytes, start + 1, end - 1);
+  }
+
+  if any_eq_ignore_case(bytes, start, end, &KEYWORDS) {
+    return Err("keyword has to be in single quotes");
+  }
+
+  if bytes[end - 1] == b':' && is_scheme(bytes, start, end - 1) {
+    return Ok(());
+  }
+
+  if is_host_source(bytes, start, end) { Ok(()) } else { Err("invalid host source") }
+}
+
+const fn check_keyword(
+  bytes: &[u8],
+  start: usize,
+  end: usize,
+) -> Result<(), &'static str> {
+  if any_eq_ignore_case(bytes, start, end, &KEYWORDS) {
+    return Ok(());
+  }
+
+  let dash = find(bytes, start, end, b'-');
+  let prefix_is_known = eq_ignore_case(bytes, start, dash, "nonce")
+    || eq_ignore_case(bytes, start, dash, "sha256")
+    || eq_ignore_case(bytes, start, dash, "sha384")
+    || eq_ignore_case(bytes, start, dash, "sha512");
+
+  if dash + 1 >= end || !prefix_is_known {
+    return Err("unknown quoted keyword");
+  }
+
+  let mut index = dash + 1;
+  while index < end {
+    let byte = bytes[index];
+    if !byte.is_ascii_alphanumeric() && !matches!(byte, b'+' | b'/' | b'-' | b'_' | b'=')
+    {
+      return Err("nonce or hash is not base64");
+    }
+    index += 1;
+  }
+
+  Ok(())
+}
+
+/// The `host-source` grammar, as checked by `HostSource::parse`.
+const fn is_host_source(bytes: &[u8], start: usize, end: usize) -> bool {
+  let mut host_start = start;
+  let mut index = start;
+  while index + 3 <= end {
+    if bytes[index] == b':' && bytes[index + 1] == b'/' && bytes[index + 2] == b'/' {
+      if !is_scheme(bytes, start, index) {
+        return false;
+      }
+      host_start = index + 3;
+      break;
+    }
+    index += 1;
+  }
+
+  let authority_end = find(bytes, host_start, end, b'/');
+
+  let mut host_end = authority_end;
+  let mut index = authority_end;
+  while index > host_start {
+    index -= 1;
+    if bytes[index] == b':' {
+      host_end = index;
+      break;
+    }
+  }
+
+  if host_end < authority_end {
+    let is_wildcard = authority_end - host_end == 2 && bytes[host_end + 1] == b'*';
+    if !is_wildcard && !all_digits(bytes, host_end + 1, authority_end) {
+      return false;
+    }
+  }
+
+  is_host(bytes, host_start, host_end)
+}
+
+const fn is_host(bytes: &[u8], mut start: usize, end: usize) -> bool {
+  if end - start >= 2 && bytes[start] == b'*' && bytes[start + 1] == b'.' {
+    start += 2;
+  }
+  if end - start == 1 && bytes[start] == b'*' {
+    return true;
+  }
+
+  let mut label_len = 0;
+  let mut index = start;
+  while index < end {
+    let byte = bytes[index];
+    if byte == b'.' {
+      if label_len == 0 {
+        return false;
+      }
+      label_len = 0;
+    } else if byte.is_ascii_alphanumeric() || byte == b'-' {
+      label_len += 1;
+    } else {
+      return false;
+    }
+    index += 1;
+  }
+
+  label_len > 0
+}
+
+const fn is_scheme(bytes: &[u8], start: usize, end: usize) -> bool {
+  if start >= end || !bytes[start].is_ascii_alphabetic() {
+    return false;
+  }
+
+  let mut index = start + 1;
+  while index < end {
+    let byte = bytes[index];
+    if !byte.is_ascii_alphanumeric() && !matches!(byte, b'+' | b'-' | b'.') {
+      return false;
+    }
+    index += 1;
+  }
+
+  true
+}
+
+const fn all_digits(bytes: &[u8], start: usize, end: usize) -> bool {
+  if start >= end {
+    return false;
+  }
+
+  let mut index = start;
+  while index < end {
+    if !bytes[index].is_ascii_digit() {
+      return false;
+    }
+    index += 1;
+  }
+
+  true
+}
+
+/// The bounds of the next whitespace separated token, which are equal if
+/// there is none left.
+const fn next_token(bytes: &[u8], mut start: usize, end: usize) -> (usize, usize) {
+  while start < end && bytes[start].is_ascii_whitespace() {
+    start += 1;
+  }
+
+  let mut token_end = start;
+  while token_end < end && !bytes[token_end].is_ascii_whitespace() {
+    token_end += 1;
+  }
+
+  (start, token_end)
+}
+
+const fn slice(bytes: &[u8], (start, end): (usize, usize)) -> &[u8] {
+  bytes.split_at(end).0.split_at(start).1
+}
+
+/// The index of the first occurrence of the byte, or `end` if there is none.
+const fn find(bytes: &[u8], start: usize, end: usize, needle: u8) -> usize {
+  let mut index = start;
+  while index < end && bytes[index] != needle {
+    index += 1;
+  }
+  index
+}
+
+const fn eq_ignore_case(bytes: &[u8], start: usize, end: usize, other: &str) -> bool {
+  let other = other.as_bytes();
+  if end - start != other.len() {
+    return false;
+  }
+
+  let mut index = 0;
+  while index < other.len() {
+    if !bytes[start + index].eq_ignore_ascii_case(&other[index]) {
+      return false;
+    }
+    index += 1;
+  }
+
+  true
+}
+
+const fn any_eq_ignore_case(
+  bytes: &[u8],
+  start: usize,
+  end: usize,
+  others: &[&str],
+) -> bool {
+  let mut index = 0;
+  while index < others.len() {
+    if eq_ignore_case(bytes, start, end, others[index]) {
+      return true;
+    }
+    index += 1;
+  }
+
+  false
+}
+
+#[derive(Debug, Clone, Copy)]
+/// An array that [`csp!`](crate::csp) builds a policy into.
+pub enum Part {
+  /// The directives.
+  Directives,
+  /// The sources of the directives that take them.
+  Sources,
+  /// The MIME types of `plugin-types`.
+  Plugins,
+  /// The URIs of `report-uri`.
+  ReportUris,
+  /// The allowances of `sandbox`.
+  SandboxAllows,
+}
+
+impl Part {
+  /// The array that holds the values of the kind of directive.
+  const fn of_values(kind: DirectiveKind) -> Option<Self> {
+    match kind {
+      DirectiveKind::PluginTypes => Some(Self::Plugins),
+      DirectiveKind::ReportUri => Some(Self::ReportUris),
+      DirectiveKind::Sandbox => Some(Self::SandboxAllows),
+      _ if kind.has_sources() => Some(Self::Sources),
+      _ => None,
+    }
+  }
+}
+
+/// The directives of a checked policy, each with the bounds of its values.
+struct Directives<'p> {
+  bytes: &'p [u8],
+  start: usize,
+}
+
+impl<'p> Directives<'p> {
+  /// Walks the directives, or none if the policy has problems, so that only
+  /// [`validate`] reports them.
+  const fn new(policy: &'p str) -> Self {
+    let bytes = policy.as_bytes();
+    Self { bytes, start: if check(policy).is_ok() { 0 } else { bytes.len() + 1 } }
+  }
+
+  const fn next(&mut self) -> Option<(DirectiveKind, usize, usize)> {
+    while self.start <= self.bytes.len() {
+      let end = find(self.bytes, self.start, self.bytes.len(), b';');
+      let (name_start, name_end) = next_token(self.bytes, self.start, end);
+      self.start = end + 1;
+
+      let index = kind_index(self.bytes, name_start, name_end);
+      if index < DirectiveKind::ALL.len() {
+        return Some((DirectiveKind::ALL[index], name_end, end));
+      }
+    }
+    None
+  }
+}
+
+/// The number of values of a directive, leaving out `'none'`, which is the
+/// absence of sources.
+const fn count_values(bytes: &[u8], mut start: usize, end: usize) -> usize {
+  let mut count = 0;
+  loop {
+    let (token_start, token_end) = next_token(bytes, start, end);
+    if token_start == token_end {
+      return count;
+    }
+    if !eq_ignore_case(bytes, token_start, token_end, "'none'") {
+      count += 1;
+    }
+    start = token_end;
+  }
+}
+
+#[must_use]
+/// The length of the array for a part of a checked policy.
+pub const fn count(policy: &str, part: Part) -> usize {
+  let mut directives = Directives::new(policy);
+  let mut count = 0;
+  while let Some((kind, start, end)) = directives.next() {
+    count += match (part, Part::of_values(kind)) {
+      (Part::Directives, _) => 1,
+      (Part::Sources, Some(Part::Sources))
+      | (Part::Plugins, Some(Part::Plugins))
+      | (Part::ReportUris, Some(Part::ReportUris))
+      | (Part::SandboxAllows, Some(Part::SandboxAllows)) => {
+        count_values(policy.as_bytes(), start, end)
+      }
+      _ => 0,
+    };
+  }
+  count
+}
+
+/// Calls the function with the bounds of each value in the part of a checked
+/// policy, in order.
+macro_rules! for_each_value {
+  ($policy:expr, $part:pat, |$start:ident, $end:ident| $body:block) => {
+    let mut directives = Directives::new($policy);
+    while let Some((kind, mut position, end)) = directives.next() {
+      if matches!(Part::of_values(kind), Some($part)) {
+        loop {
+          let ($start, $end) = next_token($policy.as_bytes(), position, end);
+          if $start == $end {
+            break;
+          }
+          position = $end;
+          if !eq_ignore_case($policy.as_bytes(), $start, $end, "'none'") {
+            $body
+          }
+        }
+      }
+    }
+  };
+}
+
+#[must_use]
+/// The sources of a checked policy.
+pub const fn sources<const N: usize>(policy: &'static str) -> [Source<'static>; N] {
+  let mut sources = [const { Source::Self_ }; N];
+  let mut index = 0;
+  for_each_value!(policy, Part::Sources, |start, end| {
+    sources[index] = source(policy, start, end);
+    index += 1;
+  });
+  sources
+}
+
+#[must_use]
+/// The MIME types of the `plugin-types` directive of a checked policy.
+pub const fn plugins<const N: usize>(
+  policy: &'static str,
+) -> [(&'static str, &'static str); N] {
+  let mut plugins = [("", ""); N];
+  let mut index = 0;
+  for_each_value!(policy, Part::Plugins, |start, end| {
+    let slash = find(policy.as_bytes(), start, end, b'/');
+    plugins[index] = (substring(policy, start, slash), substring(policy, slash + 1, end));
+    index += 1;
+  });
+  plugins
+}
+
+#[must_use]
+/// The URIs of the `report-uri` directive of a checked policy.
+pub const fn report_uris<const N: usize>(policy: &'static str) -> [&'static str; N] {
+  let mut uris = [""; N];
+  let mut index = 0;
+  for_each_value!(policy, Part::ReportUris, |start, end| {
+    uris[index] = substring(policy, start, end);
+    index += 1;
+  });
+  uris
+}
+
+#[must_use]
+/// The allowances of the `sandbox` directive of a checked policy.
+pub const fn sandbox_allows<const N: usize>(policy: &'static str) -> [SandboxAllow; N] {
+  let mut allows = [const { SandboxAllow::Forms }; N];
+  let mut index = 0;
+  for_each_value!(policy, Part::SandboxAllows, |start, end| {
+    allows[index] = sandbox_allow(policy.as_bytes(), start, end);
+    index += 1;
+  });
+  allows
+}
+
+#[must_use]
+/// The directives of a checked policy, borrowing their values from the
+/// arrays built by the other functions.
+///
+/// # Panics
+/// If the policy has a `trusted-types` directive, as its names are stored in
+/// a `Vec`, which can't be built in a constant.
+pub const fn directives<const N: usize>(
+  policy: &'static str,
+  sources: &'static [Source<'static>],
+  plugins: &'static [(&'static str, &'static str)],
+  report_uris: &'static [&'static str],
+  sandbox_allows: &'static [SandboxAllow],
+) -> [Directive<'static>; N] {
+  let bytes = policy.as_bytes();
+  let mut directives = [const { Directive::BlockAllMixedContent }; N];
+  // The start of the values of the next directive in each array.
+  let (mut source, mut plugin, mut report_uri, mut sandbox_allow) = (0, 0, 0, 0);
+
+  let mut parsed = Directives::new(policy);
+  let mut index = 0;
+  while let Some((kind, start, end)) = parsed.next() {
+    let count = count_values(bytes, start, end);
+    let directive = match kind {
+      DirectiveKind::BlockAllMixedContent => Directive::BlockAllMixedContent,
+      DirectiveKind::UpgradeInsecureRequests => Directive::UpgradeInsecureRequests,
+      DirectiveKind::PluginTypes => {
+        plugin += count;
+        Directive::PluginTypes(Plugins::from_slice(subslice(
+          plugins,
+          plugin - count,
+          plugin,
+        )))
+      }
+      DirectiveKind::ReportTo => {
+        let (start, end) = next_token(bytes, start, end);
+        Directive::ReportTo(substring(policy, start, end))
+      }
+      DirectiveKind::ReportUri => {
+        report_uri += count;
+        Directive::ReportUri(ReportUris::from_slice(subslice(
+          report_uris,
+          report_uri - count,
+          report_uri,
+        )))
+      }
+      DirectiveKind::RequireSriFor => {
+        Directive::RequireSriFor(sri_for(bytes, start, end))
+      }
+      DirectiveKind::Sandbox => {
+        sandbox_allow += count;
+        Directive::Sandbox(SandboxAllowedList::from_slice(subslice(
+          sandbox_allows,
+          sandbox_allow - count,
+          sandbox_allow,
+        )))
+      }
+      DirectiveKind::TrustedTypes => {
+        panic!("`trusted-types` can't be built at compile time, use `CSP::parse`")
+      }
+      _ => {
+        source += count;
+        kind
+          .with_source_slice(subslice(sources, source - count, source))
+          .expect("the remaining kinds have sources")
+      }
+    };
+
+    // The placeholder has nothing to drop, which constants can't do anyway.
+    mem::forget(mem::replace(&mut directives[index], directive));
+    index += 1;
+  }
+
+  directives
+}
+
+/// A checked source expression, as [`Source::parse`] would parse it.
+const fn source(policy: &'static str, start: usize, end: usize) -> Source<'static> {
+  let bytes = policy.as_bytes();
+
+  if bytes[start] == b'\'' {
+    let (start, end) = (start + 1, end - 1);
+    let mut keywords = [
+      ("self", Source::Self_),
+      ("unsafe-eval", Source::UnsafeEval),
+      ("wasm-unsafe-eval", Source::WasmUnsafeEval),
+      ("unsafe-hashes", Source::UnsafeHashes),
+      ("unsafe-inline", Source::UnsafeInline),
+      ("strict-dynamic", Source::StrictDynamic),
+      ("report-sample", Source::ReportSample),
+    ];
+    let mut index = 0;
+    while index < keywords.len() {
+      if eq_ignore_case(bytes, start, end, keywords[index].0) {
+        return mem::replace(&mut keywords[index].1, Source::Self_);
+      }
+      index += 1;
+    }
+
+    let dash = find(bytes, start, end, b'-');
+    let value = substring(policy, dash + 1, end);
+    if eq_ignore_case(bytes, start, dash, "nonce") {
+      return Source::Nonce(value);
+    }
+    let algorithms = ["sha256", "sha384", "sha512"];
+    let mut index = 0;
+    while !eq_ignore_case(bytes, start, dash, algorithms[index]) {
+      index += 1;
+    }
+    return Source::Hash((algorithms[index], value));
+  }
+
+  if bytes[end - 1] == b':' && is_scheme(bytes, start, end - 1) {
+    return Source::Scheme(substring(policy, start, end - 1));
+  }
+  Source::Host(substring(policy, start, end))
+}
+
+/// A checked `sandbox` allowance, as [`SandboxAllow::parse`] would parse it.
+const fn sandbox_allow(bytes: &[u8], start: usize, end: usize) -> SandboxAllow {
+  // In the order of `SANDBOX_ALLOWS`.
+  let mut allows = [
+    SandboxAllow::DownloadsWithoutUserActivation,
+    SandboxAllow::Forms,
+    SandboxAllow::Modals,
+    SandboxAllow::OrientationLock,
+    SandboxAllow::PointerLock,
+    SandboxAllow::Popups,
+    SandboxAllow::PopupsToEscapeSandbox,
+    SandboxAllow::Presentation,
+    SandboxAllow::SameOrigin,
+    SandboxAllow::Scripts,
+    SandboxAllow::StorageAccessByUserActivation,
+    SandboxAllow::TopNavigation,
+    SandboxAllow::TopNavigationByUserActivation,
+  ];
+  let mut index = 0;
+  while !eq_ignore_case(bytes, start, end, SANDBOX_ALLOWS[index]) {
+    index += 1;
+  }
+  mem::replace(&mut allows[index], SandboxAllow::Forms)
+}
+
+/// The checked values of a `require-sri-for` directive.
+const fn sri_for(bytes: &[u8], mut start: usize, end: usize) -> SriFor {
+  let (mut script, mut style) = (false, false);
+  loop {
+    let (token_start, token_end) = next_token(bytes, start, end);
+    if token_start == token_end {
+      break;
+    }
+    if eq_ignore_case(bytes, token_start, token_end, "script") {
+      script = true;
+    } else {
+      style = true;
+    }
+    start = token_end;
+  }
+
+  match (script, style) {
+    (true, true) => SriFor::ScriptStyle,
+    (true, false) => SriFor::Script,
+    _ => SriFor::Style,
+  }
+}
+
+const fn substring(policy: &'static str, start: usize, end: usize) -> &'static str {
+  policy.split_at(end).0.split_at(start).1
+}
+
+const fn subslice<T>(values: &'static [T], start: usize, end: usize) -> &'static [T] {
+  values.split_at(end).0.split_at(start).1
+}
+
+/// Parses a policy that is checked for mistakes at compile time.
+///
+/// Takes a string literal (or any `&'static str` constant) with the policy in
+/// its header form, as keywords such as `'self'` can't be written as Rust
+/// tokens. Unknown or duplicate directives, invalid values, keywords with a
+/// missing quote and unquoted keywords such as `unsafe-inline`, which would
+/// otherwise be treated as a host name, are all compile errors that name the
+/// directive and the value. The checked policy is then built into a
+/// [`CSP`](crate::CSP) at compile time, in `static`s that it borrows from.
+/// As the names of `trusted-types` are stored in a `Vec`, policies with that
+/// directive have to be parsed at runtime instead.
+///
+/// # Example usage
+/// ```rust
+/// let csp = csp::csp!("default-src 'self'; img-src 'self' https://*.example.org");
+///
+/// assert_eq!(csp.to_string(), "default-src 'self'; img-src 'self' https://*.example.org");
+/// ```
+///
+/// Mistakes don't compile, failing with errors such as "quoted keyword is
+/// missing its closing quote: `'self` in `default-src`":
+/// ```compile_fail
+/// let csp = csp::csp!("default-src 'self; script-src unsafe-inline");
+/// ```
+///
+/// Nor do policies with `trusted-types`:
+/// ```compile_fail
+/// let csp = csp::csp!("trusted-types default");
+/// ```
+#[macro_export]
+macro_rules! csp {
+  ($policy:expr $(,)?) => {{
+    const POLICY: &str = $policy;
+    const _: () = $crate::__validate(POLICY);
+
+    static SOURCES: [$crate::Source<'static>;
+      $crate::__count(POLICY, $crate::__Part::Sources)] = $crate::__sources(POLICY);
+    static PLUGINS: [(&str, &str); $crate::__count(POLICY, $crate::__Part::Plugins)] =
+      $crate::__plugins(POLICY);
+    static REPORT_URIS: [&str; $crate::__count(POLICY, $crate::__Part::ReportUris)] =
+      $crate::__report_uris(POLICY);
+    static SANDBOX_ALLOWS: [$crate::SandboxAllow;
+      $crate::__count(POLICY, $crate::__Part::SandboxAllows)] =
+      $crate::__sandbox_allows(POLICY);
+    static DIRECTIVES: [$crate::Directive<'static>;
+      $crate::__count(POLICY, $crate::__Part::Directives)] =
+      $crate::__directives(POLICY, &SOURCES, &PLUGINS, &REPORT_URIS, &SANDBOX_ALLOWS);
+
+    $crate::CSP::from_slice(&DIRECTIVES)
+  }};
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::CSP;
+
+  #[test]
+  fn agrees_with_parser() {
+    let valid = [
+      "",
+      " ; SCRIPT-SRC 'Self' 'none' ; img-src https: data:",
+      "default-src 'self'; img-src 'self' https://*.example.org:* http://a.example.org/path",
+      "script-src 'sha256-abc+/=' 'nonce-a_b-c' 'strict-dynamic'; object-src 'none'",
+      "sandbox allow-scripts allow-forms; require-sri-for script style; report-to main",
+      "plugin-types application/pdf; trusted-types one two; upgrade-insecure-requests",
+      "img-src example.org:443 *",
+    ];
+    for policy in valid {
+      assert_eq!(check(policy), Ok(()), "{policy}");
+      assert!(CSP::parse(policy).is_ok(), "{policy}");
+    }
+
+    let invalid = [
+      "img-src 'self",
+      "img-src 'unsafe-inlien'",
+      "img-src 'nonce-'",
+      "img-src 'nonce-a!'",
+      "img-src https://exa mple.org:80x",
+      "img-src https://ex_ample.org",
+      "img-src 1ttp://example.org",
+      "sandbox allow-everything",
+      "report-uri",
+      "report-to a b",
+      "upgrade-insecure-requests now",
+    ];
+    for policy in invalid {
+      assert!(check(policy).is_err(), "{policy}");
+      assert!(CSP::parse(policy).is_err(), "{policy}");
+    }
+  }
+
+  #[test]
+  fn agrees_on_every_directive() {
+    let values = [
+      "",
+      "'self' 'none'",
+      "https: data: *",
+      "https://*.example.org:443/path example.org:*",
+      "'nonce-a' 'sha256-abc=' 'strict-dynamic' 'unsafe-hashes' 'report-sample'",
+      "'self",
+      "'unsafe-inlien'",
+      "https://ex_ample.org",
+      "script style",
+      "allow-scripts allow-same-origin",
+      "application/pdf",
+      "main",
+      "one two",
+      "/report https://example.org/report",
+    ];
+
+    for kind in DirectiveKind::ALL {
+      for value in values {
+        let policy = format!("{} {value}", kind.name());
+        assert_eq!(check(&policy).is_ok(), CSP::parse(&policy).is_ok(), "{policy}");
+      }
+    }
+  }
+
+  #[test]
+  fn descriptions() {
+    let describe = |policy: &str| {
+      let mut buffer = [0; 256];
+      check(policy).unwrap_err().describe(policy.as_bytes(), &mut buffer).to_owned()
+    };
+
+    assert_eq!(
+      describe("default-src 'self'; img-src https://ex_ample.org"),
+      "invalid host source: `https://ex_ample.org` in `img-src`"
+    );
+    assert_eq!(describe(" ; Scirpt-Src 'self'"), "unknown directive: `Scirpt-Src`");
+    assert_eq!(describe("report-uri"), "directive requires a value: `report-uri`");
+
+    let mut buffer = [0; 16];
+    let problem = check("img-src 'unsafe-inlien'").unwrap_err();
+    assert_eq!(
+      problem.describe(b"img-src 'unsafe-inlien'", &mut buffer),
+      problem.message
+    );
+  }
+
+  #[test]
+  fn builds_like_parser() {
+    macro_rules! assert_builds {
+      ($($policy:literal),*) => {$(
+        let built: CSP<'static> = crate::csp!($policy);
+        assert_eq!(format!("{built:?}"), format!("{:?}", CSP::parse($policy).unwrap()));
+      )*};
+    }
+
+    assert_builds!(
+      "",
+      " ; SCRIPT-SRC 'Self' 'none' ; img-src https: data:",
+      "default-src 'self'; img-src 'self' https://*.example.org:* http://a.example.org/path",
+      "script-src 'SHA384-abc+/=' 'nonce-a_b-c' 'strict-dynamic'; object-src 'none'",
+      "sandbox allow-scripts allow-forms; require-sri-for style script; report-to main",
+      "plugin-types application/pdf image/png; report-uri /a https://example.org/b",
+      "block-all-mixed-content; upgrade-insecure-requests; sandbox; img-src *"
+    );
+  }
+
+  #[test]
+  fn stricter_than_parser() {
+    let policies = [
+      "img-src unsafe-inline",
+      "img-src self",
+      "img-src 'self'; img-src *",
+      "scirpt-src 'self'",
+    ];
+    for policy in policies {
+      assert!(check(policy).is_err(), "{policy}");
+      assert!(CSP::parse(policy).is_ok(), "{policy}");
+    }
+  }
+}
@@ -0,0 +1,175 @@
+//! Runs the `csp` binary, checking the output and exit code of each command.
+
+use std::{
+  fs,
+  path::PathBuf,
+  process::{Command, Stdio},
+};
+
+/// Runs the binary with the arguments, returning its exit code and stdout.
+fn csp(arguments: &[&str]) -> (i32, String) {
+  let output = Command::new(env!("CARGO_BIN_EXE_csp"))
+    .args(arguments)
+    .stdin(Stdio::null())
+    .output()
+    .unwrap();
+  (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+/// A directory for the files of a test, removed when it is dropped.
+struct Files(PathBuf);
+
+impl Files {
+  fn new(name: &str) -> Self {
+    let path =
+      std::env::temp_dir().join(format!("csp-cli-{name}-{}", std::process::id()));
+    fs::create_dir_all(&path).unwrap();
+    Self(path)
+  }
+
+  fn write(&self, name: &str, content: &str) -> String {
+    let path = self.0.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_owned()
+  }
+}
+
+impl Drop for Files {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+#[test]
+fn usage() {
+  let (code, out) = csp(&["help"]);
+  assert_eq!(code, 0);
+  assert!(out.starts_with("Usage: csp <command>"));
+
+  assert_eq!(csp(&[]), (2, String::new()));
+  assert_eq!(csp(&["unknown"]), (2, String::new()));
+}
+
+#[test]
+fn parse() {
+  assert_eq!(
+    csp(&["parse", "default-src 'self'; img-src *"]),
+    (0, "default-src 'self'\nimg-src *\n".to_owned())
+  );
+  assert_eq!(
+    csp(&["parse", "--json", "img-src * data:"]),
+    (0, "{\"img-src\": [\"*\", \"data:\"]}\n".to_owned())
+  );
+  assert_eq!(csp(&["parse", "img-src", "*"]), (2, String::new()));
+  assert_eq!(csp(&["parse", "scirpt-src 'self'; img-src *"]), (1, String::new()));
+}
+
+#[test]
+fn lint() {
+  let (code, out) = csp(&["lint", "script-src * 'unsafe-inline'"]);
+  assert_eq!(code, 1);
+  assert!(!out.is_empty());
+  assert_eq!(csp(&["lint", "--strict", "default-src 'none'"]), (2, String::new()));
+  assert_eq!(csp(&["lint", "scirpt-src 'self'"]), (1, String::new()));
+}
+
+#[test]
+fn diff() {
+  assert_eq!(
+    csp(&["diff", "script-src 'self'", "script-src 'self' https:"]),
+    (
+      1,
+      "~ script-src\n  + https:\n\nLoosenings:\n  script-src: added https:\n".to_owned()
+    )
+  );
+  // Removing the nonce turns `'unsafe-inline'` back on.
+  let (code, _) =
+    csp(&["diff", "script-src 'nonce-a' 'unsafe-inline'", "script-src 'unsafe-inline'"]);
+  assert_eq!(code, 1);
+  assert_eq!(csp(&["diff", "script-src 'self'"]), (2, String::new()));
+}
+
+#[test]
+fn check_url() {
+  let policy = "img-src https://a.example.org";
+  assert_eq!(
+    csp(&["check-url", "--directive", "img-src", "https://a.example.org/x", policy]),
+    (0, "allowed\n".to_owned())
+  );
+  let (code, _) =
+    csp(&["check-url", "--directive", "img-src", "https://b.example.org/x", policy]);
+  assert_eq!(code, 1);
+  assert_eq!(csp(&["check-url", "https://a.example.org/x", policy]), (2, String::new()));
+}
+
+#[test]
+fn hash() {
+  let files = Files::new("hash");
+  let script = files.write("a.js", "alert(1)");
+  assert_eq!(
+    csp(&["hash", &script]),
+    (0, "'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='\n".to_owned())
+  );
+  assert_eq!(csp(&["hash"]), (2, String::new()));
+  assert_eq!(csp(&["hash", "--algorithm"]), (2, String::new()));
+}
+
+#[test]
+fn minimize() {
+  assert_eq!(
+    csp(&["minimize", "default-src 'self'; script-src 'self'"]),
+    (0, "default-src 'self'\n".to_owned())
+  );
+  assert_eq!(csp(&["minimize", "default-src", "'self'"]), (2, String::new()));
+}
+
+#[test]
+fn meta() {
+  assert_eq!(
+    csp(&["meta", "default-src 'self'; frame-ancestors 'none'"]),
+    (
+      0,
+      "<meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'self'\">\n"
+        .to_owned()
+    )
+  );
+  assert_eq!(csp(&["meta", "default-src", "'self'"]), (2, String::new()));
+}
+
+#[test]
+fn from_reports() {
+  let files = Files::new("reports");
+  let reports = files.write(
+    "reports.json",
+    r#"[{"csp-report": {"document-uri": "https://example.org/",
+      "violated-directive": "img-src", "effective-directive": "img-src",
+      "original-policy": "img-src 'self'", "blocked-uri": "https://img.example.org/a.png"}}]"#,
+  );
+  assert_eq!(
+    csp(&["from-reports", &reports]),
+    (
+      0,
+      "default-src 'none'; img-src https://img.example.org; base-uri 'none'; \
+       form-action 'none'\n"
+        .to_owned()
+    )
+  );
+  assert_eq!(csp(&["from-reports"]), (2, String::new()));
+}
+
+#[test]
+fn generate() {
+  let files = Files::new("generate");
+  files.write("site/index.html", "<img src=https://img.example.org/a.png>");
+  assert_eq!(
+    csp(&["generate", &files.0.join("site").to_string_lossy()]),
+    (
+      0,
+      "default-src 'none'; img-src https://img.example.org; base-uri 'none'; \
+       form-action 'none'\n"
+        .to_owned()
+    )
+  );
+  assert_eq!(csp(&["generate"]), (2, String::new()));
+}